@@ -61,6 +61,15 @@ pub struct Position {
 }
 
 impl Position {
+    /// Returns the `(line, character)` pair of an LSP [`Position`][lsp-position] for this
+    /// position.  LSP lines are 0-indexed, like ours, so the line is used as is; the character
+    /// is the UTF-16 code unit offset within the line.
+    ///
+    /// [lsp-position]: https://microsoft.github.io/language-server-protocol/specifications/specification-current/#position
+    pub fn to_lsp_position(&self) -> (usize, usize) {
+        (self.line, self.column.utf16_offset)
+    }
+
     /// Returns a tree-sitter [`Point`][Point] for this position.
     ///
     /// [Point]: https://docs.rs/tree-sitter/*/tree_sitter/struct.Point.html
@@ -105,6 +114,36 @@ impl PartialOrd<tree_sitter::Point> for Position {
     }
 }
 
+/// Converts to an LSP position, using the UTF-16 code unit offset as the character.  Values that
+/// do not fit into LSP's 32-bit fields are saturated.
+#[cfg(feature = "lsp-types")]
+impl From<&Position> for lsp_types::Position {
+    fn from(position: &Position) -> Self {
+        let saturate = |value: usize| value.min(u32::MAX as usize) as u32;
+        lsp_types::Position {
+            line: saturate(position.line),
+            character: saturate(position.column.utf16_offset),
+        }
+    }
+}
+
+/// Converts from an LSP position, which only determines the line and the UTF-16 code unit offset
+/// of the character.  The UTF-8 and grapheme offsets and the line ranges are left at their
+/// default of zero.
+#[cfg(feature = "lsp-types")]
+impl From<lsp_types::Position> for Position {
+    fn from(position: lsp_types::Position) -> Self {
+        Position {
+            line: position.line as usize,
+            column: Offset {
+                utf16_offset: position.character as usize,
+                ..Offset::default()
+            },
+            ..Position::default()
+        }
+    }
+}
+
 /// All of the position information that we have about a range of content in a source file
 #[repr(C)]
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
@@ -115,6 +154,29 @@ pub struct Span {
     pub end: Position,
 }
 
+/// Converts to an LSP range.  See the conversion of [`Position`][] for details.
+#[cfg(feature = "lsp-types")]
+impl From<&Span> for lsp_types::Range {
+    fn from(span: &Span) -> Self {
+        lsp_types::Range {
+            start: (&span.start).into(),
+            end: (&span.end).into(),
+        }
+    }
+}
+
+/// Converts from an LSP range.  Only the lines and UTF-16 offsets are populated; see the
+/// conversion of [`Position`][] for details.
+#[cfg(feature = "lsp-types")]
+impl From<lsp_types::Range> for Span {
+    fn from(range: lsp_types::Range) -> Self {
+        Span {
+            start: range.start.into(),
+            end: range.end.into(),
+        }
+    }
+}
+
 impl Span {
     pub fn contains(&self, position: &Position) -> bool {
         &self.start <= position && &self.end > position
@@ -124,6 +186,31 @@ impl Span {
     pub fn contains_point(&self, point: &tree_sitter::Point) -> bool {
         &self.start <= point && &self.end > point
     }
+
+    /// Returns the start and end `(line, character)` pairs of an LSP [`Range`][lsp-range] for this
+    /// span.  See [`Position::to_lsp_position`][] for how each position is converted.
+    ///
+    /// [lsp-range]: https://microsoft.github.io/language-server-protocol/specifications/specification-current/#range
+    pub fn to_lsp_range(&self) -> ((usize, usize), (usize, usize)) {
+        (self.start.to_lsp_position(), self.end.to_lsp_position())
+    }
+
+    /// Returns the smallest span that contains both this span and `other`.  The spans do not need
+    /// to be adjacent or overlapping.  If either span is the default span, which we use for
+    /// content that has no source location, the other span is returned unchanged.
+    pub fn enclosing(&self, other: &Span) -> Span {
+        let default = Span::default();
+        if *self == default {
+            return other.clone();
+        }
+        if *other == default {
+            return self.clone();
+        }
+        Span {
+            start: std::cmp::min(&self.start, &other.start).clone(),
+            end: std::cmp::max(&self.end, &other.end).clone(),
+        }
+    }
 }
 
 impl Ord for Span {
@@ -8,6 +8,8 @@
 use unicode_segmentation::UnicodeSegmentation as _;
 
 use lsp_positions::Offset;
+use lsp_positions::Span;
+use lsp_positions::SpanCalculator;
 
 fn check_offsets(line: &str) {
     let offsets = Offset::all_chars(line).collect::<Vec<_>>();
@@ -47,3 +49,80 @@ fn can_calculate_column_offsets() {
     check_offsets("print '❤️', b, '👨‍👨‍👧', c");
     check_offsets("print '✨✨✨', d");
 }
+
+#[test]
+fn can_combine_spans() {
+    let source = "let a = 1;\nlet bb = a + 2;\n";
+    let mut calculator = SpanCalculator::new(source);
+    let mut span = |line, line_offset, start, end| Span {
+        start: calculator.for_line_and_column(line, line_offset, start),
+        end: calculator.for_line_and_column(line, line_offset, end),
+    };
+    let a = span(0, 0, 4, 5);
+    let one = span(0, 0, 8, 9);
+    let bb = span(1, 11, 4, 6);
+
+    let a_one = a.enclosing(&one);
+    assert_eq!(a_one.start, a.start);
+    assert_eq!(a_one.end, one.end);
+    assert_eq!(one.enclosing(&a), a_one);
+
+    let a_bb = bb.enclosing(&a);
+    assert_eq!(a_bb.start, a.start);
+    assert_eq!(a_bb.end, bb.end);
+    assert_eq!(a_one.enclosing(&a), a_one);
+
+    assert_eq!(Span::default().enclosing(&bb), bb);
+    assert_eq!(bb.enclosing(&Span::default()), bb);
+}
+
+#[test]
+fn can_convert_spans_to_lsp_ranges() {
+    let source = "a = 1\nprint('✨👨‍👨‍👧', b)\n";
+    let line_offset = source.find('p').unwrap();
+    let line = &source[line_offset..];
+    let b = line.find('b').unwrap();
+    let mut calculator = SpanCalculator::new(source);
+    let span = Span {
+        start: calculator.for_line_and_column(1, line_offset, b),
+        end: calculator.for_line_and_column(1, line_offset, b + 1),
+    };
+    // '✨' is 3 bytes but 1 UTF-16 code unit, and the family emoji consists of three 4-byte
+    // characters that are 2 code units each, joined by two 3-byte zero-width joiners that are 1
+    // code unit each.  So `b` is at byte 7 + 3 + 18 + 3, but at code unit 7 + 1 + 8 + 3.
+    assert_eq!(b, 31);
+    assert_eq!(span.to_lsp_range(), ((1, 19), (1, 20)));
+    assert_eq!(span.start.to_lsp_position(), (1, 19));
+    assert_eq!(Span::default().to_lsp_range(), ((0, 0), (0, 0)));
+}
+
+#[cfg(feature = "lsp-types")]
+#[test]
+fn can_convert_between_spans_and_lsp_types_ranges() {
+    let source = "a = 1\nprint('✨', b)\n";
+    let line_offset = source.find('p').unwrap();
+    let b = source[line_offset..].find('b').unwrap();
+    let mut calculator = SpanCalculator::new(source);
+    let span = Span {
+        start: calculator.for_line_and_column(1, line_offset, b),
+        end: calculator.for_line_and_column(1, line_offset, b + 1),
+    };
+
+    // '✨' is 3 bytes but 1 UTF-16 code unit
+    assert_eq!(b, 13);
+    let range = lsp_types::Range::from(&span);
+    assert_eq!(
+        range,
+        lsp_types::Range::new(
+            lsp_types::Position::new(1, 11),
+            lsp_types::Position::new(1, 12)
+        )
+    );
+
+    let converted = Span::from(range);
+    assert_eq!(converted.to_lsp_range(), span.to_lsp_range());
+    assert_eq!(converted.start.column.utf16_offset, 11);
+    assert_eq!(converted.start.column.utf8_offset, 0);
+    assert_eq!(converted.start.column.grapheme_offset, 0);
+    assert_eq!(converted.start.containing_line, 0..0);
+}
@@ -284,6 +284,18 @@ struct sg_node_source_info {
     struct sg_source_info source_info;
 };
 
+// A key-value pair of strings that provides debug information about a node.
+struct sg_debug_entry {
+    sg_string_handle key;
+    sg_string_handle value;
+};
+
+// An array of the debug information entries of a node.
+struct sg_debug_entries {
+    const struct sg_debug_entry *entries;
+    size_t count;
+};
+
 // A handle to an element of a partial scope stack.  A zero handle represents a missing partial
 // scope stack.  A UINT32_MAX handle represents an empty partial scope stack.
 typedef uint32_t sg_partial_scope_stack_cell_handle;
@@ -650,6 +662,19 @@ void sg_stack_graph_add_source_infos(struct sg_stack_graph *graph,
                                      size_t count,
                                      const struct sg_node_source_info *infos);
 
+// Adds a new debug information entry to a node in the stack graph.  Any existing entries for the
+// node, including ones with the same key, are kept.
+void sg_stack_graph_add_node_debug_info(struct sg_stack_graph *graph,
+                                        sg_node_handle node,
+                                        sg_string_handle key,
+                                        sg_string_handle value);
+
+// Returns a reference to the array of debug information entries of a node, in the order in which
+// they were added.  The array is empty if the node has no debug information.  The resulting
+// array pointer is only valid until the next call to any function that mutates the stack graph.
+struct sg_debug_entries sg_stack_graph_node_debug_info(const struct sg_stack_graph *graph,
+                                                       sg_node_handle node);
+
 // Returns a reference to the array of partial symbol stack content in a partial path arena.  The
 // resulting array pointer is only valid until the next call to any function that mutates the path
 // arena.
@@ -50,6 +50,13 @@ use crate::utils::equals_option;
 
 /// A handle to an instance of type `T` that was allocated from an [`Arena`][].
 ///
+/// Handles are `Copy`, `Eq`, `Ord`, and `Hash` regardless of `T`, so they can be used directly
+/// as keys of a `HashMap` or `BTreeMap`.  Two handles are equal if they have the same index, and
+/// they are ordered by their index, which for handles allocated from an [`Arena`][] is the order
+/// in which the instances were added.  (This includes the symbols, strings, files, and nodes of a
+/// [`StackGraph`][crate::graph::StackGraph], unless a custom
+/// [`SymbolInterner`][crate::graph::SymbolInterner] assigns symbol handles differently.)
+///
 /// #### Safety
 ///
 /// Because of the type parameter `T`, the compiler can ensure that you don't use a handle for one
@@ -63,18 +70,23 @@ pub struct Handle<T> {
 }
 
 impl<T> Handle<T> {
-    pub(crate) fn new(index: NonZeroU32) -> Handle<T> {
+    /// Creates a handle from its index.  This is only needed when implementing your own storage
+    /// for instances, such as a custom [`SymbolInterner`][crate::graph::SymbolInterner]; handles
+    /// that do not refer to a stored instance will cause a panic when dereferenced.
+    pub fn new(index: NonZeroU32) -> Handle<T> {
         Handle {
             index,
             _phantom: PhantomData,
         }
     }
 
+    /// Returns the index of this handle.  Indexes are never zero.
     #[inline(always)]
     pub fn as_u32(self) -> u32 {
         self.index.get()
     }
 
+    /// Returns the index of this handle as a `usize`.
     #[inline(always)]
     pub fn as_usize(self) -> usize {
         self.index.get() as usize
@@ -173,6 +185,19 @@ impl<T> Drop for Arena<T> {
     }
 }
 
+impl<T: Clone> Clone for Arena<T> {
+    fn clone(&self) -> Self {
+        let mut items = Vec::with_capacity(self.items.len());
+        items.push(MaybeUninit::uninit());
+        items.extend(
+            self.items[1..]
+                .iter()
+                .map(|item| MaybeUninit::new(unsafe { &*item.as_ptr() }.clone())),
+        );
+        Arena { items }
+    }
+}
+
 impl<T> Arena<T> {
     /// Creates a new arena.
     pub fn new() -> Arena<T> {
@@ -185,7 +210,18 @@ impl<T> Arena<T> {
     /// the arena are invalid.
     #[inline(always)]
     pub fn clear(&mut self) {
-        self.items.truncate(1);
+        unsafe {
+            let items = std::mem::transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut self.items[1..])
+                as *mut [T];
+            self.items.set_len(1);
+            items.drop_in_place();
+        }
+    }
+
+    /// Returns the number of instances that this arena can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        // The arena reserves slot 0 for a dummy entry.
+        self.items.capacity() - 1
     }
 
     /// Adds a new instance to this arena, returning a stable handle to it.
@@ -217,6 +253,33 @@ impl<T> Arena<T> {
             .map(|index| Handle::new(unsafe { NonZeroU32::new_unchecked(index as u32) }))
     }
 
+    /// Drops all instances for which `keep` returns `false`, and moves the remaining instances so
+    /// that they are stored densely, preserving their relative order.  Returns the old and new
+    /// handle of each remaining instance.  After this, all previous handles into the arena are
+    /// invalid.
+    pub(crate) fn retain<F>(&mut self, mut keep: F) -> Vec<(Handle<T>, Handle<T>)>
+    where
+        F: FnMut(Handle<T>) -> bool,
+    {
+        let mut moved = Vec::new();
+        let mut next_index = 1;
+        for index in 1..self.items.len() {
+            let handle = Handle::new(unsafe { NonZeroU32::new_unchecked(index as u32) });
+            if !keep(handle) {
+                unsafe { self.items[index].as_mut_ptr().drop_in_place() };
+                continue;
+            }
+            // Every slot below `index` has either been dropped or moved from, so we can safely
+            // swap the instance into it.
+            self.items.swap(next_index, index);
+            let new_handle = Handle::new(unsafe { NonZeroU32::new_unchecked(next_index as u32) });
+            moved.push((handle, new_handle));
+            next_index += 1;
+        }
+        self.items.truncate(next_index);
+        moved
+    }
+
     /// Returns a pointer to this arena's storage.
     pub(crate) fn as_ptr(&self) -> *const T {
         self.items.as_ptr() as *const T
@@ -291,7 +354,12 @@ impl<H, T> SupplementalArena<H, T> {
     /// all previous handles into the arena are invalid.
     #[inline(always)]
     pub fn clear(&mut self) {
-        self.items.truncate(1);
+        unsafe {
+            let items = std::mem::transmute::<&mut [MaybeUninit<T>], &mut [T]>(&mut self.items[1..])
+                as *mut [T];
+            self.items.set_len(1);
+            items.drop_in_place();
+        }
     }
 
     /// Creates a new, empty supplemental arena, preallocating enough space to store supplemental
@@ -330,6 +398,12 @@ impl<H, T> SupplementalArena<H, T> {
         self.items.len()
     }
 
+    /// Returns the number of instances that this arena can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        // The arena reserves slot 0 for a dummy entry.
+        self.items.capacity() - 1
+    }
+
     /// Iterate over the items in this arena.
     pub(crate) fn iter(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
         self.items
@@ -451,6 +525,15 @@ impl<T> Default for HandleSet<T> {
     }
 }
 
+impl<T> Clone for HandleSet<T> {
+    fn clone(&self) -> HandleSet<T> {
+        HandleSet {
+            elements: self.elements.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Arena-allocated lists
 
@@ -471,6 +554,7 @@ pub struct List<T> {
 
 #[doc(hidden)]
 #[repr(C)]
+#[derive(Clone)]
 pub struct ListCell<T> {
     head: T,
     // The value of this handle will be EMPTY_LIST_HANDLE if this is the last element of the list.
@@ -624,6 +708,7 @@ pub struct ReversibleList<T> {
 
 #[repr(C)]
 #[doc(hidden)]
+#[derive(Clone)]
 pub struct ReversibleListCell<T> {
     head: T,
     tail: Handle<ReversibleListCell<T>>,
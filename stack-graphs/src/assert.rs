@@ -20,7 +20,9 @@ use crate::partial::PartialPaths;
 use crate::stitching::Database;
 use crate::stitching::DatabaseCandidates;
 use crate::stitching::ForwardPartialPathStitcher;
+use crate::stitching::ResourceExhausted;
 use crate::stitching::StitcherConfig;
+use crate::stitching::StitchingError;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
@@ -135,6 +137,7 @@ pub enum AssertionError {
         unexpected_symbols: Vec<Handle<Symbol>>,
     },
     Cancelled(CancellationError),
+    ResourceExhausted(ResourceExhausted),
 }
 
 impl From<CancellationError> for AssertionError {
@@ -143,6 +146,15 @@ impl From<CancellationError> for AssertionError {
     }
 }
 
+impl From<StitchingError<CancellationError>> for AssertionError {
+    fn from(value: StitchingError<CancellationError>) -> Self {
+        match value {
+            StitchingError::Candidates(err) => Self::Cancelled(err),
+            StitchingError::ResourceExhausted(err) => Self::ResourceExhausted(err),
+        }
+    }
+}
+
 impl Assertion {
     /// Run this assertion against the given graph, using the given paths object for path search.
     pub fn run(
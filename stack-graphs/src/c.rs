@@ -33,6 +33,7 @@ use crate::stitching::DatabaseCandidates;
 use crate::stitching::ForwardPartialPathStitcher;
 use crate::stitching::GraphEdgeCandidates;
 use crate::stitching::StitcherConfig;
+use crate::stitching::StitchingError;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
@@ -179,9 +180,16 @@ pub struct sg_symbols {
 #[no_mangle]
 pub extern "C" fn sg_stack_graph_symbols(graph: *const sg_stack_graph) -> sg_symbols {
     let graph = unsafe { &(*graph).inner };
-    sg_symbols {
-        symbols: graph.symbols.as_ptr() as *const sg_symbol,
-        count: graph.symbols.len(),
+    match graph.default_symbol_arena() {
+        Some(symbols) => sg_symbols {
+            symbols: symbols.as_ptr() as *const sg_symbol,
+            count: symbols.len(),
+        },
+        // Stack graphs created via the C API always use the default symbol interner.
+        None => sg_symbols {
+            symbols: std::ptr::null(),
+            count: 0,
+        },
     }
 }
 
@@ -741,6 +749,60 @@ pub extern "C" fn sg_stack_graph_add_source_infos(
     }
 }
 
+//-------------------------------------------------------------------------------------------------
+// Debug info
+
+/// A key-value pair of strings that provides debug information about a node.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct sg_debug_entry {
+    pub key: sg_string_handle,
+    pub value: sg_string_handle,
+}
+
+/// An array of the debug information entries of a node.
+#[repr(C)]
+pub struct sg_debug_entries {
+    pub entries: *const sg_debug_entry,
+    pub count: usize,
+}
+
+/// Adds a new debug information entry to a node in the stack graph.  Any existing entries for the
+/// node, including ones with the same key, are kept.
+#[no_mangle]
+pub extern "C" fn sg_stack_graph_add_node_debug_info(
+    graph: *mut sg_stack_graph,
+    node: sg_node_handle,
+    key: sg_string_handle,
+    value: sg_string_handle,
+) {
+    let graph = unsafe { &mut (*graph).inner };
+    let node: Handle<Node> = unsafe { std::mem::transmute(node) };
+    let key: Handle<InternedString> = unsafe { std::mem::transmute(key) };
+    let value: Handle<InternedString> = unsafe { std::mem::transmute(value) };
+    graph.node_debug_info_mut(node).add(key, value);
+}
+
+/// Returns a reference to the array of debug information entries of a node, in the order in which
+/// they were added.  The array is empty if the node has no debug information.  The resulting
+/// array pointer is only valid until the next call to any function that mutates the stack graph.
+#[no_mangle]
+pub extern "C" fn sg_stack_graph_node_debug_info(
+    graph: *const sg_stack_graph,
+    node: sg_node_handle,
+) -> sg_debug_entries {
+    let graph = unsafe { &(*graph).inner };
+    let node: Handle<Node> = unsafe { std::mem::transmute(node) };
+    let entries = match graph.node_debug_info(node) {
+        Some(info) => info.iter().as_slice(),
+        None => &[],
+    };
+    sg_debug_entries {
+        entries: entries.as_ptr() as *const sg_debug_entry,
+        count: entries.len(),
+    }
+}
+
 //-------------------------------------------------------------------------------------------------
 // Partial symbol stacks
 
@@ -1604,3 +1666,14 @@ impl<T> From<Result<T, CancellationError>> for sg_result {
         }
     }
 }
+
+// The C stitcher config has no maximum queue size, so stitching can only stop early when it is
+// cancelled.
+impl<T> From<Result<T, StitchingError<CancellationError>>> for sg_result {
+    fn from(result: Result<T, StitchingError<CancellationError>>) -> Self {
+        match result {
+            Ok(_) => Self::SG_RESULT_SUCCESS,
+            Err(_) => Self::SG_RESULT_CANCELLED,
+        }
+    }
+}
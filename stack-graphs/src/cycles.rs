@@ -320,6 +320,36 @@ impl<H> AppendingCycleDetector<H>
 where
     H: Clone,
 {
+    /// Returns the sequence of nodes that forms the shortest cycle at the end of the path, or an
+    /// empty vector if the path does not end at a node that it has visited before.  The sequence
+    /// starts and ends with the path's current end node, and lists the start node of each
+    /// appended element in between.
+    ///
+    /// This only looks at the nodes of the path.  Use [`is_cyclic`][Self::is_cyclic] to determine
+    /// whether the symbol and scope stacks make the cycle productive or not.
+    pub fn report<'a, A, Db>(&self, db: &'a Db, appendables: &Appendables<H>) -> Vec<Handle<Node>>
+    where
+        A: Appendable + 'a,
+        Db: ToAppendable<H, A>,
+    {
+        let mut appendages = self.appendages;
+        let end_node = match appendages.clone().pop_front(&appendables.elements) {
+            Some(appendage) => appendage.end_node(db, &appendables.interned),
+            None => return Vec::new(),
+        };
+
+        let mut cycle = vec![end_node];
+        while let Some(appendage) = appendages.pop_front(&appendables.elements) {
+            let start_node = appendage.start_node(db, &appendables.interned);
+            cycle.push(start_node);
+            if start_node == end_node {
+                cycle.reverse();
+                return cycle;
+            }
+        }
+        Vec::new()
+    }
+
     /// Tests if the path is cyclic. Returns a vector indicating the kind of cycles that were found.
     /// If appending or concatenating all fragments succeeds, this function will never raise and error.
     pub fn is_cyclic<'a, A, Db>(
@@ -0,0 +1,104 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2024, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Computes the structural differences between two stack graphs.
+//!
+//! Nodes in the two graphs are matched up by their [`NodeID`][]s, i.e., by their local IDs within
+//! files with the same name.  Only the structure of the graphs is compared: source and debug info
+//! are ignored.  A node that exists in both graphs, but whose content differs, is reported as
+//! removed from the old graph and added to the new one; the same holds for edges whose precedence
+//! or cost differs.
+//!
+//! The nodes and edges in a [`GraphDiff`][] are represented using the types from the
+//! [`serde`][crate::serde] module, so that a diff can be serialized.
+
+use std::collections::HashMap;
+
+use crate::graph::StackGraph;
+use crate::serde::Edge;
+use crate::serde::Node;
+use crate::serde::NodeID;
+
+/// The structural differences between two stack graphs.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct GraphDiff {
+    /// Nodes that only exist in the new graph, in the order of the new graph.
+    pub added_nodes: Vec<Node>,
+    /// Nodes that only exist in the old graph, in the order of the old graph.
+    pub removed_nodes: Vec<Node>,
+    /// Edges that only exist in the new graph, in the order of the new graph.
+    pub added_edges: Vec<Edge>,
+    /// Edges that only exist in the old graph, in the order of the old graph.
+    pub removed_edges: Vec<Edge>,
+}
+
+impl GraphDiff {
+    /// Returns whether the two graphs are structurally equal.
+    pub fn is_empty(&self) -> bool {
+        self.added_nodes.is_empty()
+            && self.removed_nodes.is_empty()
+            && self.added_edges.is_empty()
+            && self.removed_edges.is_empty()
+    }
+}
+
+impl StackGraph {
+    /// Computes the structural differences between this (old) graph and another (new) graph.
+    pub fn diff(&self, new: &StackGraph) -> GraphDiff {
+        let (old_nodes, old_edges) = structure(self);
+        let (new_nodes, new_edges) = structure(new);
+        GraphDiff {
+            added_nodes: missing_nodes(&new_nodes, &old_nodes),
+            removed_nodes: missing_nodes(&old_nodes, &new_nodes),
+            added_edges: missing_edges(&new_edges, &old_edges),
+            removed_edges: missing_edges(&old_edges, &new_edges),
+        }
+    }
+}
+
+/// Returns the nodes and edges of a graph, without any source or debug info.
+fn structure(graph: &StackGraph) -> (Vec<Node>, Vec<Edge>) {
+    let serialized = graph.to_serializable();
+    let mut nodes = serialized.nodes.data;
+    for node in &mut nodes {
+        node.clear_info();
+    }
+    let mut edges = serialized.edges.data;
+    for edge in &mut edges {
+        edge.debug_info = None;
+    }
+    (nodes, edges)
+}
+
+/// Returns the nodes from `nodes` that do not have an equal counterpart in `others`.
+fn missing_nodes(nodes: &[Node], others: &[Node]) -> Vec<Node> {
+    let others = others
+        .iter()
+        .map(|node| (node.id(), node))
+        .collect::<HashMap<_, _>>();
+    nodes
+        .iter()
+        .filter(|node| others.get(node.id()) != Some(node))
+        .cloned()
+        .collect()
+}
+
+/// Returns the edges from `edges` that do not have an equal counterpart in `others`.
+fn missing_edges(edges: &[Edge], others: &[Edge]) -> Vec<Edge> {
+    let others = others
+        .iter()
+        .map(|edge| ((&edge.source, &edge.sink), (edge.precedence, edge.cost)))
+        .collect::<HashMap<(&NodeID, &NodeID), _>>();
+    edges
+        .iter()
+        .filter(|edge| {
+            others.get(&(&edge.source, &edge.sink)) != Some(&(edge.precedence, edge.cost))
+        })
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,110 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2024, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Exports stack graphs in the [GraphViz DOT][] format.
+//!
+//! [GraphViz DOT]: https://graphviz.org/doc/info/lang.html
+
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use crate::arena::Handle;
+use crate::graph::Edge;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+
+impl StackGraph {
+    /// Writes this stack graph in the [GraphViz DOT][] format.  Nodes are labeled with their kind
+    /// and symbol, and edges with their precedence and, if it is not zero, their cost.
+    ///
+    /// If `file` is given, only the nodes of that file are written, together with all edges that
+    /// start or end at them.  Nodes outside of the file that are connected by those edges, such
+    /// as the singleton _root_ node, are included with a dashed outline.  If `file` is `None`, the
+    /// whole graph is written, including the singleton _root_ and _jump to scope_ nodes.
+    ///
+    /// [GraphViz DOT]: https://graphviz.org/doc/info/lang.html
+    pub fn to_dot(
+        &self,
+        file: Option<Handle<File>>,
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let in_scope = |node: Handle<Node>| match file {
+            Some(file) => self[node].file() == Some(file),
+            None => true,
+        };
+        let edges = self
+            .all_edges()
+            .filter(|edge| in_scope(edge.source) || in_scope(edge.sink))
+            .collect::<Vec<_>>();
+        let external_nodes = edges
+            .iter()
+            .flat_map(|edge| [edge.source, edge.sink])
+            .filter(|node| !in_scope(*node))
+            .collect::<BTreeSet<_>>();
+
+        writeln!(writer, "digraph stack_graph {{")?;
+        writeln!(writer, "  node [shape=box];")?;
+        for node in self.iter_nodes().filter(|node| in_scope(*node)) {
+            self.write_dot_node(node, "", writer)?;
+        }
+        for node in external_nodes {
+            self.write_dot_node(node, ", style=dashed", writer)?;
+        }
+        for Edge {
+            source,
+            sink,
+            precedence,
+            cost,
+        } in edges
+        {
+            write!(
+                writer,
+                "  n{} -> n{} [label=\"{}",
+                source.as_u32(),
+                sink.as_u32(),
+                precedence,
+            )?;
+            if cost != 0 {
+                write!(writer, ", cost {}", cost)?;
+            }
+            writeln!(writer, "\"];")?;
+        }
+        writeln!(writer, "}}")
+    }
+
+    fn write_dot_node(
+        &self,
+        node: Handle<Node>,
+        attributes: &str,
+        writer: &mut dyn Write,
+    ) -> std::io::Result<()> {
+        let label = escape_dot_string(&node.display(self).to_string());
+        writeln!(
+            writer,
+            "  n{} [label=\"{}\"{}];",
+            node.as_u32(),
+            label,
+            attributes,
+        )
+    }
+}
+
+/// Escapes a string so that it can be used inside of a quoted DOT string.
+fn escape_dot_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
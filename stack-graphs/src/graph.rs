@@ -50,19 +50,31 @@
 //! [`File`]: struct.File.html
 
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt::Display;
+use std::hash::Hash;
 use std::num::NonZeroU32;
 use std::ops::Index;
 use std::ops::IndexMut;
+use std::ops::Range;
 
 use controlled_option::ControlledOption;
 use either::Either;
 use fxhash::FxHashMap;
+use itertools::Itertools;
+#[cfg(feature = "digests")]
+use sha2::Digest;
+#[cfg(feature = "digests")]
+use sha2::Sha256;
 use smallvec::SmallVec;
 
 use crate::arena::Arena;
 use crate::arena::Handle;
+use crate::arena::HandleSet;
 use crate::arena::SupplementalArena;
+use crate::CancellationError;
+use crate::CancellationFlag;
+use crate::NoCancellation;
 
 //-------------------------------------------------------------------------------------------------
 // String content
@@ -98,11 +110,23 @@ impl InternedStringArena {
         }
     }
 
+    // Removes all strings, keeping the current buffer's capacity.  The content of all strings
+    // that were previously added becomes invalid.
+    fn clear(&mut self) {
+        self.current_buffer.clear();
+        self.full_buffers.clear();
+    }
+
     // Adds a new string.  This does not check whether we've already stored a string with the same
-    // content; that is handled down below in `StackGraph::add_symbol` and `add_file`.
+    // content; that is handled down below in `DefaultSymbolInterner::intern` and `add_file`.
     fn add(&mut self, value: &str) -> InternedStringContent {
+        self.add_bytes(value.as_bytes())
+    }
+
+    // Adds new raw byte content.  Callers must not use `as_str` or `as_hash_key` on the result
+    // unless the content is known to be valid UTF-8.
+    fn add_bytes(&mut self, value: &[u8]) -> InternedStringContent {
         // Is there enough room in current_buffer to hold this string?
-        let value = value.as_bytes();
         let len = value.len();
         let capacity = self.current_buffer.capacity();
         let remaining_capacity = capacity - self.current_buffer.len();
@@ -138,6 +162,12 @@ impl InternedStringContent {
         }
     }
 
+    /// Returns the raw content of this string.  Unlike `as_str`, this is also valid for content
+    /// that was added via `InternedStringArena::add_bytes`.
+    fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.start, self.len) }
+    }
+
     // Returns a supposedly 'static reference to the string's data.  The string data isn't really
     // static, but we are careful only to use this as a key in the HashMap that StackGraph uses to
     // track whether we've stored a particular symbol already.  That HashMap lives alongside the
@@ -148,6 +178,12 @@ impl InternedStringContent {
         let bytes = std::slice::from_raw_parts(self.start, self.len);
         std::str::from_utf8_unchecked(bytes)
     }
+
+    // Returns a supposedly 'static reference to the string's raw data.  The same caveats as for
+    // `as_hash_key` apply.
+    unsafe fn as_bytes_hash_key(&self) -> &'static [u8] {
+        std::slice::from_raw_parts(self.start, self.len)
+    }
 }
 
 unsafe impl Send for InternedStringContent {}
@@ -183,27 +219,317 @@ impl PartialEq<&str> for Symbol {
     }
 }
 
+/// A backing store for the symbols of a stack graph.
+///
+/// By default, a stack graph stores its symbols in a [`DefaultSymbolInterner`][].  If you need
+/// different space or time tradeoffs, you can implement this trait yourself and create a stack
+/// graph that uses it via [`StackGraph::with_symbol_interner`][].
+///
+/// Implementations must ensure that interning the same content twice yields the same handle, and
+/// must hand out handles densely: the _n_th distinct symbol must be assigned the handle whose
+/// [`as_u32`][Handle::as_u32] value is _n_.
+pub trait SymbolInterner: Send + Sync {
+    /// Returns the handle of the symbol with the given content, adding it if it does not exist
+    /// yet.
+    fn intern(&mut self, symbol: &str) -> Handle<Symbol>;
+
+    /// Returns the content of a symbol.  Panics if the handle was not returned by this interner.
+    fn resolve(&self, handle: Handle<Symbol>) -> &str;
+
+    /// Returns the handle of the symbol with the given content, if it exists.
+    fn lookup(&self, symbol: &str) -> Option<Handle<Symbol>>;
+
+    /// Returns the number of symbols in this interner.
+    fn len(&self) -> usize;
+
+    /// Returns whether this interner contains any symbols.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Removes all symbols from this interner.  Afterwards, handles must be assigned starting at
+    /// 1 again.
+    fn clear(&mut self);
+}
+
+/// The default [`SymbolInterner`][], which copies the content of each symbol into a small number
+/// of large, preallocated buffers.
+pub struct DefaultSymbolInterner {
+    strings: InternedStringArena,
+    symbols: Arena<Symbol>,
+    handles: FxHashMap<&'static str, Handle<Symbol>>,
+}
+
+impl DefaultSymbolInterner {
+    /// Creates a new, initially empty interner.
+    pub fn new() -> DefaultSymbolInterner {
+        DefaultSymbolInterner {
+            strings: InternedStringArena::new(),
+            symbols: Arena::new(),
+            handles: FxHashMap::default(),
+        }
+    }
+
+    // Adds a symbol without deduplicating it by content.  The symbol cannot be found via `lookup`.
+    fn add_unique(&mut self, symbol: &str) -> Handle<Symbol> {
+        let content = self.strings.add(symbol);
+        self.symbols.add(Symbol { content })
+    }
+}
+
+impl Default for DefaultSymbolInterner {
+    fn default() -> DefaultSymbolInterner {
+        DefaultSymbolInterner::new()
+    }
+}
+
+impl SymbolInterner for DefaultSymbolInterner {
+    #[inline(always)]
+    fn intern(&mut self, symbol: &str) -> Handle<Symbol> {
+        if let Some(handle) = self.handles.get(symbol) {
+            return *handle;
+        }
+
+        let interned = self.strings.add(symbol);
+        let hash_key = unsafe { interned.as_hash_key() };
+        let handle = self.symbols.add(Symbol { content: interned });
+        self.handles.insert(hash_key, handle);
+        handle
+    }
+
+    #[inline(always)]
+    fn resolve(&self, handle: Handle<Symbol>) -> &str {
+        self.symbols.get(handle).as_str()
+    }
+
+    #[inline(always)]
+    fn lookup(&self, symbol: &str) -> Option<Handle<Symbol>> {
+        self.handles.get(symbol).copied()
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        // The arena reserves slot 0 for a dummy entry.
+        self.symbols.len() - 1
+    }
+
+    fn clear(&mut self) {
+        self.handles.clear();
+        self.symbols.clear();
+        self.strings.clear();
+    }
+}
+
+/// The symbol storage of a stack graph.  We dispatch statically to the default interner, so that
+/// stack graphs that don't use a custom interner don't pay for dynamic dispatch.
+enum SymbolStorage {
+    Default(DefaultSymbolInterner),
+    Custom(Box<dyn SymbolInterner>),
+}
+
+impl SymbolStorage {
+    #[inline(always)]
+    fn intern(&mut self, symbol: &str) -> Handle<Symbol> {
+        match self {
+            SymbolStorage::Default(interner) => interner.intern(symbol),
+            SymbolStorage::Custom(interner) => interner.intern(symbol),
+        }
+    }
+
+    #[inline(always)]
+    fn resolve(&self, handle: Handle<Symbol>) -> &str {
+        match self {
+            SymbolStorage::Default(interner) => interner.resolve(handle),
+            SymbolStorage::Custom(interner) => interner.resolve(handle),
+        }
+    }
+
+    #[inline(always)]
+    fn lookup(&self, symbol: &str) -> Option<Handle<Symbol>> {
+        match self {
+            SymbolStorage::Default(interner) => interner.lookup(symbol),
+            SymbolStorage::Custom(interner) => interner.lookup(symbol),
+        }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+        match self {
+            SymbolStorage::Default(interner) => interner.len(),
+            SymbolStorage::Custom(interner) => interner.len(),
+        }
+    }
+
+    fn clear(&mut self) {
+        match self {
+            SymbolStorage::Default(interner) => interner.clear(),
+            SymbolStorage::Custom(interner) => interner.clear(),
+        }
+    }
+
+    // Adds a symbol that must not be found by looking up its content.  Custom interners have no
+    // way to do that, so for them the symbol is interned as usual.
+    fn add_unique(&mut self, symbol: &str) -> Handle<Symbol> {
+        match self {
+            SymbolStorage::Default(interner) => interner.add_unique(symbol),
+            SymbolStorage::Custom(interner) => interner.intern(symbol),
+        }
+    }
+}
+
+/// Maps the normalized form of each symbol to the handle of the first symbol with that form.
+struct SymbolNormalizer {
+    normalize: Box<dyn Fn(&str) -> String + Send + Sync>,
+    handles: FxHashMap<String, Handle<Symbol>>,
+}
+
 impl StackGraph {
+    /// Creates a new, initially empty stack graph that stores its symbols in a custom
+    /// [`SymbolInterner`][].
+    pub fn with_symbol_interner<I: SymbolInterner + 'static>(interner: I) -> StackGraph {
+        StackGraph {
+            symbols: SymbolStorage::Custom(Box::new(interner)),
+            ..StackGraph::default()
+        }
+    }
+
+    /// Creates a new, initially empty stack graph, in which
+    /// [`new_node_id_for_content`][Self::new_node_id_for_content] derives node IDs from the
+    /// content of the nodes, instead of the order in which they are created.  Use this if you need
+    /// identical inputs to produce identical graphs, for instance for reproducible builds.
+    ///
+    /// This is only available when the `digests` feature is enabled.
+    #[cfg(feature = "digests")]
+    pub fn with_deterministic_ids() -> StackGraph {
+        StackGraph {
+            deterministic_ids: true,
+            ..StackGraph::default()
+        }
+    }
+
+    /// Creates a new, initially empty stack graph, in which symbols are matched by their
+    /// normalized form, as computed by `normalize`.  This is useful for languages with
+    /// case-insensitive identifiers, where you can use `|s| s.to_lowercase()` so that `Foo` and
+    /// `foo` resolve to each other.
+    ///
+    /// All symbols with the same normalized form share a single handle, whose content is the
+    /// spelling of the first of those symbols that was added to the graph.  Because symbols are
+    /// compared by handle, this affects all symbol comparisons, including those performed during
+    /// path stitching, and lookups via [`get_symbol`][Self::get_symbol].
+    pub fn with_symbol_normalizer<F>(normalize: F) -> StackGraph
+    where
+        F: Fn(&str) -> String + Send + Sync + 'static,
+    {
+        StackGraph {
+            symbol_normalizer: Some(SymbolNormalizer {
+                normalize: Box::new(normalize),
+                handles: FxHashMap::default(),
+            }),
+            ..StackGraph::default()
+        }
+    }
+
     /// Adds a symbol to the stack graph, ensuring that there's only ever one copy of a particular
     /// symbol stored in the graph.
     pub fn add_symbol<S: AsRef<str> + ?Sized>(&mut self, symbol: &S) -> Handle<Symbol> {
         let symbol = symbol.as_ref();
-        if let Some(handle) = self.symbol_handles.get(symbol) {
+        let normalizer = match &mut self.symbol_normalizer {
+            Some(normalizer) => normalizer,
+            None => return self.symbols.intern(symbol),
+        };
+        let normalized = (normalizer.normalize)(symbol);
+        if let Some(handle) = normalizer.handles.get(&normalized) {
+            return *handle;
+        }
+        let handle = self.symbols.intern(symbol);
+        normalizer.handles.insert(normalized, handle);
+        handle
+    }
+
+    /// Adds a symbol to the stack graph from its raw bytes, ensuring that there's only ever one
+    /// copy of a particular symbol stored in the graph.
+    ///
+    /// If the bytes are valid UTF-8, this is equivalent to [`add_symbol`][Self::add_symbol], and
+    /// the resulting symbol can also be found by its string content.  Otherwise, the raw bytes are
+    /// kept as the symbol's content, and are available via [`symbol_bytes`][Self::symbol_bytes];
+    /// the symbol's string representation, used when indexing into the graph or displaying the
+    /// symbol, is a lossy UTF-8 conversion of the bytes.  (For graphs with a custom
+    /// [`SymbolInterner`][], the lossy conversion is interned as a regular symbol, and therefore
+    /// shares its handle with any symbol that has the same string content.)
+    pub fn add_symbol_bytes(&mut self, bytes: &[u8]) -> Handle<Symbol> {
+        if let Ok(symbol) = std::str::from_utf8(bytes) {
+            return self.add_symbol(symbol);
+        }
+        if let Some(handle) = self.symbol_byte_handles.get(bytes) {
             return *handle;
         }
 
-        let interned = self.interned_strings.add(symbol);
-        let hash_key = unsafe { interned.as_hash_key() };
-        let handle = self.symbols.add(Symbol { content: interned });
-        self.symbol_handles.insert(hash_key, handle);
+        let raw = self.interned_strings.add_bytes(bytes);
+        let hash_key = unsafe { raw.as_bytes_hash_key() };
+        let handle = self.symbols.add_unique(&String::from_utf8_lossy(bytes));
+        self.symbol_byte_handles.insert(hash_key, handle);
+        self.symbol_bytes.insert(handle, raw);
         handle
     }
 
-    /// Returns an iterator over all of the handles of all of the symbols in this stack graph.
-    /// (Note that because we're only returning _handles_, this iterator does not retain a
-    /// reference to the `StackGraph`.)
+    /// Returns the raw bytes of a symbol.  For symbols added via
+    /// [`add_symbol_bytes`][Self::add_symbol_bytes] that are not valid UTF-8, these are the
+    /// original bytes; for all other symbols, this is the UTF-8 encoding of the symbol's content.
+    pub fn symbol_bytes(&self, handle: Handle<Symbol>) -> &[u8] {
+        match self.symbol_bytes.get(&handle) {
+            Some(raw) => raw.as_bytes(),
+            None => self[handle].as_bytes(),
+        }
+    }
+
+    /// Returns the symbol with a particular content, if it exists.  For graphs created with
+    /// [`with_symbol_normalizer`][Self::with_symbol_normalizer], this returns the symbol with the
+    /// same normalized form.
+    pub fn get_symbol<S: AsRef<str> + ?Sized>(&self, symbol: &S) -> Option<Handle<Symbol>> {
+        match &self.symbol_normalizer {
+            Some(normalizer) => normalizer
+                .handles
+                .get(&(normalizer.normalize)(symbol.as_ref()))
+                .copied(),
+            None => self.symbols.lookup(symbol.as_ref()),
+        }
+    }
+
+    /// Returns how many _push_ and _pop_ nodes refer to each symbol, for the symbols that are
+    /// referred to at all.  The result is sorted by descending count, and symbols with the same
+    /// count are ordered by handle.
+    pub fn symbol_usage_counts(&self) -> Vec<(Handle<Symbol>, usize)> {
+        let mut counts = FxHashMap::default();
+        for node in self.iter_nodes() {
+            if let Some(symbol) = self[node].symbol() {
+                *counts.entry(symbol).or_insert(0) += 1;
+            }
+        }
+        let mut counts = counts.into_iter().collect::<Vec<_>>();
+        counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        counts
+    }
+
+    /// Returns an iterator over all of the handles of all of the symbols in this stack graph, in
+    /// the order in which the symbols were first added.  (Note that because we're only returning
+    /// _handles_, this iterator does not retain a reference to the `StackGraph`.)
     pub fn iter_symbols(&self) -> impl Iterator<Item = Handle<Symbol>> {
-        self.symbols.iter_handles()
+        (1..=self.symbols.len() as u32)
+            .map(|index| Handle::new(unsafe { NonZeroU32::new_unchecked(index) }))
+    }
+
+    /// Returns the number of distinct symbols in this stack graph.
+    pub fn symbols_len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns the symbol arena of this stack graph, if it uses the default
+    /// [`SymbolInterner`][].
+    pub(crate) fn default_symbol_arena(&self) -> Option<&Arena<Symbol>> {
+        match &self.symbols {
+            SymbolStorage::Default(interner) => Some(&interner.symbols),
+            SymbolStorage::Custom(_) => None,
+        }
     }
 }
 
@@ -211,7 +537,7 @@ impl Index<Handle<Symbol>> for StackGraph {
     type Output = str;
     #[inline(always)]
     fn index(&self, handle: Handle<Symbol>) -> &str {
-        self.symbols.get(handle).as_str()
+        self.symbols.resolve(handle)
     }
 }
 
@@ -273,9 +599,27 @@ impl StackGraph {
         handle
     }
 
+    /// Returns the interned string with a particular content, if it exists.  Unlike
+    /// [`add_string`][Self::add_string], this never adds the string to the graph.
+    pub fn get_string<S: AsRef<str> + ?Sized>(&self, string: &S) -> Option<Handle<InternedString>> {
+        self.string_handles.get(string.as_ref()).copied()
+    }
+
+    /// Adds a batch of interned strings to the stack graph, returning their handles in the same
+    /// order.  As with [`add_string`][Self::add_string], there's only ever one copy of a
+    /// particular string stored in the graph.
+    pub fn add_strings<'a, I>(&mut self, strings: I) -> Vec<Handle<InternedString>>
+    where
+        I: IntoIterator<Item = &'a str>,
+    {
+        let strings = strings.into_iter();
+        self.string_handles.reserve(strings.size_hint().0);
+        strings.map(|string| self.add_string(string)).collect()
+    }
+
     /// Returns an iterator over all of the handles of all of the interned strings in this stack
-    /// graph. (Note that because we're only returning _handles_, this iterator does not retain a
-    /// reference to the `StackGraph`.)
+    /// graph, in the order in which the strings were first added.  (Note that because we're only
+    /// returning _handles_, this iterator does not retain a reference to the `StackGraph`.)
     pub fn iter_strings(&self) -> impl Iterator<Item = Handle<InternedString>> {
         self.strings.iter_handles()
     }
@@ -365,11 +709,64 @@ impl StackGraph {
         let name = name.as_ref();
         self.file_handles.get(name).copied()
     }
+
+    /// Renames a file.  The file keeps its handle, and all of its nodes keep their handles and
+    /// local IDs.  Returns an error if a different file with the new name already exists.
+    pub fn rename_file<S: AsRef<str> + ?Sized>(
+        &mut self,
+        file: Handle<File>,
+        new_name: &S,
+    ) -> Result<(), FileError> {
+        let new_name = new_name.as_ref();
+        match self.file_handles.get(new_name) {
+            Some(existing) if *existing == file => return Ok(()),
+            Some(existing) => return Err(FileError::FileAlreadyExists(*existing)),
+            None => {}
+        }
+
+        self.file_handles.remove(self.files.get(file).name());
+        let interned = self.interned_strings.add(new_name);
+        let hash_key = unsafe { interned.as_hash_key() };
+        self.files.get_mut(file).name = interned;
+        self.file_handles.insert(hash_key, file);
+        Ok(())
+    }
+
+    /// Sets the language of a file, such as the name of the grammar that its nodes were created
+    /// from.  This lets you tell apart the files of different languages in a graph that contains
+    /// several of them.
+    pub fn set_file_language(&mut self, file: Handle<File>, language: &str) {
+        let language = self.add_string(language);
+        self.file_languages[file] = language.into();
+    }
+
+    /// Returns the language of a file, if it has been set with
+    /// [`set_file_language`][Self::set_file_language].
+    pub fn file_language(&self, file: Handle<File>) -> Option<&str> {
+        let language = self.file_languages.get(file)?.into_option()?;
+        Some(&self[language])
+    }
+
+    /// Removes the language of a file.
+    #[cfg(feature = "serde")]
+    pub(crate) fn clear_file_language(&mut self, file: Handle<File>) {
+        if let Some(language) = self.file_languages.get_mut(file) {
+            *language = ControlledOption::none();
+        }
+    }
+}
+
+/// An error that can occur when modifying the files of a stack graph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FileError {
+    /// A different file with the requested name already exists.
+    FileAlreadyExists(Handle<File>),
 }
 
 impl StackGraph {
-    /// Returns an iterator of all of the nodes that belong to a particular file.  Note that this
-    /// does **_not_** include the singleton _root_ or _jump to scope_ nodes.
+    /// Returns an iterator of all of the nodes that belong to a particular file, in ascending order
+    /// of their local IDs.  Note that this does **_not_** include the singleton _root_ or _jump to
+    /// scope_ nodes.
     pub fn nodes_for_file(&self, file: Handle<File>) -> impl Iterator<Item = Handle<Node>> + '_ {
         self.node_id_handles.nodes_for_file(file)
     }
@@ -380,6 +777,11 @@ impl StackGraph {
     pub fn iter_files(&self) -> impl Iterator<Item = Handle<File>> + '_ {
         self.files.iter_handles()
     }
+
+    /// Returns the number of files in this stack graph.
+    pub fn file_count(&self) -> usize {
+        self.files.len() - 1
+    }
 }
 
 impl Display for File {
@@ -553,6 +955,8 @@ impl Node {
         }
     }
 
+    /// Returns whether this node is a definition: a _pop symbol_ or _pop scoped symbol_ node
+    /// that is flagged as a definition.
     #[inline(always)]
     pub fn is_definition(&self) -> bool {
         match self {
@@ -562,6 +966,8 @@ impl Node {
         }
     }
 
+    /// Returns whether this node is a reference: a _push symbol_ or _push scoped symbol_ node
+    /// that is flagged as a reference.
     #[inline(always)]
     pub fn is_reference(&self) -> bool {
         match self {
@@ -586,6 +992,25 @@ impl Node {
         self.is_definition() || self.is_exported_scope() || self.is_reference() || self.is_root()
     }
 
+    /// Returns whether this node is a _push scoped symbol_ or _pop scoped symbol_ node.
+    #[inline(always)]
+    pub fn is_scoped(&self) -> bool {
+        matches!(self, Node::PushScopedSymbol(_) | Node::PopScopedSymbol(_))
+    }
+
+    /// Returns whether this node has a symbol, i.e., whether [`symbol`][Self::symbol] returns
+    /// `Some`.
+    #[inline(always)]
+    pub fn has_symbol(&self) -> bool {
+        matches!(
+            self,
+            Node::PushScopedSymbol(_)
+                | Node::PushSymbol(_)
+                | Node::PopScopedSymbol(_)
+                | Node::PopSymbol(_)
+        )
+    }
+
     /// Returns this node's symbol, if it has one.  (_Pop symbol_, _pop scoped symbol_, _push
     /// symbol_, and _push scoped symbol_ nodes have symbols.)
     pub fn symbol(&self) -> Option<Handle<Symbol>> {
@@ -663,10 +1088,107 @@ impl StackGraph {
         self.node_id_handles.unused_id(file)
     }
 
+    /// Returns an unused [`NodeID`][] for the given file, for a node with the given kind, symbol,
+    /// and span.
+    ///
+    /// For graphs created with [`with_deterministic_ids`][Self::with_deterministic_ids], the local
+    /// ID is derived from a SHA-256 digest of the node's content, so that building a graph from
+    /// the same input always produces the same IDs, regardless of the order in which the nodes
+    /// are created, and with any version of this library or of Rust.  If the hashed ID is already
+    /// in use, the next unused local ID is returned.  (This means that the IDs of nodes with
+    /// identical content still depend on their relative order.)
+    ///
+    /// For all other graphs, this is equivalent to [`new_node_id`][Self::new_node_id].
+    ///
+    /// This is only available when the `digests` feature is enabled.
+    #[cfg(feature = "digests")]
+    pub fn new_node_id_for_content(
+        &mut self,
+        file: Handle<File>,
+        kind: &str,
+        symbol: Option<&str>,
+        span: &lsp_positions::Span,
+    ) -> NodeID {
+        if !self.deterministic_ids {
+            return self.new_node_id(file);
+        }
+        let mut hasher = Sha256::new();
+        hasher.update((kind.len() as u64).to_le_bytes());
+        hasher.update(kind);
+        match symbol {
+            Some(symbol) => {
+                hasher.update([1u8]);
+                hasher.update((symbol.len() as u64).to_le_bytes());
+                hasher.update(symbol);
+            }
+            None => hasher.update([0u8]),
+        }
+        for position in &[&span.start, &span.end] {
+            hasher.update((position.line as u64).to_le_bytes());
+            hasher.update((position.column.utf8_offset as u64).to_le_bytes());
+        }
+        let digest = hasher.finalize();
+        let mut local_id = u32::from_le_bytes([digest[0], digest[1], digest[2], digest[3]]);
+        loop {
+            let id = NodeID::new_in_file(file, local_id);
+            if self.node_id_handles.try_handle_for_id(id).is_none() {
+                return id;
+            }
+            local_id = local_id.wrapping_add(1);
+        }
+    }
+
     /// Returns an iterator of all of the nodes in the graph.  (Note that because we're only
     /// returning _handles_, this iterator does not retain a reference to the `StackGraph`.)
+    ///
+    /// Nodes are always returned in ascending handle order, which is the order in which they were
+    /// added to the graph, starting with the singleton _root_ and _jump to scope_ nodes.  Graphs
+    /// that are built by adding the same nodes in the same order (for instance, by loading the
+    /// same serialized graph) therefore iterate their nodes in the same order.
+    ///
+    /// Nodes that were removed with [`remove_file`][Self::remove_file] are not included.
     pub fn iter_nodes(&self) -> impl Iterator<Item = Handle<Node>> {
-        self.nodes.iter_handles()
+        if self.removed_node_count == 0 {
+            Either::Left(self.nodes.iter_handles())
+        } else {
+            let removed_nodes = self.removed_nodes.clone();
+            Either::Right(
+                self.nodes
+                    .iter_handles()
+                    .filter(move |node| !removed_nodes.contains(*node)),
+            )
+        }
+    }
+
+    /// Returns an iterator of the handles of all of the nodes in this graph that satisfy a
+    /// predicate, in the same order as [`iter_nodes`][Self::iter_nodes].
+    pub fn iter_nodes_matching<'a, F>(&'a self, f: F) -> impl Iterator<Item = Handle<Node>> + 'a
+    where
+        F: Fn(&Node) -> bool + 'a,
+    {
+        self.iter_nodes().filter(move |node| f(&self[*node]))
+    }
+
+    /// Returns an iterator of the handles of all of the reference nodes in this graph.
+    pub fn iter_references(&self) -> impl Iterator<Item = Handle<Node>> + '_ {
+        self.iter_nodes_matching(Node::is_reference)
+    }
+
+    /// Returns an iterator of the handles of all of the definition nodes in this graph.
+    pub fn iter_definitions(&self) -> impl Iterator<Item = Handle<Node>> + '_ {
+        self.iter_nodes_matching(Node::is_definition)
+    }
+
+    /// Returns an iterator of the handles of all of the (internal or exported) scope nodes in
+    /// this graph.
+    pub fn iter_scopes(&self) -> impl Iterator<Item = Handle<Node>> + '_ {
+        self.iter_nodes_matching(|node| matches!(node, Node::Scope(_)))
+    }
+
+    /// Returns the number of nodes in the graph.  Like [`iter_nodes`][Self::iter_nodes], this
+    /// includes the singleton _root_ and _jump to scope_ nodes.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - 1 - self.removed_node_count
     }
 
     /// Returns the handle to the node with a particular ID, if it exists.
@@ -682,8 +1204,40 @@ impl StackGraph {
         }
     }
 
+    /// Returns a numeric identifier for a node that is stable outside of this graph instance.  The
+    /// identifier combines the index of the node's file (in the upper 32 bits) with the node's
+    /// local ID (in the lower 32 bits).  The singleton _root_ and _jump to scope_ nodes, which
+    /// don't belong to a file, use a file index of zero.
+    ///
+    /// Unlike node handles, which depend on the order in which nodes were added, global IDs only
+    /// depend on the order in which files were added.  That order is preserved when serializing
+    /// and loading a graph, so global IDs remain valid across a serialization round trip.
+    pub fn global_id(&self, node: Handle<Node>) -> u64 {
+        let id = self[node].id();
+        let file = id.file().map(|file| file.as_u32()).unwrap_or(0);
+        (u64::from(file) << 32) | u64::from(id.local_id())
+    }
+
+    /// Returns the handle to the node with a particular [global ID][Self::global_id], if it
+    /// exists.
+    pub fn node_for_global_id(&self, global_id: u64) -> Option<Handle<Node>> {
+        let file = (global_id >> 32) as u32;
+        let local_id = global_id as u32;
+        let id = match NonZeroU32::new(file) {
+            Some(file) if (file.get() as usize) < self.files.len() => {
+                NodeID::new_in_file(Handle::new(file), local_id)
+            }
+            Some(_) => return None,
+            None => NodeID {
+                file: ControlledOption::none(),
+                local_id,
+            },
+        };
+        self.node_for_id(id)
+    }
+
     pub(crate) fn add_node(&mut self, id: NodeID, node: Node) -> Option<Handle<Node>> {
-        if let Some(_) = self.node_id_handles.handle_for_id(id) {
+        if let Some(_) = self.node_id_handles.try_handle_for_id(id) {
             return None;
         }
         let handle = self.nodes.add(node);
@@ -692,7 +1246,7 @@ impl StackGraph {
     }
 
     pub(crate) fn get_or_create_node(&mut self, id: NodeID, node: Node) -> Handle<Node> {
-        if let Some(handle) = self.node_id_handles.handle_for_id(id) {
+        if let Some(handle) = self.node_id_handles.try_handle_for_id(id) {
             return handle;
         }
         let handle = self.nodes.add(node);
@@ -941,6 +1495,35 @@ impl StackGraph {
         };
         self.add_node(id, node.into())
     }
+
+    /// Returns a _pop symbol_ node in the given file with the given symbol and definition flag,
+    /// creating a new node with a fresh local ID if there isn't one yet.  Only nodes that were
+    /// created by this method are reused; nodes added with
+    /// [`add_pop_symbol_node`][Self::add_pop_symbol_node] are never deduplicated.
+    pub fn get_or_create_pop_symbol_node(
+        &mut self,
+        file: Handle<File>,
+        symbol: Handle<Symbol>,
+        is_definition: bool,
+    ) -> Handle<Node> {
+        let key = SymbolNodeKey::Pop {
+            symbol,
+            is_definition,
+        };
+        if let Some(handle) = self
+            .symbol_node_index
+            .get(file)
+            .and_then(|index| index.get(&key))
+        {
+            return *handle;
+        }
+        let id = self.new_node_id(file);
+        let handle = self
+            .add_pop_symbol_node(id, symbol, is_definition)
+            .expect("Fresh node ID is already in use");
+        self.symbol_node_index[file].insert(key, handle);
+        handle
+    }
 }
 
 impl PopSymbolNode {
@@ -1089,6 +1672,35 @@ impl StackGraph {
         };
         self.add_node(id, node.into())
     }
+
+    /// Returns a _push symbol_ node in the given file with the given symbol and reference flag,
+    /// creating a new node with a fresh local ID if there isn't one yet.  Only nodes that were
+    /// created by this method are reused; nodes added with
+    /// [`add_push_symbol_node`][Self::add_push_symbol_node] are never deduplicated.
+    pub fn get_or_create_push_symbol_node(
+        &mut self,
+        file: Handle<File>,
+        symbol: Handle<Symbol>,
+        is_reference: bool,
+    ) -> Handle<Node> {
+        let key = SymbolNodeKey::Push {
+            symbol,
+            is_reference,
+        };
+        if let Some(handle) = self
+            .symbol_node_index
+            .get(file)
+            .and_then(|index| index.get(&key))
+        {
+            return *handle;
+        }
+        let id = self.new_node_id(file);
+        let handle = self
+            .add_push_symbol_node(id, symbol, is_reference)
+            .expect("Fresh node ID is already in use");
+        self.symbol_node_index[file].insert(key, handle);
+        handle
+    }
 }
 
 impl PushSymbolNode {
@@ -1158,56 +1770,119 @@ impl Display for RootNode {
     }
 }
 
+// Local IDs are usually allocated densely, and we store their handles in a vector indexed by
+// local ID.  IDs that are far beyond the end of that vector, such as the ones created by
+// `new_node_id_for_content`, are stored in a map instead, so that they don't blow up the vector.
+const MAX_DENSE_LOCAL_ID_GAP: usize = 1024;
+
 struct NodeIDHandles {
     files: SupplementalArena<File, Vec<Option<Handle<Node>>>>,
+    sparse_files: SupplementalArena<File, FxHashMap<u32, Handle<Node>>>,
 }
 
 impl NodeIDHandles {
     fn new() -> NodeIDHandles {
         NodeIDHandles {
             files: SupplementalArena::new(),
+            sparse_files: SupplementalArena::new(),
         }
     }
 
     fn try_handle_for_id(&self, node_id: NodeID) -> Option<Handle<Node>> {
-        let file_entry = self.files.get(node_id.file().unwrap())?;
+        let file = node_id.file().unwrap();
         let node_index = node_id.local_id as usize;
-        if node_index >= file_entry.len() {
-            return None;
+        if let Some(handle) = self
+            .files
+            .get(file)
+            .and_then(|file_entry| file_entry.get(node_index))
+            .copied()
+            .flatten()
+        {
+            return Some(handle);
         }
-        file_entry[node_index]
+        self.sparse_files
+            .get(file)
+            .and_then(|sparse_entry| sparse_entry.get(&node_id.local_id))
+            .copied()
     }
 
-    fn handle_for_id(&mut self, node_id: NodeID) -> Option<Handle<Node>> {
-        let file_entry = &mut self.files[node_id.file().unwrap()];
+    fn set_handle_for_id(&mut self, node_id: NodeID, handle: Handle<Node>) {
+        let file = node_id.file().unwrap();
+        let file_entry = &mut self.files[file];
         let node_index = node_id.local_id as usize;
+        if node_index >= file_entry.len() + MAX_DENSE_LOCAL_ID_GAP {
+            self.sparse_files[file].insert(node_id.local_id, handle);
+            return;
+        }
         if node_index >= file_entry.len() {
             file_entry.resize(node_index + 1, None);
         }
-        file_entry[node_index]
-    }
-
-    fn set_handle_for_id(&mut self, node_id: NodeID, handle: Handle<Node>) {
-        let file_entry = &mut self.files[node_id.file().unwrap()];
-        let node_index = node_id.local_id as usize;
         file_entry[node_index] = Some(handle);
     }
 
     fn unused_id(&mut self, file: Handle<File>) -> NodeID {
-        let local_id = self
+        let mut local_id = self
             .files
             .get(file)
             .map(|file_entry| file_entry.len() as u32)
             .unwrap_or(0);
+        if let Some(sparse_entry) = self.sparse_files.get(file) {
+            while sparse_entry.contains_key(&local_id) {
+                local_id += 1;
+            }
+        }
         NodeID::new_in_file(file, local_id)
     }
 
+    /// Returns the handles of the nodes of a file, in ascending order of their local IDs.
     fn nodes_for_file(&self, file: Handle<File>) -> impl Iterator<Item = Handle<Node>> + '_ {
-        let file_entry = match self.files.get(file) {
-            Some(file_entry) => file_entry,
-            None => return Either::Left(std::iter::empty()),
-        };
-        Either::Right(file_entry.iter().filter_map(|entry| *entry))
+        let dense_handles = self.files.get(file).into_iter().flat_map(|file_entry| {
+            file_entry
+                .iter()
+                .enumerate()
+                .filter_map(|(local_id, handle)| Some((local_id as u32, (*handle)?)))
+        });
+        let mut sparse_handles = self
+            .sparse_files
+            .get(file)
+            .into_iter()
+            .flat_map(|sparse_entry| {
+                sparse_entry
+                    .iter()
+                    .map(|(local_id, handle)| (*local_id, *handle))
+            })
+            .collect::<Vec<_>>();
+        sparse_handles.sort_unstable_by_key(|(local_id, _)| *local_id);
+        dense_handles
+            .merge_by(sparse_handles, |dense, sparse| dense.0 <= sparse.0)
+            .map(|(_, handle)| handle)
+    }
+
+    fn handles_for_file_mut(
+        &mut self,
+        file: Handle<File>,
+    ) -> impl Iterator<Item = &mut Handle<Node>> + '_ {
+        let dense_handles = self.files.get_mut(file).into_iter().flatten().flatten();
+        let sparse_handles = self
+            .sparse_files
+            .get_mut(file)
+            .into_iter()
+            .flat_map(|sparse_entry| sparse_entry.values_mut());
+        dense_handles.chain(sparse_handles)
+    }
+
+    fn clear(&mut self) {
+        self.files.clear();
+        self.sparse_files.clear();
+    }
+
+    fn clear_file(&mut self, file: Handle<File>) {
+        if let Some(file_entry) = self.files.get_mut(file) {
+            file_entry.clear();
+        }
+        if let Some(sparse_entry) = self.sparse_files.get_mut(file) {
+            sparse_entry.clear();
+        }
     }
 }
 
@@ -1240,6 +1915,31 @@ impl StackGraph {
         };
         self.add_node(id, node.into())
     }
+
+    /// Sets whether a _scope_ node is exported, so that a scope can be turned from an internal
+    /// one into an exported one (or vice versa) after it has been created.  Returns an error,
+    /// leaving the graph unchanged, if the node is not a _scope_ node.  Partial paths that were
+    /// already computed for the node's file are not updated.
+    pub fn set_exported_scope(
+        &mut self,
+        node: Handle<Node>,
+        is_exported: bool,
+    ) -> Result<(), NodeError> {
+        match &mut self[node] {
+            Node::Scope(scope) => {
+                scope.is_exported = is_exported;
+                Ok(())
+            }
+            _ => Err(NodeError::NotAScope(node)),
+        }
+    }
+}
+
+/// An error that can occur when modifying a node of a stack graph.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NodeError {
+    /// The node is not a _scope_ node.
+    NotAScope(Handle<Node>),
 }
 
 impl ScopeNode {
@@ -1285,56 +1985,279 @@ impl<'a> Display for DisplayScopeNode<'a> {
 /// in a stack graph.  (Though not all sequence of edges is a well-formed name binding: the nodes
 /// that you encounter along the path must also satisfy all of the rules for maintaining correct
 /// symbol and scope stacks.)
+///
+/// Besides its precedence, each edge has a cost, which is zero unless set with
+/// [`StackGraph::set_edge_cost`][].  Costs do not influence which paths are found, but they can be
+/// used to [order complete paths][crate::stitching::StitcherConfig::with_sort_by_cost], for
+/// instance to prefer nearby imports over distant ones.  Costs are integers, so that edges can
+/// still be compared and hashed exactly.  To express fractional costs, such as an import distance
+/// of 1.5, scale them to a finer unit, for instance thousandths.
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct Edge {
     pub source: Handle<Node>,
     pub sink: Handle<Node>,
     pub precedence: i32,
+    pub cost: u32,
+}
+
+/// An error that can occur when adding a batch of edges with [`StackGraph::add_edges`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EdgeError {
+    /// The edge at the given index of the batch refers to a node that does not exist.
+    InvalidNode {
+        index: usize,
+        source: Handle<Node>,
+        sink: Handle<Node>,
+    },
 }
 
 pub(crate) struct OutgoingEdge {
     sink: Handle<Node>,
     precedence: i32,
+    cost: u32,
 }
 
 impl StackGraph {
-    /// Adds a new edge to the stack graph.
+    /// Adds a new edge to the stack graph.  The graph contains at most one edge between any source
+    /// and sink node, so if there already is an edge between these nodes, nothing is added, even
+    /// if its precedence is different.
     pub fn add_edge(&mut self, source: Handle<Node>, sink: Handle<Node>, precedence: i32) {
+        self.add_edge_checked(source, sink, precedence);
+    }
+
+    /// Adds a new edge to the stack graph, like [`add_edge`][Self::add_edge], and returns whether
+    /// it was added.  Returns `false`, without changing the graph, if there already is an edge
+    /// between these nodes.  Use [`set_edge_precedence`][Self::set_edge_precedence] to change the
+    /// precedence of an existing edge.
+    ///
+    /// Because duplicate edges are never added, there is no need to deduplicate the edges of a
+    /// graph after it has been built.
+    pub fn add_edge_checked(
+        &mut self,
+        source: Handle<Node>,
+        sink: Handle<Node>,
+        precedence: i32,
+    ) -> bool {
         let edges = &mut self.outgoing_edges[source];
-        if let Err(index) = edges.binary_search_by_key(&sink, |o| o.sink) {
-            edges.insert(index, OutgoingEdge { sink, precedence });
-            self.incoming_edges[sink] += Degree::One;
+        match edges.binary_search_by_key(&sink, |o| o.sink) {
+            Ok(_) => false,
+            Err(index) => {
+                edges.insert(
+                    index,
+                    OutgoingEdge {
+                        sink,
+                        precedence,
+                        cost: 0,
+                    },
+                );
+                self.incoming_edges[sink] += Degree::One;
+                self.edge_count += 1;
+                true
+            }
+        }
+    }
+
+    /// Adds a batch of edges, given as `(source, sink, precedence)` tuples, to the stack graph.
+    /// This is equivalent to calling [`add_edge`][Self::add_edge] for each edge in order, but
+    /// grows the list of outgoing edges of each source node only once.
+    ///
+    /// If any edge refers to a node that does not exist in the graph, returns an error that
+    /// identifies the first such edge, and does not add any of the edges.
+    pub fn add_edges<I>(&mut self, edges: I) -> Result<(), EdgeError>
+    where
+        I: IntoIterator<Item = (Handle<Node>, Handle<Node>, i32)>,
+    {
+        let mut edges = edges.into_iter().collect::<Vec<_>>();
+        if let Some((index, (source, sink, _))) = edges
+            .iter()
+            .enumerate()
+            .find(|(_, (source, sink, _))| !self.is_live_node(*source) || !self.is_live_node(*sink))
+        {
+            return Err(EdgeError::InvalidNode {
+                index,
+                source: *source,
+                sink: *sink,
+            });
+        }
+        // The sort is stable, so if the batch contains several edges between the same nodes, the
+        // first one is added, just like when adding them one at a time.
+        edges.sort_by_key(|(source, _, _)| *source);
+        let mut start = 0;
+        while start < edges.len() {
+            let source = edges[start].0;
+            let end = start
+                + edges[start..]
+                    .iter()
+                    .take_while(|(other, _, _)| *other == source)
+                    .count();
+            self.outgoing_edges[source].reserve(end - start);
+            for (source, sink, precedence) in &edges[start..end] {
+                self.add_edge(*source, *sink, *precedence);
+            }
+            start = end;
         }
+        Ok(())
     }
 
-    /// Sets edge precedence of the given edge.
+    /// Returns whether a node handle refers to a node in this graph that has not been removed.
+    pub(crate) fn is_live_node(&self, node: Handle<Node>) -> bool {
+        node.as_usize() < self.nodes.len() && !self.removed_nodes.contains(node)
+    }
+
+    /// Sets edge precedence of the given edge.  Returns whether the edge exists.  (Since
+    /// [`add_edge`][Self::add_edge] never adds more than one edge between the same source and sink
+    /// nodes, there is at most one edge to update.)
     pub fn set_edge_precedence(
         &mut self,
         source: Handle<Node>,
         sink: Handle<Node>,
         precedence: i32,
-    ) {
-        let edges = &mut self.outgoing_edges[source];
-        if let Ok(index) = edges.binary_search_by_key(&sink, |o| o.sink) {
-            edges[index].precedence = precedence;
+    ) -> bool {
+        let edges = match self.outgoing_edges.get_mut(source) {
+            Some(edges) => edges,
+            None => return false,
+        };
+        match edges.binary_search_by_key(&sink, |o| o.sink) {
+            Ok(index) => {
+                edges[index].precedence = precedence;
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// Sets the cost of the given edge.  Returns whether the edge exists.  Edges have a cost of
+    /// zero when they are added.
+    pub fn set_edge_cost(&mut self, source: Handle<Node>, sink: Handle<Node>, cost: u32) -> bool {
+        let edges = match self.outgoing_edges.get_mut(source) {
+            Some(edges) => edges,
+            None => return false,
+        };
+        match edges.binary_search_by_key(&sink, |o| o.sink) {
+            Ok(index) => {
+                edges[index].cost = cost;
+                true
+            }
+            Err(_) => false,
         }
     }
 
-    /// Returns an iterator of all of the edges that begin at a particular source node.
+    /// Returns an iterator of all of the edges that begin at a particular source node.  The edges
+    /// are ordered by the handle of their sink node, regardless of their precedence; use
+    /// [`outgoing_edges_by_precedence`][Self::outgoing_edges_by_precedence] if you need them
+    /// ordered by precedence.
     pub fn outgoing_edges(&self, source: Handle<Node>) -> impl Iterator<Item = Edge> + '_ {
         match self.outgoing_edges.get(source) {
             Some(edges) => Either::Right(edges.iter().map(move |o| Edge {
                 source,
                 sink: o.sink,
                 precedence: o.precedence,
+                cost: o.cost,
             })),
             None => Either::Left(std::iter::empty()),
         }
     }
 
+    /// Returns an iterator of all of the edges that begin at a particular source node, ordered by
+    /// descending precedence.  Edges with the same precedence are returned in the same order as
+    /// [`outgoing_edges`][Self::outgoing_edges] returns them.
+    pub fn outgoing_edges_by_precedence(&self, source: Handle<Node>) -> impl Iterator<Item = Edge> {
+        let mut edges = self.outgoing_edges(source).collect::<SmallVec<[_; 4]>>();
+        edges.sort_by_key(|edge| std::cmp::Reverse(edge.precedence));
+        edges.into_iter()
+    }
+
+    /// Returns an iterator of all of the edges in this graph.  Edges are grouped by their source
+    /// node, in the order that [`iter_nodes`][Self::iter_nodes] returns them, and the edges of each
+    /// source node are ordered as in [`outgoing_edges`][Self::outgoing_edges].
+    pub fn all_edges(&self) -> impl Iterator<Item = Edge> + '_ {
+        self.iter_nodes()
+            .flat_map(move |source| self.outgoing_edges(source))
+    }
+
+    /// Returns the edges that cross the boundary of a file, i.e., the edges that connect a node
+    /// of the file with a node outside of it, such as the singleton _root_ and _jump to scope_
+    /// nodes or a node of another file.  Edges in both directions are included, ordered as in
+    /// [`all_edges`][Self::all_edges]; edges between two nodes of the file are not.
+    pub fn file_boundary_edges(&self, file: Handle<File>) -> Vec<Edge> {
+        let in_file = |node: Handle<Node>| self[node].file() == Some(file);
+        self.all_edges()
+            .filter(|edge| in_file(edge.source) != in_file(edge.sink))
+            .collect()
+    }
+
     /// Returns the number of edges that end at a particular sink node.
     pub fn incoming_edge_degree(&self, sink: Handle<Node>) -> Degree {
-        self.incoming_edges[sink]
+        self.incoming_edges
+            .get(sink)
+            .cloned()
+            .unwrap_or(Degree::Zero)
+    }
+
+    /// Returns all nodes that have no incoming and no outgoing edges, other than the singleton
+    /// _root_ and _jump to scope_ nodes.  Such nodes cannot take part in any path, so they usually
+    /// indicate a problem in the code that generated the graph.  The nodes are returned in handle
+    /// order.
+    pub fn unreferenced_nodes(&self) -> Vec<Handle<Node>> {
+        self.iter_nodes()
+            .filter(|node| !self[*node].is_root() && !self[*node].is_jump_to())
+            .filter(|node| self.incoming_edge_degree(*node) == Degree::Zero)
+            .filter(|node| self.outgoing_edges(*node).next().is_none())
+            .collect()
+    }
+
+    /// Returns the scope nodes that are closest to a node, where the distance is the number of
+    /// edges in the shortest path from the scope to the node.  Only the structure of the graph is
+    /// considered: the path does not have to be a valid name binding, and symbol and scope stacks
+    /// are ignored.  The node itself is not included, even if it is a scope.
+    ///
+    /// Several scopes can be equally close, so all of them are returned, ordered by handle.
+    /// Returns an empty vector if no scope node reaches the node.
+    ///
+    /// Since the graph only stores the outgoing edges of each node, this iterates over all edges
+    /// of the graph to find the incoming ones.
+    pub fn enclosing_scopes(&self, node: Handle<Node>) -> Vec<Handle<Node>> {
+        let mut predecessors = HashMap::<_, Vec<_>>::new();
+        for edge in self.all_edges() {
+            predecessors.entry(edge.sink).or_default().push(edge.source);
+        }
+        let mut visited = HashSet::new();
+        visited.insert(node);
+        let mut frontier = vec![node];
+        while !frontier.is_empty() {
+            let mut next = Vec::new();
+            for sink in frontier {
+                for source in predecessors.get(&sink).into_iter().flatten() {
+                    if visited.insert(*source) {
+                        next.push(*source);
+                    }
+                }
+            }
+            let mut scopes = next
+                .iter()
+                .copied()
+                .filter(|node| matches!(self[*node], Node::Scope(_)))
+                .collect::<Vec<_>>();
+            if !scopes.is_empty() {
+                scopes.sort();
+                return scopes;
+            }
+            frontier = next;
+        }
+        Vec::new()
+    }
+
+    /// Returns the scope node that is closest to a node, as defined by
+    /// [`enclosing_scopes`][Self::enclosing_scopes].  If several scopes are equally close, the
+    /// one with the lowest handle is returned; use `enclosing_scopes` to get all of them.
+    pub fn enclosing_scope(&self, node: Handle<Node>) -> Option<Handle<Node>> {
+        self.enclosing_scopes(node).into_iter().next()
+    }
+
+    /// Returns the number of edges in the graph.  This is a running count that is maintained as
+    /// edges are added, so it does not require iterating over the graph.
+    pub fn edge_count(&self) -> usize {
+        self.edge_count
     }
 }
 
@@ -1367,17 +2290,79 @@ impl StackGraph {
         self.source_info.get(node)
     }
 
+    /// Returns the nodes of a file whose source span contains a position, given as a 0-indexed
+    /// line and a UTF-8 byte offset within that line.  The nodes are ordered innermost first, by
+    /// the number of lines that their spans cover, and then by the number of columns.
+    ///
+    /// This checks the span of every node in the file, so it takes time linear in the size of the
+    /// file.
+    pub fn nodes_at_position(
+        &self,
+        file: Handle<File>,
+        line: usize,
+        utf8_column: usize,
+    ) -> Vec<Handle<Node>> {
+        let contains = |span: &lsp_positions::Span| {
+            let position = (line, utf8_column);
+            (span.start.line, span.start.column.utf8_offset) <= position
+                && position < (span.end.line, span.end.column.utf8_offset)
+        };
+        let mut nodes = self
+            .nodes_for_file(file)
+            .filter_map(|node| {
+                let span = &self.source_info(node)?.span;
+                if !contains(span) {
+                    return None;
+                }
+                let size = (
+                    span.end.line - span.start.line,
+                    span.end.column.utf8_offset as isize - span.start.column.utf8_offset as isize,
+                );
+                Some((size, node))
+            })
+            .collect::<Vec<_>>();
+        nodes.sort();
+        nodes.into_iter().map(|(_, node)| node).collect()
+    }
+
     /// Returns a mutable reference to the information about the source code that a stack graph
     /// node represents.
     pub fn source_info_mut(&mut self, node: Handle<Node>) -> &mut SourceInfo {
         &mut self.source_info[node]
     }
+
+    /// Returns the UTF-8 byte range, within its whole source file, of the source code that a stack
+    /// graph node represents, which can be used to slice the file's content.
+    ///
+    /// Each end of the range is computed from the start of its [containing
+    /// line][lsp_positions::Position::containing_line] and its UTF-8 column offset, so both must
+    /// be populated.  Returns `None` if the node has no span, or if an end of the span is on a
+    /// line other than the first but its containing line starts at byte 0, which means that the
+    /// span was created without the byte offsets of its lines.
+    pub fn node_byte_range(&self, node: Handle<Node>) -> Option<Range<usize>> {
+        let span = &self.source_info(node)?.span;
+        if *span == lsp_positions::Span::default() {
+            return None;
+        }
+        let offset = |position: &lsp_positions::Position| {
+            if position.line > 0 && position.containing_line.start == 0 {
+                return None;
+            }
+            Some(position.containing_line.start + position.column.utf8_offset)
+        };
+        let start = offset(&span.start)?;
+        let end = offset(&span.end)?;
+        if end < start {
+            return None;
+        }
+        Some(start..end)
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
 // Debug info
 
-/// Contains debug info about a stack graph node as key-value pairs of strings.
+/// Contains debug info about a stack graph node, edge, or file as key-value pairs of strings.
 #[derive(Default)]
 pub struct DebugInfo {
     entries: Vec<DebugEntry>,
@@ -1388,12 +2373,42 @@ impl DebugInfo {
         self.entries.push(DebugEntry { key, value });
     }
 
+    /// Sets the value of the first entry with the given key, or adds a new entry if there is no
+    /// entry with that key yet.
+    pub fn set(&mut self, key: Handle<InternedString>, value: Handle<InternedString>) {
+        match self.entries.iter_mut().find(|entry| entry.key == key) {
+            Some(entry) => entry.value = value,
+            None => self.add(key, value),
+        }
+    }
+
+    /// Returns the value of the first entry with the given key.
+    pub fn get(&self, key: Handle<InternedString>) -> Option<Handle<InternedString>> {
+        self.entries
+            .iter()
+            .find(|entry| entry.key == key)
+            .map(|entry| entry.value)
+    }
+
+    /// Adds several key-value pairs, in order.
+    pub fn add_many<I>(&mut self, pairs: I)
+    where
+        I: IntoIterator<Item = (Handle<InternedString>, Handle<InternedString>)>,
+    {
+        self.entries.extend(
+            pairs
+                .into_iter()
+                .map(|(key, value)| DebugEntry { key, value }),
+        );
+    }
+
     pub fn iter(&self) -> std::slice::Iter<DebugEntry> {
         self.entries.iter()
     }
 }
 
 /// A debug entry consisting of a string key-value air of strings.
+#[repr(C)]
 pub struct DebugEntry {
     pub key: Handle<InternedString>,
     pub value: Handle<InternedString>,
@@ -1436,11 +2451,45 @@ impl StackGraph {
         };
         &mut es[idx].1
     }
+
+    /// Returns debug information about the file, such as the language or a hash of the source
+    /// that the file's nodes were created from.
+    pub fn file_debug_info(&self, file: Handle<File>) -> Option<&DebugInfo> {
+        self.file_debug_info.get(file)
+    }
+
+    /// Returns a mutable reference to the debug info about the file.
+    pub fn file_debug_info_mut(&mut self, file: Handle<File>) -> &mut DebugInfo {
+        &mut self.file_debug_info[file]
+    }
+
+    /// Sets the value of a debug info entry of the file, replacing any previous value for the
+    /// same key.
+    pub fn set_file_debug_info(&mut self, file: Handle<File>, key: &str, value: &str) {
+        let key = self.add_string(key);
+        let value = self.add_string(value);
+        self.file_debug_info[file].set(key, value);
+    }
 }
 
 //-------------------------------------------------------------------------------------------------
 // Stack graphs
 
+/// Identifies the symbol nodes of a file that can be shared by
+/// [`get_or_create_push_symbol_node`][StackGraph::get_or_create_push_symbol_node] and
+/// [`get_or_create_pop_symbol_node`][StackGraph::get_or_create_pop_symbol_node].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum SymbolNodeKey {
+    Push {
+        symbol: Handle<Symbol>,
+        is_reference: bool,
+    },
+    Pop {
+        symbol: Handle<Symbol>,
+        is_definition: bool,
+    },
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 #[repr(u8)]
 pub enum Degree {
@@ -1474,19 +2523,29 @@ impl std::ops::AddAssign for Degree {
 /// Contains all of the nodes and edges that make up a stack graph.
 pub struct StackGraph {
     interned_strings: InternedStringArena,
-    pub(crate) symbols: Arena<Symbol>,
-    symbol_handles: FxHashMap<&'static str, Handle<Symbol>>,
+    symbols: SymbolStorage,
+    symbol_normalizer: Option<SymbolNormalizer>,
+    symbol_byte_handles: FxHashMap<&'static [u8], Handle<Symbol>>,
+    symbol_bytes: FxHashMap<Handle<Symbol>, InternedStringContent>,
     pub(crate) strings: Arena<InternedString>,
     string_handles: FxHashMap<&'static str, Handle<InternedString>>,
     pub(crate) files: Arena<File>,
     file_handles: FxHashMap<&'static str, Handle<File>>,
+    file_languages: SupplementalArena<File, ControlledOption<Handle<InternedString>>>,
     pub(crate) nodes: Arena<Node>,
     pub(crate) source_info: SupplementalArena<Node, SourceInfo>,
     node_id_handles: NodeIDHandles,
+    #[cfg(feature = "digests")]
+    deterministic_ids: bool,
+    removed_nodes: HandleSet<Node>,
+    removed_node_count: usize,
+    symbol_node_index: SupplementalArena<File, FxHashMap<SymbolNodeKey, Handle<Node>>>,
     outgoing_edges: SupplementalArena<Node, SmallVec<[OutgoingEdge; 4]>>,
     incoming_edges: SupplementalArena<Node, Degree>,
+    edge_count: usize,
     pub(crate) node_debug_info: SupplementalArena<Node, DebugInfo>,
     pub(crate) edge_debug_info: SupplementalArena<Node, SmallVec<[(Handle<Node>, DebugInfo); 4]>>,
+    file_debug_info: SupplementalArena<File, DebugInfo>,
 }
 
 impl StackGraph {
@@ -1495,18 +2554,80 @@ impl StackGraph {
         StackGraph::default()
     }
 
-    /// Copies the given stack graph into this stack graph. Panics if any of the files
-    /// in the other stack graph are already defined in the current one.
+    /// Removes all files, nodes, edges, symbols, and strings, and all source and debug info, from
+    /// this stack graph, so that it behaves like a newly created graph.  The capacity that the
+    /// graph has allocated so far is kept, which makes this cheaper than creating a new graph if
+    /// you process many files one after the other.  The graph keeps its configuration, such as a
+    /// custom [`SymbolInterner`][] or symbol normalizer.
+    ///
+    /// All handles into this graph are invalid after this call.
+    pub fn clear(&mut self) {
+        self.symbol_byte_handles.clear();
+        self.symbol_bytes.clear();
+        if let Some(normalizer) = &mut self.symbol_normalizer {
+            normalizer.handles.clear();
+        }
+        self.symbols.clear();
+        self.string_handles.clear();
+        self.strings.clear();
+        self.file_handles.clear();
+        self.file_languages.clear();
+        self.files.clear();
+        self.interned_strings.clear();
+        self.nodes.clear();
+        self.nodes.add(RootNode::new().into());
+        self.nodes.add(JumpToNode::new().into());
+        self.source_info.clear();
+        self.node_id_handles.clear();
+        self.removed_nodes.clear();
+        self.removed_node_count = 0;
+        self.symbol_node_index.clear();
+        self.outgoing_edges.clear();
+        self.incoming_edges.clear();
+        self.edge_count = 0;
+        self.node_debug_info.clear();
+        self.edge_debug_info.clear();
+        self.file_debug_info.clear();
+    }
+
+    /// Returns the number of nodes that this stack graph can hold without reallocating, including
+    /// the singleton _root_ and _jump to scope_ nodes.
+    pub fn node_capacity(&self) -> usize {
+        self.nodes.capacity()
+    }
+
+    /// Copies the given stack graph into this stack graph.  Symbols and strings are re-interned
+    /// in this graph, and the root and jump-to nodes of the other graph are mapped onto this
+    /// graph's own singleton nodes.  Returns a mapping from the node handles in the other graph
+    /// to the corresponding node handles in this graph, or the handle of the first file of the
+    /// other graph that is already defined in this graph.
     pub fn add_from_graph(
         &mut self,
         other: &StackGraph,
-    ) -> Result<Vec<Handle<File>>, Handle<File>> {
+    ) -> Result<HashMap<Handle<Node>, Handle<Node>>, Handle<File>> {
         let mut files = HashMap::new();
         for other_file in other.iter_files() {
             let file = self.add_file(other[other_file].name())?;
             files.insert(other_file, file);
         }
         let files = files;
+        for (other_file, file) in &files {
+            if let Some(language) = other.file_language(*other_file) {
+                self.set_file_language(*file, language);
+            }
+            if let Some(debug_info) = other.file_debug_info(*other_file) {
+                *self.file_debug_info_mut(*file) = DebugInfo {
+                    entries: debug_info
+                        .entries
+                        .iter()
+                        .map(|e| DebugEntry {
+                            key: self.add_string(&other[e.key]),
+                            value: self.add_string(&other[e.value]),
+                        })
+                        .collect::<Vec<_>>(),
+                };
+            }
+        }
         let node_id = |other_node_id: NodeID| {
             if other_node_id.is_root() {
                 NodeID::root()
@@ -1547,7 +2668,7 @@ impl StackGraph {
                         ..
                     }) => PopScopedSymbolNode {
                         id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
+                        symbol: self.add_symbol_bytes(other.symbol_bytes(symbol)),
                         _scope: NodeID::default(),
                         is_definition: is_definition,
                     }
@@ -1559,7 +2680,7 @@ impl StackGraph {
                         ..
                     }) => PopSymbolNode {
                         id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
+                        symbol: self.add_symbol_bytes(other.symbol_bytes(symbol)),
                         _scope: NodeID::default(),
                         is_definition: is_definition,
                     }
@@ -1572,7 +2693,7 @@ impl StackGraph {
                         ..
                     }) => PushScopedSymbolNode {
                         id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
+                        symbol: self.add_symbol_bytes(other.symbol_bytes(symbol)),
                         scope: node_id(scope),
                         is_reference: is_reference,
                         _phantom: (),
@@ -1585,7 +2706,7 @@ impl StackGraph {
                         ..
                     }) => PushSymbolNode {
                         id: NodeID::new_in_file(file, id.local_id),
-                        symbol: self.add_symbol(&other[symbol]),
+                        symbol: self.add_symbol_bytes(other.symbol_bytes(symbol)),
                         _scope: NodeID::default(),
                         is_reference: is_reference,
                     }
@@ -1623,7 +2744,11 @@ impl StackGraph {
                             .map(|cl| self.add_string(&other[cl]))
                             .into(),
                         definiens_span: source_info.definiens_span.clone(),
-                        fully_qualified_name: ControlledOption::default(),
+                        fully_qualified_name: source_info
+                            .fully_qualified_name
+                            .into_option()
+                            .map(|fqn| self.add_string(&other[fqn]))
+                            .into(),
                     };
                 }
                 if let Some(debug_info) = other.node_debug_info(other_node) {
@@ -1639,17 +2764,237 @@ impl StackGraph {
                     };
                 }
             }
-            for other_node in nodes.keys().cloned() {
-                for other_edge in other.outgoing_edges(other_node) {
-                    self.add_edge(
-                        nodes[&other_edge.source],
-                        nodes[&other_edge.sink],
-                        other_edge.precedence,
-                    );
+        }
+        // Edges are only copied once all nodes are known, because they can cross file boundaries.
+        for other_node in nodes.keys().cloned().collect::<Vec<_>>() {
+            for other_edge in other.outgoing_edges(other_node) {
+                let source = nodes[&other_edge.source];
+                let sink = nodes[&other_edge.sink];
+                self.add_edge(source, sink, other_edge.precedence);
+                self.set_edge_cost(source, sink, other_edge.cost);
+                if let Some(debug_info) = other.edge_debug_info(other_edge.source, other_edge.sink)
+                {
+                    let entries = debug_info
+                        .entries
+                        .iter()
+                        .map(|e| DebugEntry {
+                            key: self.add_string(&other[e.key]),
+                            value: self.add_string(&other[e.value]),
+                        })
+                        .collect::<Vec<_>>();
+                    *self.edge_debug_info_mut(source, sink) = DebugInfo { entries };
+                }
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Removes all of the nodes that belong to a file from this stack graph, together with all
+    /// edges that start or end at those nodes (including edges from the singleton _root_ and
+    /// _jump to scope_ nodes), and their source and debug info.  The file itself remains part of
+    /// the graph, so that new nodes can be added to it afterwards.
+    ///
+    /// The handles of all other nodes remain valid.  The slots of the removed nodes are
+    /// tombstoned: they are not returned by [`iter_nodes`][Self::iter_nodes],
+    /// [`nodes_for_file`][Self::nodes_for_file], or [`node_for_id`][Self::node_for_id] anymore,
+    /// and their handles must not be used after this call.
+    ///
+    /// Because incoming edges are not indexed, this takes time linear in the size of the graph.
+    pub fn remove_file(&mut self, file: Handle<File>) {
+        let removed = self.nodes_for_file(file).collect::<Vec<_>>();
+        self.node_id_handles.clear_file(file);
+        if let Some(index) = self.symbol_node_index.get_mut(file) {
+            index.clear();
+        }
+        if removed.is_empty() {
+            return;
+        }
+
+        for node in &removed {
+            self.removed_nodes.add(*node);
+            if let Some(source_info) = self.source_info.get_mut(*node) {
+                *source_info = SourceInfo::default();
+            }
+            if let Some(debug_info) = self.node_debug_info.get_mut(*node) {
+                *debug_info = DebugInfo::default();
+            }
+        }
+        self.removed_node_count += removed.len();
+
+        // Incoming edge degrees cannot be decremented, so we recompute them while removing the
+        // edges that start or end at a removed node.
+        let removed_nodes = &self.removed_nodes;
+        self.incoming_edges.clear();
+        self.edge_count = 0;
+        for source in self.nodes.iter_handles() {
+            let is_removed = removed_nodes.contains(source);
+            if let Some(edges) = self.outgoing_edges.get_mut(source) {
+                if is_removed {
+                    edges.clear();
+                } else {
+                    edges.retain(|edge| !removed_nodes.contains(edge.sink));
+                }
+                for edge in edges.iter() {
+                    self.incoming_edges[edge.sink] += Degree::One;
+                }
+                self.edge_count += edges.len();
+            }
+            if let Some(edges) = self.edge_debug_info.get_mut(source) {
+                if is_removed {
+                    edges.clear();
+                } else {
+                    edges.retain(|(sink, _)| !removed_nodes.contains(*sink));
                 }
             }
         }
-        Ok(files.into_values().collect())
+    }
+
+    /// Reclaims the slots of nodes that were removed by [`remove_file`][Self::remove_file],
+    /// renumbering the remaining nodes densely while preserving their relative order.  Edges and
+    /// source and debug info are updated accordingly.  The singleton _root_ and _jump to scope_
+    /// nodes keep their handles.
+    ///
+    /// All other node handles from before this call are invalid afterwards.  The returned map
+    /// contains the new handle of every remaining node, keyed by its old handle, so that handles
+    /// stored outside of the graph can be updated.
+    pub fn compact(&mut self) -> HashMap<Handle<Node>, Handle<Node>> {
+        let removed_nodes = &self.removed_nodes;
+        let moved = self.nodes.retain(|node| !removed_nodes.contains(node));
+        let mapping = moved.iter().cloned().collect::<HashMap<_, _>>();
+
+        let mut source_info = SupplementalArena::new();
+        let mut outgoing_edges = SupplementalArena::new();
+        let mut incoming_edges = SupplementalArena::new();
+        let mut node_debug_info = SupplementalArena::new();
+        let mut edge_debug_info = SupplementalArena::new();
+        for (old, new) in moved {
+            if let Some(info) = self.source_info.get_mut(old) {
+                source_info[new] = std::mem::take(info);
+            }
+            if let Some(edges) = self.outgoing_edges.get_mut(old) {
+                let mut edges = std::mem::take(edges);
+                for edge in &mut edges {
+                    edge.sink = mapping[&edge.sink];
+                    incoming_edges[edge.sink] += Degree::One;
+                }
+                outgoing_edges[new] = edges;
+            }
+            if let Some(info) = self.node_debug_info.get_mut(old) {
+                node_debug_info[new] = std::mem::take(info);
+            }
+            if let Some(edges) = self.edge_debug_info.get_mut(old) {
+                let mut edges = std::mem::take(edges);
+                for (sink, _) in &mut edges {
+                    *sink = mapping[sink];
+                }
+                edge_debug_info[new] = edges;
+            }
+        }
+        self.source_info = source_info;
+        self.outgoing_edges = outgoing_edges;
+        self.incoming_edges = incoming_edges;
+        self.node_debug_info = node_debug_info;
+        self.edge_debug_info = edge_debug_info;
+
+        for file in self.files.iter_handles() {
+            for handle in self.node_id_handles.handles_for_file_mut(file) {
+                *handle = mapping[handle];
+            }
+            if let Some(index) = self.symbol_node_index.get_mut(file) {
+                for handle in index.values_mut() {
+                    *handle = mapping[handle];
+                }
+            }
+        }
+
+        self.removed_nodes.clear();
+        self.removed_node_count = 0;
+        mapping
+    }
+}
+
+/// A violation of one of the invariants of a stack graph, as reported by
+/// [`StackGraph::validate`][].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValidationError {
+    /// An edge starts or ends at a node that doesn't exist or has been removed.
+    DanglingEdge {
+        source: Handle<Node>,
+        sink: Handle<Node>,
+    },
+    /// A node's ID does not resolve back to the node, because another node in the same file has
+    /// the same local ID.
+    DuplicateNodeID { node: Handle<Node>, id: NodeID },
+    /// A _push scoped symbol_ node's attached scope does not refer to an exported scope node.
+    InvalidAttachedScope { node: Handle<Node>, scope: NodeID },
+    /// A node refers to a symbol that doesn't exist in the graph.
+    UnknownSymbol {
+        node: Handle<Node>,
+        symbol: Handle<Symbol>,
+    },
+}
+
+impl StackGraph {
+    /// Checks the invariants of this stack graph, which can be violated by graphs that are built
+    /// by buggy generators, or via the C API.  All violations are collected, instead of stopping
+    /// at the first one.
+    pub fn validate(&self) -> Result<(), Vec<ValidationError>> {
+        self.validate_with_cancellation(&NoCancellation)
+            .expect("should never be cancelled")
+    }
+
+    /// Like [`validate`][Self::validate], but checks the cancellation flag for each node.  The
+    /// outer result is an error if the flag fires before validation has finished; the inner
+    /// result is the outcome of the validation.
+    pub fn validate_with_cancellation(
+        &self,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Result<(), Vec<ValidationError>>, CancellationError> {
+        let mut errors = Vec::new();
+        let is_live = |node: Handle<Node>| self.is_live_node(node);
+        for node in self.iter_nodes() {
+            cancellation_flag.check("validating graph")?;
+            let value = &self[node];
+            let id = value.id();
+            if self.node_for_id(id) != Some(node) {
+                errors.push(ValidationError::DuplicateNodeID { node, id });
+            }
+            if let Some(symbol) = value.symbol() {
+                if symbol.as_usize() > self.symbols.len() {
+                    errors.push(ValidationError::UnknownSymbol { node, symbol });
+                }
+            }
+            if let Some(scope) = value.scope() {
+                let is_exported_scope = matches!(
+                    self.node_for_id(scope),
+                    Some(scope) if self[scope].is_exported_scope()
+                );
+                if !is_exported_scope {
+                    errors.push(ValidationError::InvalidAttachedScope { node, scope });
+                }
+            }
+            for edge in self.outgoing_edges(node) {
+                if !is_live(edge.sink) {
+                    errors.push(ValidationError::DanglingEdge {
+                        source: edge.source,
+                        sink: edge.sink,
+                    });
+                }
+            }
+        }
+        for node in self.removed_nodes.iter() {
+            for edge in self.outgoing_edges(node) {
+                errors.push(ValidationError::DanglingEdge {
+                    source: edge.source,
+                    sink: edge.sink,
+                });
+            }
+        }
+        if errors.is_empty() {
+            Ok(Ok(()))
+        } else {
+            Ok(Err(errors))
+        }
     }
 }
 
@@ -1661,19 +3006,493 @@ impl Default for StackGraph {
 
         StackGraph {
             interned_strings: InternedStringArena::new(),
-            symbols: Arena::new(),
-            symbol_handles: FxHashMap::default(),
+            symbols: SymbolStorage::Default(DefaultSymbolInterner::new()),
+            symbol_normalizer: None,
+            symbol_byte_handles: FxHashMap::default(),
+            symbol_bytes: FxHashMap::default(),
             strings: Arena::new(),
             string_handles: FxHashMap::default(),
             files: Arena::new(),
             file_handles: FxHashMap::default(),
+            file_languages: SupplementalArena::new(),
             nodes,
             source_info: SupplementalArena::new(),
             node_id_handles: NodeIDHandles::new(),
+            #[cfg(feature = "digests")]
+            deterministic_ids: false,
+            removed_nodes: HandleSet::new(),
+            removed_node_count: 0,
+            symbol_node_index: SupplementalArena::new(),
             outgoing_edges: SupplementalArena::new(),
             incoming_edges: SupplementalArena::new(),
+            edge_count: 0,
             node_debug_info: SupplementalArena::new(),
             edge_debug_info: SupplementalArena::new(),
+            file_debug_info: SupplementalArena::new(),
+        }
+    }
+}
+
+impl StackGraph {
+    /// Returns a textual dump of the whole stack graph.  It lists the singleton nodes and then each
+    /// file, sorted by name.  Each node is followed by its source span, if it has one, and by its
+    /// outgoing edges with their precedence.  Nodes are sorted by local ID, and edges by sink.  The
+    /// output only depends on the content of the graph, not on the order in which it was built,
+    /// so it can be used in snapshot tests.
+    pub fn display_full(&self) -> impl Display + '_ {
+        DisplayStackGraph { graph: self }
+    }
+}
+
+#[doc(hidden)]
+pub struct DisplayStackGraph<'a> {
+    graph: &'a StackGraph,
+}
+
+impl<'a> DisplayStackGraph<'a> {
+    fn sort_key(&self, node: Handle<Node>) -> (Option<&'a str>, u32) {
+        let id = self.graph[node].id();
+        (id.file().map(|f| self.graph[f].name()), id.local_id())
+    }
+
+    fn fmt_node(&self, f: &mut std::fmt::Formatter, node: Handle<Node>) -> std::fmt::Result {
+        let graph = self.graph;
+        write!(f, "  {}", node.display(graph))?;
+        if let Some(source_info) = graph.source_info(node) {
+            let span = &source_info.span;
+            if *span != lsp_positions::Span::default() {
+                write!(
+                    f,
+                    " {}:{}-{}:{}",
+                    span.start.line,
+                    span.start.column.utf8_offset,
+                    span.end.line,
+                    span.end.column.utf8_offset,
+                )?;
+            }
+        }
+        writeln!(f)?;
+        let mut edges = graph.outgoing_edges(node).collect::<Vec<_>>();
+        edges.sort_by_key(|edge| self.sort_key(edge.sink));
+        for edge in edges {
+            writeln!(
+                f,
+                "    -> {} ({})",
+                edge.sink.display(graph),
+                edge.precedence
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Display for DisplayStackGraph<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let graph = self.graph;
+        writeln!(f, "singletons")?;
+        self.fmt_node(f, StackGraph::root_node())?;
+        self.fmt_node(f, StackGraph::jump_to_node())?;
+        let mut files = graph.iter_files().collect::<Vec<_>>();
+        files.sort_by_key(|file| graph[*file].name());
+        for file in files {
+            writeln!(f, "file {}", graph[file].name())?;
+            let mut nodes = graph.nodes_for_file(file).collect::<Vec<_>>();
+            nodes.sort_by_key(|node| self.sort_key(*node));
+            for node in nodes {
+                self.fmt_node(f, node)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Memory usage
+
+/// An approximate breakdown of the memory that a stack graph has allocated, in bytes.
+///
+/// The numbers are computed from the capacities of the graph's internal arenas, vectors, and hash
+/// maps, and are meant for memory profiling, not for exact accounting.  Allocator overhead and the
+/// control bytes of hash maps are not included.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct MemoryUsage {
+    /// Memory used by nodes, including the indexes that look them up by ID or symbol.
+    pub nodes: usize,
+    /// Memory used by edges.
+    pub edges: usize,
+    /// Memory used by symbols.  This does not include the content of symbols stored by a custom
+    /// [`SymbolInterner`][].
+    pub symbols: usize,
+    /// Memory used by interned strings and file names.
+    pub strings: usize,
+    /// Memory used by the source info of nodes.
+    pub source_info: usize,
+    /// Memory used by the debug info of nodes, edges, and files.
+    pub debug_info: usize,
+}
+
+impl MemoryUsage {
+    /// Returns the total number of bytes across all categories.
+    pub fn total(&self) -> usize {
+        self.nodes + self.edges + self.symbols + self.strings + self.source_info + self.debug_info
+    }
+}
+
+impl StackGraph {
+    /// Returns an approximate breakdown of the memory that this graph has allocated.  This only
+    /// sums up capacities and lengths, and does not modify the graph.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        let nodes = arena_bytes(&self.nodes)
+            + supplemental_arena_bytes(&self.node_id_handles.files)
+            + self
+                .node_id_handles
+                .files
+                .iter()
+                .map(|(_, handles)| vec_bytes(handles))
+                .sum::<usize>()
+            + supplemental_arena_bytes(&self.node_id_handles.sparse_files)
+            + self
+                .node_id_handles
+                .sparse_files
+                .iter()
+                .map(|(_, handles)| hash_map_bytes(handles))
+                .sum::<usize>()
+            + supplemental_arena_bytes(&self.symbol_node_index)
+            + self
+                .symbol_node_index
+                .iter()
+                .map(|(_, index)| hash_map_bytes(index))
+                .sum::<usize>();
+
+        let edges = supplemental_arena_bytes(&self.outgoing_edges)
+            + self
+                .outgoing_edges
+                .iter()
+                .map(|(_, edges)| small_vec_bytes(edges))
+                .sum::<usize>()
+            + supplemental_arena_bytes(&self.incoming_edges);
+
+        let mut symbols =
+            hash_map_bytes(&self.symbol_byte_handles) + hash_map_bytes(&self.symbol_bytes);
+        if let SymbolStorage::Default(interner) = &self.symbols {
+            symbols += interner.strings.allocated_bytes()
+                + arena_bytes(&interner.symbols)
+                + hash_map_bytes(&interner.handles);
+        }
+        if let Some(normalizer) = &self.symbol_normalizer {
+            symbols += hash_map_bytes(&normalizer.handles);
+        }
+
+        let strings = self.interned_strings.allocated_bytes()
+            + arena_bytes(&self.strings)
+            + hash_map_bytes(&self.string_handles)
+            + arena_bytes(&self.files)
+            + hash_map_bytes(&self.file_handles)
+            + supplemental_arena_bytes(&self.file_languages);
+
+        let source_info = supplemental_arena_bytes(&self.source_info);
+
+        let debug_info = supplemental_arena_bytes(&self.node_debug_info)
+            + self
+                .node_debug_info
+                .iter()
+                .map(|(_, info)| info.allocated_bytes())
+                .sum::<usize>()
+            + supplemental_arena_bytes(&self.edge_debug_info)
+            + self
+                .edge_debug_info
+                .iter()
+                .map(|(_, infos)| {
+                    small_vec_bytes(infos)
+                        + infos
+                            .iter()
+                            .map(|(_, info)| info.allocated_bytes())
+                            .sum::<usize>()
+                })
+                .sum::<usize>()
+            + supplemental_arena_bytes(&self.file_debug_info)
+            + self
+                .file_debug_info
+                .iter()
+                .map(|(_, info)| info.allocated_bytes())
+                .sum::<usize>();
+
+        MemoryUsage {
+            nodes,
+            edges,
+            symbols,
+            strings,
+            source_info,
+            debug_info,
+        }
+    }
+}
+
+impl InternedStringArena {
+    fn allocated_bytes(&self) -> usize {
+        self.current_buffer.capacity()
+            + vec_bytes(&self.full_buffers)
+            + self
+                .full_buffers
+                .iter()
+                .map(|buffer| buffer.capacity())
+                .sum::<usize>()
+    }
+}
+
+impl DebugInfo {
+    fn allocated_bytes(&self) -> usize {
+        vec_bytes(&self.entries)
+    }
+}
+
+// The arenas reserve slot 0 for a dummy entry, which still takes up space.
+fn arena_bytes<T>(arena: &Arena<T>) -> usize {
+    (arena.capacity() + 1) * std::mem::size_of::<T>()
+}
+
+fn supplemental_arena_bytes<H, T>(arena: &SupplementalArena<H, T>) -> usize {
+    (arena.capacity() + 1) * std::mem::size_of::<T>()
+}
+
+fn vec_bytes<T>(vec: &Vec<T>) -> usize {
+    vec.capacity() * std::mem::size_of::<T>()
+}
+
+// Small vectors only allocate once they have spilled onto the heap.
+fn small_vec_bytes<A: smallvec::Array>(vec: &SmallVec<A>) -> usize {
+    if vec.spilled() {
+        vec.capacity() * std::mem::size_of::<A::Item>()
+    } else {
+        0
+    }
+}
+
+fn hash_map_bytes<K, V, S>(map: &HashMap<K, V, S>) -> usize {
+    map.capacity() * (std::mem::size_of::<K>() + std::mem::size_of::<V>())
+}
+
+//-------------------------------------------------------------------------------------------------
+// File digests
+
+/// One end of an edge that is included in a file digest: either a node of the file, identified
+/// by its index, or a node outside of the file, identified by the digest of its global identity.
+#[cfg(feature = "digests")]
+#[derive(Clone, Copy)]
+enum DigestEndpoint {
+    Local(usize),
+    External([u8; 32]),
+}
+
+#[cfg(feature = "digests")]
+impl StackGraph {
+    /// Returns a digest of the structure of a file's subgraph, which can be used to detect
+    /// whether a file has to be reprocessed, for instance to skip re-stitching files whose
+    /// structure hasn't changed.
+    ///
+    /// The digest covers the kind, symbol, and flags of each node in the file, the edges that
+    /// start at one of those nodes, the edges from the singleton _root_ and _jump to scope_ nodes
+    /// to one of those nodes, and the attached scopes of _push scoped symbol_ nodes.  It does not
+    /// cover the file's name, source info, or debug info.  Nodes of the file are not identified
+    /// by their local IDs, so two structurally identical files have the same digest, even if
+    /// their nodes were created in a different order.  Nodes in other files are identified by
+    /// their file name and local ID.
+    ///
+    /// Nodes are distinguished by their content and, iteratively, by their neighborhoods, which
+    /// is the same refinement that is used by the Weisfeiler-Lehman graph isomorphism test.  As
+    /// with that test, it is possible (but rare in practice) for two files that are _not_
+    /// structurally identical to have the same digest.
+    ///
+    /// This is only available when the `digests` feature is enabled.
+    pub fn file_digest(&self, file: Handle<File>) -> [u8; 32] {
+        let nodes = self.nodes_for_file(file).collect::<Vec<_>>();
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (*node, index))
+            .collect::<HashMap<_, _>>();
+        let endpoint = |id: NodeID| match self.node_for_id(id).and_then(|node| index.get(&node)) {
+            Some(index) => DigestEndpoint::Local(*index),
+            None => DigestEndpoint::External(self.external_node_digest(id)),
+        };
+
+        // Collect the edges and attached scopes that touch the file as (source, sink, (precedence,
+        // cost), kind) tuples, where kind distinguishes edges from attached scopes.
+        let mut edges = Vec::new();
+        for node in &nodes {
+            for edge in self.outgoing_edges(*node) {
+                let sink = endpoint(self[edge.sink].id());
+                edges.push((index[node], sink, (edge.precedence, edge.cost), 0u8));
+            }
+            if let Some(scope) = self[*node].scope() {
+                edges.push((index[node], endpoint(scope), (0, 0), 1u8));
+            }
+        }
+        let mut singleton_edges = Vec::new();
+        for singleton in &[StackGraph::root_node(), StackGraph::jump_to_node()] {
+            let source = self.external_node_digest(self[*singleton].id());
+            for edge in self.outgoing_edges(*singleton) {
+                if let Some(sink) = index.get(&edge.sink) {
+                    singleton_edges.push((source, *sink, (edge.precedence, edge.cost)));
+                }
+            }
+        }
+
+        // Refine the node labels until they no longer split any set of nodes that share a label.
+        let mut labels = nodes
+            .iter()
+            .map(|node| self.node_content_digest(*node))
+            .collect::<Vec<_>>();
+        let mut distinct = labels.iter().collect::<HashSet<_>>().len();
+        loop {
+            let mut neighbors = vec![Vec::new(); nodes.len()];
+            for (source, sink, precedence, kind) in &edges {
+                match sink {
+                    DigestEndpoint::Local(sink) => {
+                        neighbors[*source].push((*kind, *precedence, labels[*sink]));
+                        neighbors[*sink].push((*kind | 2, *precedence, labels[*source]));
+                    }
+                    DigestEndpoint::External(sink) => {
+                        neighbors[*source].push((*kind, *precedence, *sink));
+                    }
+                }
+            }
+            for (source, sink, precedence) in &singleton_edges {
+                neighbors[*sink].push((2, *precedence, *source));
+            }
+            let refined = labels
+                .iter()
+                .zip(neighbors)
+                .map(|(label, mut neighbors)| {
+                    neighbors.sort_unstable();
+                    let mut hasher = Sha256::new();
+                    hasher.update(label);
+                    hasher.update((neighbors.len() as u64).to_le_bytes());
+                    for (kind, (precedence, cost), label) in neighbors {
+                        hasher.update([kind]);
+                        hasher.update(precedence.to_le_bytes());
+                        hasher.update(cost.to_le_bytes());
+                        hasher.update(label);
+                    }
+                    hasher.finalize().into()
+                })
+                .collect::<Vec<[u8; 32]>>();
+            let refined_distinct = refined.iter().collect::<HashSet<_>>().len();
+            labels = refined;
+            if refined_distinct <= distinct {
+                break;
+            }
+            distinct = refined_distinct;
+        }
+
+        labels.sort_unstable();
+        let mut hasher = Sha256::new();
+        hasher.update((labels.len() as u64).to_le_bytes());
+        for label in labels {
+            hasher.update(label);
+        }
+        hasher.finalize().into()
+    }
+
+    /// Returns a digest of the kind, symbol, and flags of a node.
+    fn node_content_digest(&self, node: Handle<Node>) -> [u8; 32] {
+        let (kind, flag) = match &self[node] {
+            Node::DropScopes(_) => (0u8, false),
+            Node::JumpTo(_) => (1, false),
+            Node::PopScopedSymbol(node) => (2, node.is_definition),
+            Node::PopSymbol(node) => (3, node.is_definition),
+            Node::PushScopedSymbol(node) => (4, node.is_reference),
+            Node::PushSymbol(node) => (5, node.is_reference),
+            Node::Root(_) => (6, false),
+            Node::Scope(node) => (7, node.is_exported),
+        };
+        let mut hasher = Sha256::new();
+        hasher.update([kind, flag as u8]);
+        if let Some(symbol) = self[node].symbol() {
+            let symbol = &self[symbol];
+            hasher.update((symbol.len() as u64).to_le_bytes());
+            hasher.update(symbol);
+        }
+        hasher.finalize().into()
+    }
+
+    /// Returns a digest of the global identity of a node, which is used for nodes outside of the
+    /// file being digested.
+    fn external_node_digest(&self, id: NodeID) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        match id.file() {
+            Some(file) => {
+                let name = self[file].name();
+                hasher.update([0u8]);
+                hasher.update((name.len() as u64).to_le_bytes());
+                hasher.update(name);
+            }
+            None => hasher.update([1u8]),
         }
+        hasher.update(id.local_id().to_le_bytes());
+        hasher.finalize().into()
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Concurrent graph building
+
+/// Merges stack graphs that are built independently — for instance, one per file on separate
+/// worker threads — into a single shared stack graph.
+///
+/// Each worker constructs its own [`StackGraph`][] for the files it processes, without any
+/// synchronization, and hands the finished graph to [`add`][Self::add].  Merging uses
+/// [`StackGraph::add_from_graph`][], under a lock on the shared graph: merges from different
+/// threads are serialized, but building the per-file graphs, which is usually far more expensive
+/// than copying them, proceeds fully in parallel.  Symbols and strings are re-interned in the
+/// shared graph while merging, so a symbol that appears in many files is only stored once.
+///
+/// The speedup over building all files on a single thread therefore approaches the number of
+/// worker threads as long as the time spent building a file dominates the time spent merging
+/// it, which is linear in the size of the file's graph.
+pub struct ConcurrentGraphBuilder {
+    graph: std::sync::Mutex<StackGraph>,
+}
+
+impl ConcurrentGraphBuilder {
+    /// Creates a new builder whose shared graph is initially empty.
+    pub fn new() -> ConcurrentGraphBuilder {
+        ConcurrentGraphBuilder::from_graph(StackGraph::new())
+    }
+
+    /// Creates a new builder that merges graphs into an existing stack graph.  Use this to
+    /// configure the shared graph, for instance with a custom symbol interner.
+    pub fn from_graph(graph: StackGraph) -> ConcurrentGraphBuilder {
+        ConcurrentGraphBuilder {
+            graph: std::sync::Mutex::new(graph),
+        }
+    }
+
+    /// Merges a stack graph into the shared graph.  This blocks while another thread is merging.
+    /// Returns a mapping from the node handles in `other` to the corresponding node handles in the
+    /// shared graph, or the handle of the first file of `other` that is already defined in the
+    /// shared graph.
+    pub fn add(
+        &self,
+        other: &StackGraph,
+    ) -> Result<HashMap<Handle<Node>, Handle<Node>>, Handle<File>> {
+        self.graph
+            .lock()
+            .expect("another thread panicked while merging")
+            .add_from_graph(other)
+    }
+
+    /// Returns the shared graph, once all workers are done.
+    pub fn into_graph(self) -> StackGraph {
+        self.graph
+            .into_inner()
+            .expect("another thread panicked while merging")
+    }
+}
+
+impl Default for ConcurrentGraphBuilder {
+    fn default() -> ConcurrentGraphBuilder {
+        ConcurrentGraphBuilder::new()
     }
 }
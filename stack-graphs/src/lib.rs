@@ -55,6 +55,7 @@
 //! importantly, each “chunk” of the overall graph only depends on “local” information from the
 //! original source file.  (a.k.a., it’s incremental!)
 
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::{Duration, Instant};
 
 use thiserror::Error;
@@ -65,6 +66,8 @@ pub mod c;
 pub mod cycles;
 #[macro_use]
 mod debugging;
+pub mod diff;
+mod dot;
 pub mod graph;
 pub mod partial;
 pub mod paths;
@@ -73,6 +76,8 @@ pub mod stats;
 pub mod stitching;
 #[cfg(feature = "storage")]
 pub mod storage;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub(crate) mod utils;
 #[cfg(feature = "visualization")]
 pub mod visualization;
@@ -112,6 +117,65 @@ impl CancellationFlag for CancelAfterDuration {
     }
 }
 
-#[derive(Clone, Debug, Error)]
+/// Cancels execution once a deadline has passed.  Unlike [`CancelAfterDuration`][], this only
+/// reads the clock on every _n_th check, where _n_ is the [granularity][Self::with_granularity],
+/// which keeps the cost of checking low in hot loops.  Once the deadline has been observed to
+/// pass, all further checks report cancellation.
+pub struct TimeoutCancellation {
+    deadline: Instant,
+    granularity: usize,
+    unclocked_checks: AtomicUsize,
+    expired: AtomicBool,
+}
+
+impl TimeoutCancellation {
+    /// The default number of checks between clock reads.
+    pub const DEFAULT_GRANULARITY: usize = 64;
+
+    /// Creates a flag that cancels execution once `timeout` has passed from now.
+    pub fn new(timeout: Duration) -> Self {
+        Self::with_deadline(Instant::now() + timeout)
+    }
+
+    /// Creates a flag that cancels execution once `deadline` has passed.
+    pub fn with_deadline(deadline: Instant) -> Self {
+        Self {
+            deadline,
+            granularity: Self::DEFAULT_GRANULARITY,
+            unclocked_checks: AtomicUsize::new(0),
+            expired: AtomicBool::new(false),
+        }
+    }
+
+    /// Sets the number of checks between clock reads.  A granularity of 1 reads the clock on
+    /// every check.
+    pub fn with_granularity(mut self, granularity: usize) -> Self {
+        self.granularity = granularity.max(1);
+        self
+    }
+}
+
+impl CancellationFlag for TimeoutCancellation {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        if self.expired.load(Ordering::Relaxed) {
+            return Err(CancellationError(at));
+        }
+        let unclocked_checks = self.unclocked_checks.load(Ordering::Relaxed);
+        if unclocked_checks > 0 {
+            self.unclocked_checks
+                .store(unclocked_checks - 1, Ordering::Relaxed);
+            return Ok(());
+        }
+        self.unclocked_checks
+            .store(self.granularity - 1, Ordering::Relaxed);
+        if Instant::now() >= self.deadline {
+            self.expired.store(true, Ordering::Relaxed);
+            return Err(CancellationError(at));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Error, PartialEq)]
 #[error("Cancelled at \"{0}\"")]
 pub struct CancellationError(pub &'static str);
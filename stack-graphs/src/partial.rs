@@ -32,6 +32,62 @@
 //! producing the original "full" path that represents a name binding.
 //!
 //! [concatenate]: struct.PartialPath.html#method.concatenate
+//!
+//! # Partial symbol and scope stacks
+//!
+//! The preconditions and postconditions of a partial path are described by
+//! [`PartialSymbolStack`][]s and [`PartialScopeStack`][]s.  You can also build and inspect these
+//! stacks directly, for instance to implement your own resolution strategy.
+//!
+//! The stacks are small `Copy` values that refer to their contents, which are stored in arenas
+//! owned by a [`PartialPaths`][] instance.  You must always use a stack with the `PartialPaths`
+//! instance that it was created with.  Most operations take a `&mut PartialPaths`, even ones that
+//! only read a stack's contents, since the arenas lazily cache the reversal of each stack to
+//! support efficient access at both ends.  Because the contents are shared and immutable,
+//! pushing onto or popping from a copy of a stack never affects the original.
+//!
+//! ```
+//! # use controlled_option::ControlledOption;
+//! # use stack_graphs::graph::NodeID;
+//! # use stack_graphs::graph::StackGraph;
+//! # use stack_graphs::partial::PartialPaths;
+//! # use stack_graphs::partial::PartialScopeStack;
+//! # use stack_graphs::partial::PartialScopedSymbol;
+//! # use stack_graphs::partial::PartialSymbolStack;
+//! let mut graph = StackGraph::new();
+//! let file = graph.get_or_create_file("test.py");
+//! let x = graph.add_symbol("x");
+//! let y = graph.add_symbol("y");
+//! let scope = graph
+//!     .add_scope_node(NodeID::new_in_file(file, 1), true)
+//!     .unwrap();
+//! let mut partials = PartialPaths::new();
+//!
+//! let mut scopes = PartialScopeStack::empty();
+//! scopes.push_front(&mut partials, scope);
+//! assert_eq!(scopes.iter_scopes(&mut partials).collect::<Vec<_>>(), vec![scope]);
+//!
+//! let mut symbols = PartialSymbolStack::empty();
+//! symbols.push_front(&mut partials, PartialScopedSymbol {
+//!     symbol: y,
+//!     scopes: ControlledOption::none(),
+//! });
+//! symbols.push_front(&mut partials, PartialScopedSymbol {
+//!     symbol: x,
+//!     scopes: ControlledOption::some(scopes),
+//! });
+//! let contents = symbols
+//!     .iter(&mut partials)
+//!     .map(|symbol| symbol.symbol)
+//!     .collect::<Vec<_>>();
+//! assert_eq!(contents, vec![x, y]);
+//!
+//! let mut copy = symbols;
+//! let front = copy.pop_front(&mut partials).unwrap();
+//! assert_eq!(front.symbol, x);
+//! assert_eq!(copy.len(), 1);
+//! assert_eq!(symbols.len(), 2);
+//! ```
 
 use std::convert::TryFrom;
 use std::fmt::Display;
@@ -466,6 +522,8 @@ impl PartialSymbolStack {
         self.variable.is_some()
     }
 
+    /// Returns the number of symbols in this partial symbol stack, not counting its symbol stack
+    /// variable.
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.length as usize
@@ -797,7 +855,7 @@ impl PartialSymbolStack {
         )
     }
 
-    /// Returns an iterator over the contents of this partial symbol stack.
+    /// Returns an iterator over the symbols in this partial symbol stack, from front to back.
     pub fn iter<'a>(
         &self,
         partials: &'a mut PartialPaths,
@@ -818,6 +876,8 @@ impl PartialSymbolStack {
             .copied()
     }
 
+    /// Returns the symbol stack variable at the end of this partial symbol stack.  If the stack
+    /// does not contain a symbol stack variable, returns `None`.
     pub fn variable(&self) -> Option<SymbolStackVariable> {
         self.variable.clone().into_option()
     }
@@ -930,6 +990,8 @@ impl PartialScopeStack {
         self.variable.is_some()
     }
 
+    /// Returns the number of scopes in this partial scope stack, not counting its scope stack
+    /// variable.
     #[inline(always)]
     pub fn len(&self) -> usize {
         self.length as usize
@@ -1208,7 +1270,7 @@ impl PartialScopeStack {
             })
     }
 
-    /// Returns an iterator over the scopes in this partial scope stack.
+    /// Returns an iterator over the scopes in this partial scope stack, from front to back.
     pub fn iter_scopes<'a>(
         &self,
         partials: &'a mut PartialPaths,
@@ -1836,6 +1898,31 @@ impl PartialPath {
         self.starts_at_reference(graph) && self.ends_at_definition(graph)
     }
 
+    /// Returns the total [cost][crate::graph::Edge] of the edges in this partial path.  Edges
+    /// that are not part of the graph, such as the ones from a _jump to scope_ node to the scope
+    /// that it jumps to, do not add to the cost.
+    pub fn cost(&self, graph: &StackGraph, partials: &mut PartialPaths) -> u64 {
+        let sources = self
+            .edges
+            .iter(partials)
+            .map(|edge| graph.node_for_id(edge.source_node_id))
+            .collect::<Vec<_>>();
+        let sinks = sources
+            .iter()
+            .skip(1)
+            .copied()
+            .chain(std::iter::once(Some(self.end_node)));
+        sources
+            .iter()
+            .zip(sinks)
+            .filter_map(|(source, sink)| {
+                let (source, sink) = ((*source)?, sink?);
+                graph.outgoing_edges(source).find(|edge| edge.sink == sink)
+            })
+            .map(|edge| edge.cost as u64)
+            .sum()
+    }
+
     pub fn starts_at_endpoint(&self, graph: &StackGraph) -> bool {
         graph[self.start_node].is_endpoint()
     }
@@ -1947,6 +2034,59 @@ impl PartialPath {
         }
     }
 
+    /// Removes redundant nodes from the edge list of this partial path.  A _push symbol_ node that
+    /// is immediately followed by a _pop symbol_ node for the same symbol has no net effect on the
+    /// symbol stack, so we can drop both of them from the path.  (Scoped symbols are never
+    /// cancelled out, since popping one replaces the scope stack.)  We repeat this until no such
+    /// pairs remain, so nested pairs are removed as well.  The start and end nodes of the path are
+    /// always kept.
+    ///
+    /// Edge precedences decide whether one path [shadows][Self::shadows] another, so a pair is
+    /// only dropped if none of the outgoing edges of either node has a non-zero precedence.  The
+    /// pre- and postconditions are not affected, so the simplified path resolves like the original
+    /// one; only the recorded edges get shorter.  The [cost][Self::cost] of the simplified path
+    /// does not include the edges that were dropped.
+    pub fn simplify(&mut self, graph: &StackGraph, partials: &mut PartialPaths) {
+        let has_precedence =
+            |node: Handle<Node>| graph.outgoing_edges(node).any(|edge| edge.precedence != 0);
+        let mut edges = Vec::with_capacity(self.edges.len());
+        let mut remaining = self.edges;
+        while let Some(edge) = remaining.pop_front(partials) {
+            // The first edge starts at the start node, which we must keep.
+            if edges.len() > 1 {
+                let previous: &PartialPathEdge = edges.last().unwrap();
+                let cancels = match (
+                    graph.node_for_id(previous.source_node_id),
+                    graph.node_for_id(edge.source_node_id),
+                ) {
+                    (Some(push_node), Some(pop_node)) => {
+                        match (&graph[push_node], &graph[pop_node]) {
+                            (Node::PushSymbol(push), Node::PopSymbol(pop)) => {
+                                push.symbol == pop.symbol
+                                    && !has_precedence(push_node)
+                                    && !has_precedence(pop_node)
+                            }
+                            _ => false,
+                        }
+                    }
+                    _ => false,
+                };
+                if cancels {
+                    edges.pop();
+                    continue;
+                }
+            }
+            edges.push(edge);
+        }
+        if edges.len() == self.edges.len() {
+            return;
+        }
+        self.edges = PartialPathEdgeList::empty();
+        for edge in edges {
+            self.edges.push_back(partials, edge);
+        }
+    }
+
     /// Returns the largest value of any symbol stack variable in this partial path.
     pub fn largest_symbol_stack_variable(&self) -> u32 {
         // We don't have to check the postconditions, because it's not valid for a postcondition to
@@ -2006,6 +2146,92 @@ impl PartialPath {
     ) -> impl Display + 'a {
         display_with(self, graph, partials)
     }
+
+    /// Returns a multi-line, human-readable rendering of this partial path, which shows the
+    /// pre- and postconditions of the symbol and scope stacks separately, followed by all of the
+    /// edges of the path in order.  This is more verbose than [`display`][Self::display], and is
+    /// useful when debugging why a path does or does not resolve.
+    pub fn display_detailed<'a>(
+        &'a self,
+        graph: &'a StackGraph,
+        partials: &'a mut PartialPaths,
+    ) -> impl Display + 'a {
+        display_with(DetailedPartialPath(self), graph, partials)
+    }
+}
+
+/// Displays a partial path in the multi-line format of [`PartialPath::display_detailed`].
+struct DetailedPartialPath<'a>(&'a PartialPath);
+
+impl<'a> DisplayWithPartialPaths for DetailedPartialPath<'a> {
+    fn prepare(&mut self, graph: &StackGraph, partials: &mut PartialPaths) {
+        let mut path = self.0;
+        path.prepare(graph, partials);
+        path.edges.clone().prepare(graph, partials);
+    }
+
+    fn display_with(
+        &self,
+        graph: &StackGraph,
+        partials: &PartialPaths,
+        f: &mut std::fmt::Formatter,
+    ) -> std::fmt::Result {
+        let path = self.0;
+        writeln!(
+            f,
+            "{} -> {}",
+            path.start_node.display(graph),
+            path.end_node.display(graph)
+        )?;
+        writeln!(
+            f,
+            "  symbol stack precondition:  <{}>",
+            display_prepared(path.symbol_stack_precondition, graph, partials)
+        )?;
+        writeln!(
+            f,
+            "  symbol stack postcondition: <{}>",
+            display_prepared(path.symbol_stack_postcondition, graph, partials)
+        )?;
+        writeln!(
+            f,
+            "  scope stack precondition:   ({})",
+            display_prepared(path.scope_stack_precondition, graph, partials)
+        )?;
+        writeln!(
+            f,
+            "  scope stack postcondition:  ({})",
+            display_prepared(path.scope_stack_postcondition, graph, partials)
+        )?;
+        write!(f, "  edges:")?;
+        if path.edges.is_empty() {
+            write!(f, " none")?;
+        }
+        let mut edges = path
+            .edges
+            .edges
+            .iter_reused(&partials.partial_path_edges)
+            .peekable();
+        while let Some(edge) = edges.next() {
+            write!(f, "\n    ")?;
+            match graph.node_for_id(edge.source_node_id) {
+                Some(node) => write!(f, "{}", node.display(graph))?,
+                None => write!(f, "[missing]")?,
+            }
+            write!(f, " -> ")?;
+            match edges.peek() {
+                Some(next) => match graph.node_for_id(next.source_node_id) {
+                    Some(node) => write!(f, "{}", node.display(graph))?,
+                    None => write!(f, "[missing]")?,
+                },
+                None => write!(f, "{}", path.end_node.display(graph))?,
+            }
+            if edge.precedence != 0 {
+                write!(f, " (precedence {})", edge.precedence)?;
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(Debug, EnumSetType)]
@@ -2460,7 +2686,53 @@ impl Node {
 //-------------------------------------------------------------------------------------------------
 // Extending partial paths with partial paths
 
+/// Describes why two partial paths cannot be concatenated.
+#[derive(Debug)]
+pub enum ConcatenationError {
+    /// The left-hand partial path does not end at the node where the right-hand partial path
+    /// starts.
+    IncorrectJoinNode {
+        lhs_end_node: Handle<Node>,
+        rhs_start_node: Handle<Node>,
+    },
+    /// The symbol stack postcondition of the left-hand partial path is not compatible with the
+    /// symbol stack precondition of the right-hand partial path.
+    IncompatibleSymbolStacks(PathResolutionError),
+    /// The scope stack postcondition of the left-hand partial path is not compatible with the
+    /// scope stack precondition of the right-hand partial path.
+    IncompatibleScopeStacks(PathResolutionError),
+}
+
+impl From<ConcatenationError> for PathResolutionError {
+    fn from(err: ConcatenationError) -> PathResolutionError {
+        match err {
+            ConcatenationError::IncorrectJoinNode { .. } => {
+                PathResolutionError::IncorrectSourceNode
+            }
+            ConcatenationError::IncompatibleSymbolStacks(err) => err,
+            ConcatenationError::IncompatibleScopeStacks(err) => err,
+        }
+    }
+}
+
 impl PartialPath {
+    /// Checks whether a partial path can be appended to this one, using the same checks as
+    /// [`concatenate`][Self::concatenate]: the right-hand partial path must start at the node
+    /// where this one ends, and the pre- and postconditions must be compatible at that node.  If
+    /// the partial paths cannot be concatenated, we return an error describing why.
+    ///
+    /// As with `concatenate`, it's your responsibility to ensure that the two partial paths have
+    /// no variables in common, if that's needed for your use case.
+    pub fn can_concatenate(
+        &self,
+        rhs: &PartialPath,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+    ) -> Result<(), ConcatenationError> {
+        Self::compute_join(graph, partials, self, rhs)?;
+        Ok(())
+    }
+
     /// Attempts to append a partial path to this one.  If the postcondition of the “left” partial path
     /// is not compatible with the precondition of the “right” path, we return an error describing why.
     ///
@@ -2468,15 +2740,50 @@ impl PartialPath {
     /// common, then we ensure that the variables bind to the same values on both sides.  It's your
     /// responsibility to update the two partial paths so that they have no variables in common, if
     /// that's needed for your use case.
-    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
     pub fn concatenate(
         &mut self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         rhs: &PartialPath,
+    ) -> Result<(), PathResolutionError> {
+        self.concatenate_with_optional_fuel(graph, partials, rhs, None)
+    }
+
+    /// Attempts to append a partial path to this one, like [`concatenate`][Self::concatenate],
+    /// while bounding the amount of work that it is allowed to do.
+    ///
+    /// Each internal operation consumes one unit of `fuel`: every symbol and scope that takes part
+    /// in unifying the two paths at their join node, every symbol and scope of the conditions that
+    /// are rewritten with the resulting bindings, and every edge that is appended.  The fuel for
+    /// each step is consumed before the step is performed.  If there is not enough fuel left, we
+    /// return [`PathResolutionError::OutOfFuel`][] and leave this partial path unmodified.
+    pub fn concatenate_with_fuel(
+        &mut self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        rhs: &PartialPath,
+        fuel: &mut u64,
+    ) -> Result<(), PathResolutionError> {
+        self.concatenate_with_optional_fuel(graph, partials, rhs, Some(fuel))
+    }
+
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    fn concatenate_with_optional_fuel(
+        &mut self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        rhs: &PartialPath,
+        mut fuel: Option<&mut u64>,
     ) -> Result<(), PathResolutionError> {
         let lhs = self;
 
+        consume_fuel(
+            &mut fuel,
+            1 + lhs.symbol_stack_postcondition.len()
+                + lhs.scope_stack_postcondition.len()
+                + rhs.symbol_stack_precondition.len()
+                + rhs.scope_stack_precondition.len(),
+        )?;
         #[cfg_attr(not(feature = "copious-debugging"), allow(unused_mut))]
         let mut join = Self::compute_join(graph, partials, lhs, rhs)?;
         #[cfg(feature = "copious-debugging")]
@@ -2500,6 +2807,15 @@ impl PartialPath {
             );
         }
 
+        consume_fuel(
+            &mut fuel,
+            lhs.symbol_stack_precondition.len()
+                + rhs.symbol_stack_postcondition.len()
+                + lhs.scope_stack_precondition.len()
+                + rhs.scope_stack_postcondition.len()
+                + rhs.edges.len(),
+        )?;
+
         lhs.symbol_stack_precondition = lhs.symbol_stack_precondition.apply_partial_bindings(
             partials,
             &join.symbol_bindings,
@@ -2536,9 +2852,12 @@ impl PartialPath {
         partials: &mut PartialPaths,
         lhs: &PartialPath,
         rhs: &PartialPath,
-    ) -> Result<Join, PathResolutionError> {
+    ) -> Result<Join, ConcatenationError> {
         if lhs.end_node != rhs.start_node {
-            return Err(PathResolutionError::IncorrectSourceNode);
+            return Err(ConcatenationError::IncorrectJoinNode {
+                lhs_end_node: lhs.end_node,
+                rhs_start_node: rhs.start_node,
+            });
         }
 
         // Ensure the right post- and left precondition are half-open, so we can unify them.
@@ -2575,17 +2894,17 @@ impl PartialPath {
 
         let mut symbol_bindings = PartialSymbolStackBindings::new();
         let mut scope_bindings = PartialScopeStackBindings::new();
-        let unified_symbol_stack = lhs_symbol_stack_postcondition.unify(
-            partials,
-            rhs_symbol_stack_precondition,
-            &mut symbol_bindings,
-            &mut scope_bindings,
-        )?;
-        let unified_scope_stack = lhs_scope_stack_postcondition.unify(
-            partials,
-            rhs_scope_stack_precondition,
-            &mut scope_bindings,
-        )?;
+        let unified_symbol_stack = lhs_symbol_stack_postcondition
+            .unify(
+                partials,
+                rhs_symbol_stack_precondition,
+                &mut symbol_bindings,
+                &mut scope_bindings,
+            )
+            .map_err(ConcatenationError::IncompatibleSymbolStacks)?;
+        let unified_scope_stack = lhs_scope_stack_postcondition
+            .unify(partials, rhs_scope_stack_precondition, &mut scope_bindings)
+            .map_err(ConcatenationError::IncompatibleScopeStacks)?;
 
         Ok(Join {
             unified_symbol_stack,
@@ -2596,6 +2915,19 @@ impl PartialPath {
     }
 }
 
+/// Consumes `amount` units of fuel, if the operation is fuel-limited, failing without consuming
+/// anything if there is not enough fuel left.
+fn consume_fuel(fuel: &mut Option<&mut u64>, amount: usize) -> Result<(), PathResolutionError> {
+    if let Some(fuel) = fuel.as_deref_mut() {
+        let amount = amount as u64;
+        if *fuel < amount {
+            return Err(PathResolutionError::OutOfFuel);
+        }
+        *fuel -= amount;
+    }
+    Ok(())
+}
+
 struct Join {
     #[cfg_attr(not(feature = "copious-debugging"), allow(dead_code))]
     pub unified_symbol_stack: PartialSymbolStack,
@@ -2610,6 +2942,11 @@ struct Join {
 
 /// Manages the state of a collection of partial paths built up as part of the partial-path-finding
 /// algorithm or path-stitching algorithm.
+///
+/// Cloning a `PartialPaths` copies all of the partial paths' content, so that the clone can be
+/// used independently, for instance to stitch together the partial paths in a shared
+/// [`Database`][crate::stitching::Database] from several threads at once.
+#[derive(Clone)]
 pub struct PartialPaths {
     pub(crate) partial_symbol_stacks: DequeArena<PartialScopedSymbol>,
     pub(crate) partial_scope_stacks: DequeArena<Handle<Node>>,
@@ -45,6 +45,8 @@ pub enum PathResolutionError {
     /// The path contains a _pop scoped symbol_ node, but the symbol at the top of the symbol stack
     /// does not have an attached scope list to pop off.
     MissingAttachedScopeList,
+    /// The operation ran out of fuel before it could finish.
+    OutOfFuel,
     /// The path's scope stack does not satisfy the partial path's scope stack precondition.
     ScopeStackUnsatisfied,
     /// The path's symbol stack does not satisfy the partial path's symbol stack precondition.
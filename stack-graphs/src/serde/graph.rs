@@ -20,6 +20,16 @@ pub struct StackGraph {
     pub files: Files,
     pub nodes: Nodes,
     pub edges: Edges,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub file_debug_info: Vec<FileDebugInfo>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub file_languages: Vec<FileLanguage>,
 }
 
 #[derive(Debug, Error, Eq, PartialEq)]
@@ -34,6 +44,12 @@ pub enum Error {
     InvalidStackVariable(u32),
     #[error("failed to locate node `{0}` in graph")]
     NodeNotFound(NodeID),
+    #[error("failed to locate symbol `{0}` in graph")]
+    SymbolNotFound(String),
+    #[error("expected exactly one {0} node, found {1}")]
+    InvalidSingletonCount(&'static str, usize),
+    #[error("{0} node has unexpected id `{1}`")]
+    InvalidSingletonID(&'static str, NodeID),
 }
 
 impl StackGraph {
@@ -46,10 +62,14 @@ impl StackGraph {
         let files = graph.filter_files(&filter);
         let nodes = graph.filter_nodes(&filter);
         let edges = graph.filter_edges(&filter);
+        let file_debug_info = graph.filter_file_debug_info(&filter);
+        let file_languages = graph.filter_file_languages(&filter);
         Self {
             files,
             nodes,
             edges,
+            file_debug_info,
+            file_languages,
         }
     }
 
@@ -62,144 +82,60 @@ impl StackGraph {
 
     fn load_files(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
         for file in self.files.data.iter() {
-            graph
-                .add_file(&file)
-                .map_err(|_| Error::FileAlreadyPresent(file.to_owned()))?;
+            load_file(file, graph)?;
+        }
+        for debug_info in &self.file_debug_info {
+            debug_info.load_into(graph)?;
+        }
+        for language in &self.file_languages {
+            language.load_into(graph)?;
         }
 
         Ok(())
     }
 
     fn load_nodes(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+        let mut singletons = Singletons::default();
         for node in &self.nodes.data {
-            let handle = match node {
-                Node::DropScopes { id, .. } => {
-                    let node_id = id.to_node_id(graph)?;
-                    graph.add_drop_scopes_node(node_id)
-                }
-                Node::PopScopedSymbol {
-                    id,
-                    symbol,
-                    is_definition,
-                    ..
-                } => {
-                    let node_id = id.to_node_id(graph)?;
-                    let symbol_handle = graph.add_symbol(&symbol);
-                    graph.add_pop_scoped_symbol_node(node_id, symbol_handle, *is_definition)
-                }
-                Node::PopSymbol {
-                    id,
-                    symbol,
-                    is_definition,
-                    ..
-                } => {
-                    let node_id = id.to_node_id(graph)?;
-                    let symbol_handle = graph.add_symbol(&symbol);
-                    graph.add_pop_symbol_node(node_id, symbol_handle, *is_definition)
-                }
-                Node::PushScopedSymbol {
-                    id,
-                    symbol,
-                    scope,
-                    is_reference,
-                    ..
-                } => {
-                    let node_id = id.to_node_id(graph)?;
-                    let scope_id = scope.to_node_id(graph)?;
-                    let symbol_handle = graph.add_symbol(&symbol);
-                    graph.add_push_scoped_symbol_node(
-                        node_id,
-                        symbol_handle,
-                        scope_id,
-                        *is_reference,
-                    )
-                }
-                Node::PushSymbol {
-                    id,
-                    symbol,
-                    is_reference,
-                    ..
-                } => {
-                    let node_id = id.to_node_id(graph)?;
-                    let symbol_handle = graph.add_symbol(&symbol);
-                    graph.add_push_symbol_node(node_id, symbol_handle, *is_reference)
-                }
-                Node::Scope {
-                    id, is_exported, ..
-                } => {
-                    let node_id = id.to_node_id(graph)?;
-                    graph.add_scope_node(node_id, *is_exported)
-                }
-                Node::JumpToScope { .. } | Node::Root { .. } => None,
-            };
-
-            if let Some(handle) = handle {
-                // load source-info of each node
-                if let Some(source_info) = node.source_info() {
-                    *graph.source_info_mut(handle) = crate::graph::SourceInfo {
-                        span: source_info.span.clone(),
-                        syntax_type: source_info
-                            .syntax_type
-                            .as_ref()
-                            .map(|st| graph.add_string(&st))
-                            .into(),
-                        ..Default::default()
-                    };
-                }
-
-                // load debug-info of each node
-                if let Some(debug_info) = node.debug_info() {
-                    *graph.node_debug_info_mut(handle) = debug_info.data.iter().fold(
-                        crate::graph::DebugInfo::default(),
-                        |mut info, entry| {
-                            let key = graph.add_string(&entry.key);
-                            let value = graph.add_string(&entry.value);
-                            info.add(key, value);
-                            info
-                        },
-                    );
-                }
-            }
+            singletons.add(node)?;
+        }
+        singletons.check()?;
+        for node in &self.nodes.data {
+            node.load_into(graph)?;
         }
         Ok(())
     }
 
     fn load_edges(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
-        // load edges into stack-graph
-        for Edge {
-            source,
-            sink,
-            precedence,
-            debug_info,
-        } in &self.edges.data
-        {
-            let source_id = source.to_node_id(graph)?;
-            let sink_id = sink.to_node_id(graph)?;
-
-            let source_handle = graph
-                .node_for_id(source_id)
-                .ok_or(Error::InvalidGlobalNodeID(source.local_id))?;
-            let sink_handle = graph
-                .node_for_id(sink_id)
-                .ok_or(Error::InvalidGlobalNodeID(sink.local_id))?;
+        for edge in &self.edges.data {
+            edge.load_into(graph)?;
+        }
+        Ok(())
+    }
+}
 
-            graph.add_edge(source_handle, sink_handle, *precedence);
+fn load_file(file: &String, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+    graph
+        .add_file(&file)
+        .map_err(|_| Error::FileAlreadyPresent(file.to_owned()))?;
+    Ok(())
+}
 
-            // load debug-info of each node
-            if let Some(debug_info) = debug_info {
-                *graph.edge_debug_info_mut(source_handle, sink_handle) = debug_info
-                    .data
-                    .iter()
-                    .fold(crate::graph::DebugInfo::default(), |mut info, entry| {
-                        let key = graph.add_string(&entry.key);
-                        let value = graph.add_string(&entry.value);
-                        info.add(key, value);
-                        info
-                    });
-            }
+/// Like [`load_file`], but if the file is already present, removes its content instead of failing,
+/// so that the serialized content replaces it.
+#[cfg(feature = "serde")]
+fn replace_file(file: &String, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+    match graph.get_file(file) {
+        Some(handle) => {
+            graph.remove_file(handle);
+            *graph.file_debug_info_mut(handle) = crate::graph::DebugInfo::default();
+            graph.clear_file_language(handle);
+        }
+        None => {
+            graph.get_or_create_file(file);
         }
-        Ok(())
     }
+    Ok(())
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -241,6 +177,9 @@ pub enum Node {
 
     JumpToScope {
         id: NodeID,
+        /// Always `true`; marks this node as one of the graph's singleton nodes.
+        #[cfg_attr(feature = "serde", serde(default = "singleton_default"))]
+        singleton: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
     },
@@ -280,6 +219,9 @@ pub enum Node {
 
     Root {
         id: NodeID,
+        /// Always `true`; marks this node as one of the graph's singleton nodes.
+        #[cfg_attr(feature = "serde", serde(default = "singleton_default"))]
+        singleton: bool,
         source_info: Option<SourceInfo>,
         debug_info: Option<DebugInfo>,
     },
@@ -292,7 +234,198 @@ pub enum Node {
     },
 }
 
+#[cfg(feature = "serde")]
+fn singleton_default() -> bool {
+    true
+}
+
+/// Counts the singleton nodes of a serialized graph, so that we can verify that it contains
+/// exactly one root node and one jump-to node, each with its expected ID.
+#[derive(Default)]
+struct Singletons {
+    root: usize,
+    jump_to: usize,
+}
+
+impl Singletons {
+    fn add(&mut self, node: &Node) -> Result<(), Error> {
+        match node {
+            Node::Root { id, .. } => {
+                Self::check_id("root", id, crate::graph::ROOT_NODE_ID)?;
+                self.root += 1;
+            }
+            Node::JumpToScope { id, .. } => {
+                Self::check_id("jump-to", id, crate::graph::JUMP_TO_NODE_ID)?;
+                self.jump_to += 1;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn check_id(kind: &'static str, id: &NodeID, local_id: u32) -> Result<(), Error> {
+        if id.file.is_some() || id.local_id != local_id {
+            return Err(Error::InvalidSingletonID(kind, id.clone()));
+        }
+        Ok(())
+    }
+
+    fn check(&self) -> Result<(), Error> {
+        if self.root != 1 {
+            return Err(Error::InvalidSingletonCount("root", self.root));
+        }
+        if self.jump_to != 1 {
+            return Err(Error::InvalidSingletonCount("jump-to", self.jump_to));
+        }
+        Ok(())
+    }
+}
+
 impl Node {
+    fn load_into(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+        let node = self;
+        let handle = match node {
+            Node::DropScopes { id, .. } => {
+                let node_id = id.to_node_id(graph)?;
+                graph.add_drop_scopes_node(node_id)
+            }
+            Node::PopScopedSymbol {
+                id,
+                symbol,
+                is_definition,
+                ..
+            } => {
+                let node_id = id.to_node_id(graph)?;
+                let symbol_handle = graph.add_symbol(&symbol);
+                graph.add_pop_scoped_symbol_node(node_id, symbol_handle, *is_definition)
+            }
+            Node::PopSymbol {
+                id,
+                symbol,
+                is_definition,
+                ..
+            } => {
+                let node_id = id.to_node_id(graph)?;
+                let symbol_handle = graph.add_symbol(&symbol);
+                graph.add_pop_symbol_node(node_id, symbol_handle, *is_definition)
+            }
+            Node::PushScopedSymbol {
+                id,
+                symbol,
+                scope,
+                is_reference,
+                ..
+            } => {
+                let node_id = id.to_node_id(graph)?;
+                let scope_id = scope.to_node_id(graph)?;
+                let symbol_handle = graph.add_symbol(&symbol);
+                graph.add_push_scoped_symbol_node(node_id, symbol_handle, scope_id, *is_reference)
+            }
+            Node::PushSymbol {
+                id,
+                symbol,
+                is_reference,
+                ..
+            } => {
+                let node_id = id.to_node_id(graph)?;
+                let symbol_handle = graph.add_symbol(&symbol);
+                graph.add_push_symbol_node(node_id, symbol_handle, *is_reference)
+            }
+            Node::Scope {
+                id, is_exported, ..
+            } => {
+                let node_id = id.to_node_id(graph)?;
+                graph.add_scope_node(node_id, *is_exported)
+            }
+            Node::JumpToScope { .. } | Node::Root { .. } => None,
+        };
+
+        if let Some(handle) = handle {
+            // load source-info of each node
+            if let Some(source_info) = node.source_info() {
+                *graph.source_info_mut(handle) = crate::graph::SourceInfo {
+                    span: source_info.span.clone(),
+                    definiens_span: source_info.definiens_span.clone().unwrap_or_default(),
+                    syntax_type: source_info
+                        .syntax_type
+                        .as_ref()
+                        .map(|st| graph.add_string(&st))
+                        .into(),
+                    ..Default::default()
+                };
+            }
+
+            // load debug-info of each node
+            if let Some(debug_info) = node.debug_info() {
+                *graph.node_debug_info_mut(handle) = debug_info.to_debug_info(graph);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the ID of this node.
+    pub fn id(&self) -> &NodeID {
+        match self {
+            Self::DropScopes { id, .. }
+            | Self::JumpToScope { id, .. }
+            | Self::PopScopedSymbol { id, .. }
+            | Self::PopSymbol { id, .. }
+            | Self::PushScopedSymbol { id, .. }
+            | Self::PushSymbol { id, .. }
+            | Self::Root { id, .. }
+            | Self::Scope { id, .. } => id,
+        }
+    }
+
+    /// Removes the source and debug info of this node, leaving only its structure.
+    pub(crate) fn clear_info(&mut self) {
+        match self {
+            Self::DropScopes {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::JumpToScope {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::PopScopedSymbol {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::PopSymbol {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::PushScopedSymbol {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::PushSymbol {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::Root {
+                source_info,
+                debug_info,
+                ..
+            }
+            | Self::Scope {
+                source_info,
+                debug_info,
+                ..
+            } => {
+                *source_info = None;
+                *debug_info = None;
+            }
+        }
+    }
+
     fn source_info(&self) -> Option<&SourceInfo> {
         match self {
             Self::DropScopes { source_info, .. } => source_info,
@@ -331,6 +464,8 @@ impl Node {
 #[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
 pub struct SourceInfo {
     pub span: lsp_positions::Span,
+    /// The span of the node's definiens, if it has one.
+    pub definiens_span: Option<lsp_positions::Span>,
     pub syntax_type: Option<String>,
 }
 
@@ -353,7 +488,58 @@ pub struct DebugEntry {
     pub value: String,
 }
 
+impl DebugInfo {
+    fn to_debug_info(&self, graph: &mut crate::graph::StackGraph) -> crate::graph::DebugInfo {
+        self.data
+            .iter()
+            .fold(crate::graph::DebugInfo::default(), |mut info, entry| {
+                let key = graph.add_string(&entry.key);
+                let value = graph.add_string(&entry.value);
+                info.add(key, value);
+                info
+            })
+    }
+}
+
+/// The debug info of a file, which refers to the file by name.
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+pub struct FileDebugInfo {
+    pub file: String,
+    pub debug_info: DebugInfo,
+}
+
+impl FileDebugInfo {
+    fn load_into(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+        let file = graph
+            .get_file(&self.file)
+            .ok_or_else(|| Error::FileNotFound(self.file.clone()))?;
+        *graph.file_debug_info_mut(file) = self.debug_info.to_debug_info(graph);
+        Ok(())
+    }
+}
+
+/// The language of a file, which refers to the file by name.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
+pub struct FileLanguage {
+    pub file: String,
+    pub language: String,
+}
+
+impl FileLanguage {
+    fn load_into(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+        let file = graph
+            .get_file(&self.file)
+            .ok_or_else(|| Error::FileNotFound(self.file.clone()))?;
+        graph.set_file_language(file, &self.language);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(
     feature = "serde",
     serde_with::skip_serializing_none, // must come before derive
@@ -437,9 +623,54 @@ pub struct Edge {
     pub source: NodeID,
     pub sink: NodeID,
     pub precedence: i32,
+    #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "is_zero"))]
+    pub cost: u32,
     pub debug_info: Option<DebugInfo>,
 }
 
+#[cfg(feature = "serde")]
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+impl Edge {
+    fn load_into(&self, graph: &mut crate::graph::StackGraph) -> Result<(), Error> {
+        let Edge {
+            source,
+            sink,
+            precedence,
+            cost,
+            debug_info,
+        } = self;
+        let source_id = source.to_node_id(graph)?;
+        let sink_id = sink.to_node_id(graph)?;
+
+        let source_handle = graph
+            .node_for_id(source_id)
+            .ok_or(Error::InvalidGlobalNodeID(source.local_id))?;
+        let sink_handle = graph
+            .node_for_id(sink_id)
+            .ok_or(Error::InvalidGlobalNodeID(sink.local_id))?;
+
+        graph.add_edge(source_handle, sink_handle, *precedence);
+        graph.set_edge_cost(source_handle, sink_handle, *cost);
+
+        // load debug-info of each node
+        if let Some(debug_info) = debug_info {
+            *graph.edge_debug_info_mut(source_handle, sink_handle) = debug_info.data.iter().fold(
+                crate::graph::DebugInfo::default(),
+                |mut info, entry| {
+                    let key = graph.add_string(&entry.key);
+                    let value = graph.add_string(&entry.value);
+                    info.add(key, value);
+                    info
+                },
+            );
+        }
+        Ok(())
+    }
+}
+
 impl crate::graph::StackGraph {
     pub fn to_serializable(&self) -> StackGraph {
         self.to_serializable_filter(&NoFilter)
@@ -472,10 +703,45 @@ impl crate::graph::StackGraph {
     ) -> Option<SourceInfo> {
         self.source_info(handle).map(|info| SourceInfo {
             span: info.span.clone(),
+            definiens_span: Some(info.definiens_span.clone())
+                .filter(|span| *span != lsp_positions::Span::default()),
             syntax_type: info.syntax_type.into_option().map(|ty| self[ty].to_owned()),
         })
     }
 
+    fn filter_file_debug_info(&self, filter: &dyn Filter) -> Vec<FileDebugInfo> {
+        self.iter_files()
+            .filter(|f| filter.include_file(self, f))
+            .filter_map(|f| {
+                let info = self.file_debug_info(f)?;
+                Some(FileDebugInfo {
+                    file: self[f].name().to_owned(),
+                    debug_info: DebugInfo {
+                        data: info
+                            .iter()
+                            .map(|entry| DebugEntry {
+                                key: self[entry.key].to_owned(),
+                                value: self[entry.value].to_owned(),
+                            })
+                            .collect(),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    fn filter_file_languages(&self, filter: &dyn Filter) -> Vec<FileLanguage> {
+        self.iter_files()
+            .filter(|f| filter.include_file(self, f))
+            .filter_map(|f| {
+                Some(FileLanguage {
+                    file: self[f].name().to_owned(),
+                    language: self.file_language(f)?.to_owned(),
+                })
+            })
+            .collect()
+    }
+
     fn filter_node_debug_info<'a>(
         &self,
         _filter: &'a dyn Filter,
@@ -497,88 +763,93 @@ impl crate::graph::StackGraph {
             data: self
                 .iter_nodes()
                 .filter(|n| filter.include_node(self, &n))
-                .map(|handle| {
-                    let node = &self[handle];
-                    let id = self.filter_node(filter, node.id());
-                    let source_info = self.filter_source_info(filter, handle);
-                    let debug_info = self.filter_node_debug_info(filter, handle);
-
-                    match node {
-                        crate::graph::Node::DropScopes(_node) => Node::DropScopes {
-                            id,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::JumpTo(_node) => Node::JumpToScope {
-                            id,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::PopScopedSymbol(node) => Node::PopScopedSymbol {
-                            id,
-                            symbol: self[node.symbol].to_owned(),
-                            is_definition: node.is_definition,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::PopSymbol(node) => Node::PopSymbol {
-                            id,
-                            symbol: self[node.symbol].to_owned(),
-                            is_definition: node.is_definition,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::PushScopedSymbol(node) => Node::PushScopedSymbol {
-                            id,
-                            symbol: self[node.symbol].to_owned(),
-                            scope: self.filter_node(filter, node.scope),
-                            is_reference: node.is_reference,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::PushSymbol(node) => Node::PushSymbol {
-                            id,
-                            symbol: self[node.symbol].to_owned(),
-                            is_reference: node.is_reference,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::Root(_node) => Node::Root {
-                            id,
-                            source_info,
-                            debug_info,
-                        },
-                        crate::graph::Node::Scope(node) => Node::Scope {
-                            id,
-                            is_exported: node.is_exported,
-                            source_info,
-                            debug_info,
-                        },
-                    }
-                })
+                .map(|handle| self.filter_node_data(filter, handle))
                 .collect::<Vec<_>>(),
         }
     }
 
+    fn filter_node_data(&self, filter: &dyn Filter, handle: Handle<crate::graph::Node>) -> Node {
+        let node = &self[handle];
+        let id = self.filter_node(filter, node.id());
+        let source_info = self.filter_source_info(filter, handle);
+        let debug_info = self.filter_node_debug_info(filter, handle);
+
+        match node {
+            crate::graph::Node::DropScopes(_node) => Node::DropScopes {
+                id,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::JumpTo(_node) => Node::JumpToScope {
+                id,
+                singleton: true,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::PopScopedSymbol(node) => Node::PopScopedSymbol {
+                id,
+                symbol: self[node.symbol].to_owned(),
+                is_definition: node.is_definition,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::PopSymbol(node) => Node::PopSymbol {
+                id,
+                symbol: self[node.symbol].to_owned(),
+                is_definition: node.is_definition,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::PushScopedSymbol(node) => Node::PushScopedSymbol {
+                id,
+                symbol: self[node.symbol].to_owned(),
+                scope: self.filter_node(filter, node.scope),
+                is_reference: node.is_reference,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::PushSymbol(node) => Node::PushSymbol {
+                id,
+                symbol: self[node.symbol].to_owned(),
+                is_reference: node.is_reference,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::Root(_node) => Node::Root {
+                id,
+                singleton: true,
+                source_info,
+                debug_info,
+            },
+            crate::graph::Node::Scope(node) => Node::Scope {
+                id,
+                is_exported: node.is_exported,
+                source_info,
+                debug_info,
+            },
+        }
+    }
+
     fn filter_edges<'a>(&self, filter: &'a dyn Filter) -> Edges {
         Edges {
             data: self
-                .iter_nodes()
-                .map(|source| {
-                    self.outgoing_edges(source)
-                        .filter(|e| filter.include_edge(self, &e.source, &e.sink))
-                        .map(|e| Edge {
-                            source: self.filter_node(filter, self[e.source].id()),
-                            sink: self.filter_node(filter, self[e.sink].id()),
-                            precedence: e.precedence,
-                            debug_info: self.filter_edge_debug_info(filter, e.source, e.sink),
-                        })
-                })
-                .flatten()
+                .all_edges()
+                .filter(|e| filter.include_edge(self, &e.source, &e.sink))
+                .map(|e| self.filter_edge_data(filter, e))
                 .collect::<Vec<_>>(),
         }
     }
 
+    fn filter_edge_data(&self, filter: &dyn Filter, edge: crate::graph::Edge) -> Edge {
+        Edge {
+            source: self.filter_node(filter, self[edge.source].id()),
+            sink: self.filter_node(filter, self[edge.sink].id()),
+            precedence: edge.precedence,
+            cost: edge.cost,
+            debug_info: self.filter_edge_debug_info(filter, edge.source, edge.sink),
+        }
+    }
+
     fn filter_edge_debug_info<'a>(
         &self,
         _filter: &'a dyn Filter,
@@ -597,3 +868,441 @@ impl crate::graph::StackGraph {
             })
     }
 }
+
+#[cfg(feature = "serde")]
+impl crate::graph::StackGraph {
+    /// Reads a stack graph from a JSON document in the format produced by serializing the result
+    /// of [`to_serializable`][Self::to_serializable].  Unlike deserializing a
+    /// [`serde::StackGraph`][StackGraph] and calling [`load_into`][StackGraph::load_into], this
+    /// streams the document: nodes and edges are added to the graph one at a time as they are
+    /// parsed, so the document never has to be fully resident in memory.  Nodes or edges that
+    /// appear before the files or nodes they refer to are buffered until the end of the document.
+    pub fn read_from_reader<R: std::io::Read>(reader: R) -> Result<Self, serde_json::Error> {
+        use serde::de::DeserializeSeed;
+        let mut graph = Self::new();
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        GraphSeed(&mut graph, load_file).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(graph)
+    }
+
+    /// Reads a JSON document in the same format as [`read_from_reader`][Self::read_from_reader],
+    /// adding its content to this graph instead of a new one.  Symbols are interned in this graph
+    /// and node handles are allocated as needed, so the files that are already present stay
+    /// available for resolution while the document is being read.  If the document contains a
+    /// file that is already present in this graph, the existing content of that file is removed
+    /// (as with [`remove_file`][Self::remove_file]) and replaced by the serialized content.
+    pub fn extend_from_json<R: std::io::Read>(
+        &mut self,
+        reader: R,
+    ) -> Result<(), serde_json::Error> {
+        use serde::de::DeserializeSeed;
+        let mut deserializer = serde_json::Deserializer::from_reader(reader);
+        GraphSeed(self, replace_file).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(())
+    }
+
+    /// Reads only the source info of the nodes in a JSON document in the same format as
+    /// [`read_from_reader`][Self::read_from_reader], without loading the rest of the graph.  The
+    /// result is keyed by the [global ID][Self::global_id] that each node would have in the graph
+    /// returned by [`read_from_reader`][Self::read_from_reader] for the same document.  Nodes
+    /// without source info are omitted.  Edges, debug info, and the other properties of nodes are
+    /// skipped while parsing, so this is cheaper than loading the graph when only positions are
+    /// needed, for instance to look up the definiens span of a definition.
+    pub fn read_source_info_only<R: std::io::Read>(
+        reader: R,
+    ) -> Result<std::collections::HashMap<u64, SourceInfo>, serde_json::Error> {
+        use serde::de::Error as _;
+        let document: SourceInfoDocument = serde_json::from_reader(reader)?;
+        // Files are added in document order, and file handles start at 1.
+        let files = document
+            .files
+            .data
+            .iter()
+            .enumerate()
+            .map(|(index, file)| (file.as_str(), index as u64 + 1))
+            .collect::<std::collections::HashMap<_, _>>();
+        let mut result = std::collections::HashMap::new();
+        for node in document.nodes {
+            let source_info = match node.source_info {
+                Some(source_info) => source_info,
+                None => continue,
+            };
+            let file = match &node.id.file {
+                Some(file) => *files
+                    .get(file.as_str())
+                    .ok_or_else(|| serde_json::Error::custom(Error::FileNotFound(file.clone())))?,
+                None => 0,
+            };
+            result.insert((file << 32) | u64::from(node.id.local_id), source_info);
+        }
+        Ok(result)
+    }
+
+    /// Writes some of the files of this stack graph as a JSON document, in the same format as
+    /// [`to_serializable`][Self::to_serializable].  The document contains the given files, their
+    /// nodes, all edges that start at one of those nodes, and all edges from the singleton _root_
+    /// and _jump to scope_ nodes to one of those nodes.  That means that writing each file of a
+    /// graph separately writes every edge exactly once, except for edges between the singleton
+    /// nodes themselves.
+    ///
+    /// An edge that crosses into a file that is not written refers to its sink node by the file's
+    /// name.  Loading such a document via [`load_into`][StackGraph::load_into] requires that the
+    /// sink node already exists in the graph being loaded into.
+    pub fn write_partial<W: std::io::Write>(
+        &self,
+        files: &[Handle<crate::graph::File>],
+        writer: W,
+    ) -> Result<(), serde_json::Error> {
+        let filter =
+            |_: &crate::graph::StackGraph, file: &Handle<crate::graph::File>| files.contains(file);
+        let filter = ImplicationFilter(&filter);
+        let in_files = |node: Handle<crate::graph::Node>| matches!(self[node].file(), Some(file) if files.contains(&file));
+        let edges = self
+            .all_edges()
+            .filter(|e| in_files(e.source) || (self[e.source].file().is_none() && in_files(e.sink)))
+            .map(|e| self.filter_edge_data(&filter, e))
+            .collect::<Vec<_>>();
+        let graph = StackGraph {
+            files: self.filter_files(&filter),
+            nodes: self.filter_nodes(&filter),
+            edges: Edges { data: edges },
+            file_debug_info: self.filter_file_debug_info(&filter),
+            file_languages: self.filter_file_languages(&filter),
+        };
+        serde_json::to_writer(writer, &graph)
+    }
+
+    /// Writes this stack graph as newline-delimited JSON, with one record per line.  A record is
+    /// written for each node, followed by a record for each edge.  Each record has a `type` field,
+    /// which is either `node` or `edge`.  Node records contain the node's
+    /// [global ID][crate::graph::StackGraph::global_id] in their `id` field, and the node in the
+    /// same format as [`to_serializable`][Self::to_serializable] in their `node` field.  Edge
+    /// records refer to their `source` and `sink` nodes by global ID.
+    ///
+    /// Records are written as they are produced, so the serialized graph never has to be fully
+    /// resident in memory.
+    pub fn write_jsonl<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
+        for handle in self.iter_nodes() {
+            let node = self.filter_node_data(&NoFilter, handle);
+            let record = JsonlRecord::Node {
+                id: self.global_id(handle),
+                node: &node,
+            };
+            serde_json::to_writer(&mut writer, &record)?;
+            writeln!(writer)?;
+        }
+        for edge in self.all_edges() {
+            let record = JsonlRecord::Edge {
+                source: self.global_id(edge.source),
+                sink: self.global_id(edge.sink),
+                precedence: edge.precedence,
+                cost: edge.cost,
+                debug_info: self.filter_edge_debug_info(&NoFilter, edge.source, edge.sink),
+            };
+            serde_json::to_writer(&mut writer, &record)?;
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+}
+
+/// The magic bytes at the start of every binary stack graph.
+#[cfg(feature = "bincode")]
+const BINARY_MAGIC: &[u8; 4] = b"SGBN";
+
+/// The version of the binary stack graph format.  This must be incremented whenever the layout
+/// of the serialized types changes.
+#[cfg(feature = "bincode")]
+const BINARY_VERSION: u32 = 6;
+
+/// An error that can occur while reading or writing a binary stack graph.
+#[cfg(feature = "bincode")]
+#[derive(Debug, Error)]
+pub enum BinaryError {
+    #[error("not a binary stack graph")]
+    InvalidHeader,
+    #[error("unsupported binary stack graph version {0}")]
+    UnsupportedVersion(u32),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Encode(#[from] bincode::error::EncodeError),
+    #[error(transparent)]
+    Decode(#[from] bincode::error::DecodeError),
+    #[error(transparent)]
+    Load(#[from] Error),
+    #[error("failed to load partial path at index {index}: {error}")]
+    InvalidPath { index: usize, error: Error },
+}
+
+/// Writes the magic bytes and version that start a binary document.
+#[cfg(feature = "bincode")]
+pub(super) fn write_binary_header<W: std::io::Write>(
+    mut writer: W,
+    magic: &[u8; 4],
+    version: u32,
+) -> Result<(), BinaryError> {
+    writer.write_all(magic)?;
+    bincode::encode_into_std_write(version, &mut writer, bincode::config::standard())?;
+    Ok(())
+}
+
+/// Reads the magic bytes and version that start a binary document, and checks that they match
+/// the expected ones.
+#[cfg(feature = "bincode")]
+pub(super) fn read_binary_header<R: std::io::Read>(
+    mut reader: R,
+    expected_magic: &[u8; 4],
+    expected_version: u32,
+) -> Result<(), BinaryError> {
+    let mut magic = [0u8; 4];
+    reader
+        .read_exact(&mut magic)
+        .map_err(|_| BinaryError::InvalidHeader)?;
+    if &magic != expected_magic {
+        return Err(BinaryError::InvalidHeader);
+    }
+    let version: u32 = bincode::decode_from_std_read(&mut reader, bincode::config::standard())?;
+    if version != expected_version {
+        return Err(BinaryError::UnsupportedVersion(version));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "bincode")]
+impl crate::graph::StackGraph {
+    /// Writes this stack graph in a compact binary format, including all source and debug info.
+    /// The format starts with a version tag, so that [`read_binary`][Self::read_binary] can reject
+    /// graphs that were written with an incompatible layout.
+    pub fn write_binary<W: std::io::Write>(&self, mut writer: W) -> Result<(), BinaryError> {
+        let config = bincode::config::standard();
+        write_binary_header(&mut writer, BINARY_MAGIC, BINARY_VERSION)?;
+        bincode::encode_into_std_write(self.to_serializable(), &mut writer, config)?;
+        Ok(())
+    }
+
+    /// Reads a stack graph that was written by [`write_binary`][Self::write_binary].  Returns an
+    /// error if the data was not written by `write_binary`, or by a version of this crate with an
+    /// incompatible binary format.
+    pub fn read_binary<R: std::io::Read>(mut reader: R) -> Result<Self, BinaryError> {
+        let config = bincode::config::standard();
+        read_binary_header(&mut reader, BINARY_MAGIC, BINARY_VERSION)?;
+        let serialized: StackGraph = bincode::decode_from_std_read(&mut reader, config)?;
+        let mut graph = Self::new();
+        serialized.load_into(&mut graph)?;
+        Ok(graph)
+    }
+}
+
+/// A single line of the newline-delimited JSON written by
+/// [`write_jsonl`][crate::graph::StackGraph::write_jsonl].
+#[cfg(feature = "serde")]
+#[serde_with::skip_serializing_none]
+#[derive(serde::Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum JsonlRecord<'a> {
+    Node {
+        id: u64,
+        node: &'a Node,
+    },
+    Edge {
+        source: u64,
+        sink: u64,
+        precedence: i32,
+        #[serde(skip_serializing_if = "is_zero")]
+        cost: u32,
+        debug_info: Option<DebugInfo>,
+    },
+}
+
+/// The parts of a serialized stack graph that are needed by
+/// [`StackGraph::read_source_info_only`][crate::graph::StackGraph::read_source_info_only].  All
+/// other fields are skipped.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SourceInfoDocument {
+    files: Files,
+    nodes: Vec<NodeSourceInfo>,
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct NodeSourceInfo {
+    id: NodeID,
+    #[serde(default)]
+    source_info: Option<SourceInfo>,
+}
+
+/// Loads a serialized stack graph into an existing graph while it is being deserialized, using the
+/// given function to load each file.
+#[cfg(feature = "serde")]
+struct GraphSeed<'a>(
+    &'a mut crate::graph::StackGraph,
+    fn(&String, &mut crate::graph::StackGraph) -> Result<(), Error>,
+);
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::DeserializeSeed<'de> for GraphSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(
+            "StackGraph",
+            &[
+                "files",
+                "nodes",
+                "edges",
+                "file_debug_info",
+                "file_languages",
+            ],
+            self,
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for GraphSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a stack graph")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::MapAccess<'de>,
+    {
+        use serde::de::Error as _;
+        let graph = self.0;
+        let mut load_file = self.1;
+        let mut files_loaded = false;
+        let mut nodes_loaded = false;
+        let mut edges_loaded = false;
+        let mut pending_nodes = None;
+        let mut pending_edges = None;
+        let mut pending_file_debug_info = None;
+        let mut pending_file_languages = None;
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "files" => {
+                    map.next_value_seed(ElementsSeed(graph, &mut load_file))?;
+                    files_loaded = true;
+                }
+                "nodes" if files_loaded => {
+                    let mut singletons = Singletons::default();
+                    let mut load_node = |node: &Node, graph: &mut crate::graph::StackGraph| {
+                        singletons.add(node)?;
+                        node.load_into(graph)
+                    };
+                    map.next_value_seed(ElementsSeed(graph, &mut load_node))?;
+                    singletons.check().map_err(A::Error::custom)?;
+                    nodes_loaded = true;
+                }
+                "nodes" => pending_nodes = Some(map.next_value::<Nodes>()?),
+                "edges" if nodes_loaded => {
+                    map.next_value_seed(ElementsSeed(graph, &mut Edge::load_into))?;
+                    edges_loaded = true;
+                }
+                "edges" => pending_edges = Some(map.next_value::<Edges>()?),
+                "file_debug_info" if files_loaded => {
+                    map.next_value_seed(ElementsSeed(graph, &mut FileDebugInfo::load_into))?;
+                }
+                "file_debug_info" => {
+                    pending_file_debug_info = Some(map.next_value::<Vec<FileDebugInfo>>()?)
+                }
+                "file_languages" if files_loaded => {
+                    map.next_value_seed(ElementsSeed(graph, &mut FileLanguage::load_into))?;
+                }
+                "file_languages" => {
+                    pending_file_languages = Some(map.next_value::<Vec<FileLanguage>>()?)
+                }
+                _ => {
+                    map.next_value::<serde::de::IgnoredAny>()?;
+                }
+            }
+        }
+        if !files_loaded {
+            return Err(A::Error::missing_field("files"));
+        }
+        for debug_info in pending_file_debug_info.iter().flatten() {
+            debug_info.load_into(graph).map_err(A::Error::custom)?;
+        }
+        for language in pending_file_languages.iter().flatten() {
+            language.load_into(graph).map_err(A::Error::custom)?;
+        }
+        if let Some(nodes) = pending_nodes {
+            let mut singletons = Singletons::default();
+            for node in &nodes.data {
+                singletons.add(node).map_err(A::Error::custom)?;
+            }
+            singletons.check().map_err(A::Error::custom)?;
+            for node in &nodes.data {
+                node.load_into(graph).map_err(A::Error::custom)?;
+            }
+            nodes_loaded = true;
+        }
+        if !nodes_loaded {
+            return Err(A::Error::missing_field("nodes"));
+        }
+        if let Some(edges) = pending_edges {
+            for edge in &edges.data {
+                edge.load_into(graph).map_err(A::Error::custom)?;
+            }
+            edges_loaded = true;
+        }
+        if !edges_loaded {
+            return Err(A::Error::missing_field("edges"));
+        }
+        Ok(())
+    }
+}
+
+/// Loads each element of a serialized sequence into a stack graph as soon as it is deserialized.
+#[cfg(feature = "serde")]
+struct ElementsSeed<'a, T>(
+    &'a mut crate::graph::StackGraph,
+    &'a mut dyn FnMut(&T, &mut crate::graph::StackGraph) -> Result<(), Error>,
+);
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::de::DeserializeSeed<'de> for ElementsSeed<'_, T>
+where
+    T: serde::Deserialize<'de>,
+{
+    type Value = ();
+
+    fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::de::Visitor<'de> for ElementsSeed<'_, T>
+where
+    T: serde::Deserialize<'de>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a sequence")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: serde::de::SeqAccess<'de>,
+    {
+        use serde::de::Error as _;
+        while let Some(element) = seq.next_element::<T>()? {
+            (self.1)(&element, self.0).map_err(A::Error::custom)?;
+        }
+        Ok(())
+    }
+}
@@ -231,7 +231,7 @@ impl SymbolStackVariable {
 )]
 #[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
 pub struct PartialScopedSymbol {
-    symbol: String,
+    pub(crate) symbol: String,
     pub(crate) scopes: Option<PartialScopeStack>,
 }
 
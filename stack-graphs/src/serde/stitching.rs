@@ -5,14 +5,36 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+#[cfg(feature = "serde")]
+use thiserror::Error as ThisError;
+
 use crate::graph::StackGraph;
 use crate::partial::PartialPaths;
+#[cfg(feature = "serde")]
+use crate::stitching::ForwardPartialPathStitcher;
+#[cfg(feature = "serde")]
+use crate::stitching::StitcherConfig;
+#[cfg(feature = "serde")]
+use crate::stitching::StitchingError;
+#[cfg(feature = "serde")]
+use crate::CancellationError;
+#[cfg(feature = "serde")]
+use crate::CancellationFlag;
+#[cfg(feature = "serde")]
+use crate::NoCancellation;
 
+#[cfg(feature = "bincode")]
+use super::graph::read_binary_header;
+#[cfg(feature = "bincode")]
+use super::graph::write_binary_header;
+#[cfg(feature = "bincode")]
+use super::BinaryError;
 use super::Error;
 use super::Filter;
 use super::ImplicationFilter;
 use super::NoFilter;
 use super::PartialPath;
+use super::StackGraph as SerializableStackGraph;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 #[cfg_attr(
@@ -20,6 +42,7 @@ use super::PartialPath;
     derive(serde::Deserialize, serde::Serialize),
     serde(transparent)
 )]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
 pub struct Database {
     paths: Vec<PartialPath>,
 }
@@ -52,6 +75,35 @@ impl Database {
         Self { paths }
     }
 
+    /// Converts all partial paths back into the given graph, after checking that every node and
+    /// symbol that they refer to already exists in it.  Returns the index of the first partial
+    /// path that cannot be loaded, together with the reason.
+    #[cfg(any(feature = "serde", feature = "bincode"))]
+    fn validated_paths(
+        &self,
+        graph: &mut crate::graph::StackGraph,
+        partials: &mut PartialPaths,
+    ) -> Result<Vec<crate::partial::PartialPath>, (usize, Error)> {
+        let mut paths = Vec::with_capacity(self.paths.len());
+        for (index, path) in self.paths.iter().enumerate() {
+            let symbols = path
+                .symbol_stack_precondition
+                .symbols
+                .iter()
+                .chain(path.symbol_stack_postcondition.symbols.iter());
+            for symbol in symbols {
+                if graph.get_symbol(&symbol.symbol).is_none() {
+                    return Err((index, Error::SymbolNotFound(symbol.symbol.clone())));
+                }
+            }
+            paths.push(
+                path.to_partial_path(graph, partials)
+                    .map_err(|error| (index, error))?,
+            );
+        }
+        Ok(paths)
+    }
+
     pub fn load_into(
         &self,
         graph: &mut crate::graph::StackGraph,
@@ -79,4 +131,213 @@ impl crate::stitching::Database {
     ) -> Database {
         Database::from_database_filter(graph, partials, self, filter)
     }
+
+    /// Reads a serialized list of partial paths from a JSON document, and adds them to this
+    /// database.  The partial paths are loaded against the given graph: every node that they
+    /// refer to must already exist in the graph, and so must every symbol on their symbol stacks.
+    /// If any partial path cannot be loaded, an error identifying it is returned, and none of the
+    /// partial paths are added to the database.
+    #[cfg(feature = "serde")]
+    pub fn load_from_json<R: std::io::Read>(
+        &mut self,
+        reader: R,
+        graph: &mut StackGraph,
+        partials: &mut PartialPaths,
+    ) -> Result<(), DatabaseLoadError> {
+        let database: Database = serde_json::from_reader(reader)?;
+        self.load_validated(&database, graph, partials)
+    }
+
+    /// Adds the partial paths of a deserialized database to this database, after checking that
+    /// every node and symbol that they refer to already exists in the graph.
+    #[cfg(feature = "serde")]
+    fn load_validated(
+        &mut self,
+        database: &Database,
+        graph: &mut StackGraph,
+        partials: &mut PartialPaths,
+    ) -> Result<(), DatabaseLoadError> {
+        let paths = database
+            .validated_paths(graph, partials)
+            .map_err(|(index, error)| DatabaseLoadError::InvalidPath { index, error })?;
+        for path in paths {
+            self.add_partial_path(graph, partials, path);
+        }
+        Ok(())
+    }
+}
+
+/// The magic bytes at the start of every binary partial path database.
+#[cfg(feature = "bincode")]
+const DATABASE_BINARY_MAGIC: &[u8; 4] = b"SGPP";
+
+/// The version of the binary partial path database format.  This must be incremented whenever
+/// the layout of the serialized types changes.
+#[cfg(feature = "bincode")]
+const DATABASE_BINARY_VERSION: u32 = 1;
+
+#[cfg(feature = "bincode")]
+impl crate::stitching::Database {
+    /// Writes the partial paths in this database in a compact binary format.  Nodes are
+    /// identified by their file and local ID, and symbols by their content, so the result can be
+    /// loaded into a different instance of the same graph, such as one that was read back with
+    /// [`StackGraph::read_binary`][crate::graph::StackGraph::read_binary].  The format starts with
+    /// a version tag, so that [`load_from_binary`][Self::load_from_binary] can reject databases
+    /// that were written with an incompatible layout.
+    pub fn write_binary<W: std::io::Write>(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        mut writer: W,
+    ) -> Result<(), BinaryError> {
+        write_binary_header(&mut writer, DATABASE_BINARY_MAGIC, DATABASE_BINARY_VERSION)?;
+        bincode::encode_into_std_write(
+            self.to_serializable(graph, partials),
+            &mut writer,
+            bincode::config::standard(),
+        )?;
+        Ok(())
+    }
+
+    /// Reads partial paths that were written by [`write_binary`][Self::write_binary], and adds
+    /// them to this database.  Like [`load_from_json`][Self::load_from_json], every node and
+    /// symbol that the partial paths refer to must already exist in the graph, and none of the
+    /// partial paths are added if any of them cannot be loaded.
+    pub fn load_from_binary<R: std::io::Read>(
+        &mut self,
+        mut reader: R,
+        graph: &mut StackGraph,
+        partials: &mut PartialPaths,
+    ) -> Result<(), BinaryError> {
+        read_binary_header(&mut reader, DATABASE_BINARY_MAGIC, DATABASE_BINARY_VERSION)?;
+        let database: Database =
+            bincode::decode_from_std_read(&mut reader, bincode::config::standard())?;
+        let paths = database
+            .validated_paths(graph, partials)
+            .map_err(|(index, error)| BinaryError::InvalidPath { index, error })?;
+        for path in paths {
+            self.add_partial_path(graph, partials, path);
+        }
+        Ok(())
+    }
+}
+
+impl PartialPaths {
+    /// Writes the minimal set of partial paths of every file in a stack graph as pretty-printed
+    /// JSON, for debugging.  Each partial path includes its start and end nodes, its symbol and
+    /// scope stack pre- and postconditions, and its edges.  The document has the same format as a
+    /// serialized [`Database`][], so it can also be loaded with
+    /// [`Database::load_from_json`][crate::stitching::Database::load_from_json].
+    #[cfg(feature = "serde")]
+    pub fn write_all_json<W: std::io::Write>(
+        &mut self,
+        graph: &StackGraph,
+        writer: W,
+    ) -> Result<(), serde_json::Error> {
+        match self.write_all_json_with_cancellation(graph, writer, &NoCancellation) {
+            Ok(()) => Ok(()),
+            Err(WriteError::Json(error)) => Err(error),
+            Err(WriteError::Cancelled(_)) => unreachable!("should never be cancelled"),
+        }
+    }
+
+    /// Like [`write_all_json`][Self::write_all_json], but checks the cancellation flag while
+    /// finding the partial paths.  Nothing is written if the flag fires.
+    #[cfg(feature = "serde")]
+    pub fn write_all_json_with_cancellation<W: std::io::Write>(
+        &mut self,
+        graph: &StackGraph,
+        writer: W,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), WriteError> {
+        let mut paths = Vec::new();
+        for file in graph.iter_files() {
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                graph,
+                self,
+                file,
+                StitcherConfig::default(),
+                cancellation_flag,
+                |graph, partials, path| {
+                    paths.push(PartialPath::from_partial_path(graph, partials, path));
+                },
+            )
+            .map_err(StitchingError::into_candidates_error)?;
+        }
+        serde_json::to_writer_pretty(writer, &Database { paths })?;
+        Ok(())
+    }
+}
+
+/// A stack graph together with a database of partial paths in that graph, which are serialized
+/// as a single document.  See
+/// [`StackGraph::write_with_partials`][crate::graph::StackGraph::write_with_partials].
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+pub struct StackGraphWithPartials {
+    pub graph: SerializableStackGraph,
+    pub database: Database,
+}
+
+#[cfg(feature = "serde")]
+impl StackGraph {
+    /// Writes this stack graph together with a database of partial paths in it as a single JSON
+    /// document, so that both can be cached and restored at once with
+    /// [`read_with_partials`][Self::read_with_partials].
+    pub fn write_with_partials<W: std::io::Write>(
+        &self,
+        partials: &mut PartialPaths,
+        db: &crate::stitching::Database,
+        writer: W,
+    ) -> Result<(), serde_json::Error> {
+        let document = StackGraphWithPartials {
+            graph: self.to_serializable(),
+            database: db.to_serializable(self, partials),
+        };
+        serde_json::to_writer(writer, &document)
+    }
+
+    /// Reads a stack graph and a database of partial paths in it from a JSON document written by
+    /// [`write_with_partials`][Self::write_with_partials].  The partial paths are loaded against
+    /// the deserialized graph, so all handles in the result are consistent with each other.  It
+    /// is an error for a partial path to refer to a node or symbol that is not part of the graph.
+    pub fn read_with_partials<R: std::io::Read>(
+        reader: R,
+    ) -> Result<(StackGraph, PartialPaths, crate::stitching::Database), DatabaseLoadError> {
+        let document: StackGraphWithPartials = serde_json::from_reader(reader)?;
+        let mut graph = StackGraph::new();
+        document
+            .graph
+            .load_into(&mut graph)
+            .map_err(DatabaseLoadError::InvalidGraph)?;
+        let mut partials = PartialPaths::new();
+        let mut db = crate::stitching::Database::new();
+        db.load_validated(&document.database, &mut graph, &mut partials)?;
+        Ok((graph, partials, db))
+    }
+}
+
+/// An error that can occur while loading partial paths into a database with
+/// [`Database::load_from_json`][crate::stitching::Database::load_from_json] or
+/// [`StackGraph::read_with_partials`][crate::graph::StackGraph::read_with_partials].
+#[cfg(feature = "serde")]
+#[derive(Debug, ThisError)]
+pub enum DatabaseLoadError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("failed to load stack graph: {0}")]
+    InvalidGraph(Error),
+    #[error("failed to load partial path at index {index}: {error}")]
+    InvalidPath { index: usize, error: Error },
+}
+
+/// An error that can occur while writing partial paths with
+/// [`PartialPaths::write_all_json_with_cancellation`][].
+#[cfg(feature = "serde")]
+#[derive(Debug, ThisError)]
+pub enum WriteError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error(transparent)]
+    Cancelled(#[from] CancellationError),
 }
@@ -37,12 +37,14 @@
 
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::VecDeque;
 #[cfg(feature = "copious-debugging")]
 use std::fmt::Display;
 
 use itertools::izip;
 use itertools::Itertools;
+use thiserror::Error;
 
 use crate::arena::Arena;
 use crate::arena::Handle;
@@ -70,6 +72,7 @@ use crate::paths::PathResolutionError;
 use crate::stats::FrequencyDistribution;
 use crate::CancellationError;
 use crate::CancellationFlag;
+use crate::NoCancellation;
 
 //-------------------------------------------------------------------------------------------------
 // Appendable
@@ -183,6 +186,15 @@ where
 /// A trait to support finding candidates for partial path extension. The candidates are represented
 /// by handles `H`, which are mapped to appendables `A` using the database `Db`. Loading errors are
 /// reported as values of the `Err` type.
+///
+/// The stitcher only accesses candidates through this trait, so you can implement it to stitch
+/// partial paths that live outside of a single in-memory [`Database`][], for instance in a remote or
+/// sharded index.  The stitcher calls [`load_forward_candidates`][Self::load_forward_candidates]
+/// for every path it is about to extend, before asking for its candidates.  A typical lazy
+/// implementation fetches the partial paths that might be needed for that path, adds them to a
+/// local [`Database`][] that acts as a cache, and then answers the other methods from that cache,
+/// like [`DatabaseCandidates`][] does.  The SQLite-backed reader in the `storage` module works
+/// this way.
 pub trait ForwardCandidates<H, A, Db, Err>
 where
     A: Appendable,
@@ -280,6 +292,17 @@ impl ToAppendable<Edge, Edge> for GraphEdges {
 /// We've written the path-stitching algorithm so that you have a chance to only load in the
 /// partial paths that are actually needed, placing them into a `Database` instance as they're
 /// needed.
+///
+/// If your stack graph is stable, you can instead populate a `Database` once, with the partial
+/// paths of all files, and reuse it for any number of queries.  Looking up partial paths only
+/// requires a shared reference to the database, so [`DatabaseCandidates`][] and
+/// [`StackGraph::definitions_for_reference`][] never modify it, and a populated database can be
+/// shared between threads behind an `Arc`.
+///
+/// The partial paths in a database refer to content stored in the [`PartialPaths`][] instance
+/// that they were created with, and every query must use that instance.  Stitching adds new
+/// content to it, so it cannot be shared between concurrent queries.  Instead, give each thread
+/// its own clone of the `PartialPaths`, made after the database was populated.
 pub struct Database {
     pub(crate) partial_paths: Arena<PartialPath>,
     pub(crate) local_nodes: HandleSet<Node>,
@@ -381,7 +404,7 @@ impl Database {
     /// If the end node is the root node, returns paths with a symbol stack precondition
     /// that are compatible with the path's symbol stack post condition.
     pub fn find_candidate_partial_paths<R>(
-        &mut self,
+        &self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         path: &PartialPath,
@@ -407,7 +430,7 @@ impl Database {
     /// stack precondition that is compatible with a given symbol stack.
     #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
     pub fn find_candidate_partial_paths_from_root<R>(
-        &mut self,
+        &self,
         graph: &StackGraph,
         partials: &mut PartialPaths,
         symbol_stack: Option<PartialSymbolStack>,
@@ -419,8 +442,10 @@ impl Database {
         // symbol stack precondition is compatible with the path.
         match symbol_stack {
             Some(symbol_stack) => {
-                let mut key =
-                    SymbolStackKey::from_partial_symbol_stack(partials, self, symbol_stack);
+                // If the database has never seen the whole symbol stack, no path can have it (or an
+                // extension of it) as its precondition, but paths might still have a prefix of it.
+                let (mut key, is_complete) =
+                    SymbolStackKey::find_partial_symbol_stack(partials, self, symbol_stack);
                 copious_debugging!(
                     "      Search for symbol stack <{}>",
                     key.display(graph, self)
@@ -429,6 +454,7 @@ impl Database {
                 if let Some(paths) = self
                     .root_paths_by_precondition_without_variable
                     .get(key.back_handle())
+                    .filter(|_| is_complete)
                 {
                     #[cfg(feature = "copious-debugging")]
                     {
@@ -442,7 +468,7 @@ impl Database {
                     result.extend(paths.iter().copied());
                 }
                 // paths that have an extension of this symbol stack
-                if symbol_stack.has_variable() {
+                if is_complete && symbol_stack.has_variable() {
                     if let Some(paths) = self
                         .root_paths_by_precondition_prefix
                         .get(key.back_handle())
@@ -503,6 +529,52 @@ impl Database {
         }
     }
 
+    /// Returns all partial paths in this database that start at the root node, and whose symbol
+    /// stack precondition starts with the given symbols.  The first element of `prefix` is the
+    /// top of the symbol stack.  This is useful for completion, where you want to know which
+    /// definitions are reachable for a partially typed qualified name, without having to create a
+    /// reference node for it.  An empty prefix returns all paths that start at the root node.
+    #[cfg_attr(not(feature = "copious-debugging"), allow(unused_variables))]
+    pub fn find_candidates_with_prefix(
+        &self,
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        prefix: &[Handle<Symbol>],
+    ) -> Vec<PartialPath> {
+        if prefix.is_empty() {
+            return self
+                .partial_paths
+                .iter_handles()
+                .map(|path| &self[path])
+                .filter(|path| graph[path.start_node].is_root())
+                .cloned()
+                .collect();
+        }
+        // If the database has never seen the prefix, no path can have it as the start of its
+        // precondition.
+        let key = match SymbolStackKey::find_symbols(self, prefix) {
+            Some(key) => key,
+            None => return Vec::new(),
+        };
+        let mut result = Vec::new();
+        for paths in [
+            &self.root_paths_by_precondition_with_variable,
+            &self.root_paths_by_precondition_without_variable,
+            &self.root_paths_by_precondition_prefix,
+        ] {
+            if let Some(paths) = paths.get(key.back_handle()) {
+                for path in paths {
+                    copious_debugging!(
+                        "        Found path with prefix {}",
+                        self[*path].display(graph, partials)
+                    );
+                    result.push(self[*path].clone());
+                }
+            }
+        }
+        result
+    }
+
     /// Find all partial paths in the database that start at the given node.  We don't filter the
     /// results any further than that, since we have to check each partial path for compatibility
     /// as we try to append it to the current incomplete path anyway, and non-root nodes will
@@ -533,6 +605,33 @@ impl Database {
         }
     }
 
+    /// Returns all of the partial paths in this database that start at the given node.  Unlike
+    /// [`find_candidate_partial_paths`][Self::find_candidate_partial_paths], this does not take
+    /// the symbol stack of any path into account, which makes it useful for inspecting the
+    /// contents of the database.
+    pub fn partial_paths_for_node(
+        &self,
+        node: Handle<Node>,
+    ) -> impl Iterator<Item = &PartialPath> + '_ {
+        // Paths that start at the root node are indexed by their symbol stack preconditions
+        // instead of their start node, so we have to look at all of them.
+        let root_paths = if node == StackGraph::root_node() {
+            Some(
+                self.partial_paths
+                    .iter_handles()
+                    .filter(move |path| self[*path].start_node == node),
+            )
+        } else {
+            None
+        };
+        let node_paths = self.paths_by_start_node.get(node).into_iter().flatten();
+        root_paths
+            .into_iter()
+            .flatten()
+            .chain(node_paths.copied())
+            .map(move |path| &self[path])
+    }
+
     /// Returns the number of paths in this database that share the given end node.
     pub fn get_incoming_path_degree(&self, end_node: Handle<Node>) -> Degree {
         self.incoming_paths[end_node]
@@ -621,6 +720,43 @@ impl Database {
         self.partial_paths.iter_handles()
     }
 
+    /// Removes all partial paths that refer to a node that is no longer part of the graph, for
+    /// instance after its file was removed with [`StackGraph::remove_file`][].  A partial path is
+    /// removed if its start node, its end node, or the source node of any of its edges was
+    /// removed.  Returns the number of partial paths that were removed.
+    ///
+    /// If any partial path is removed, the database is rebuilt from the remaining paths, and all
+    /// partial path handles from before this call are invalid afterwards.  This must be called
+    /// before [`StackGraph::compact`][], which reuses the handles of removed nodes.
+    pub fn prune(&mut self, graph: &StackGraph, partials: &mut PartialPaths) -> usize {
+        let live_paths = self
+            .partial_paths
+            .iter_handles()
+            .filter(|handle| {
+                let path = self.partial_paths.get(*handle);
+                graph.is_live_node(path.start_node)
+                    && graph.is_live_node(path.end_node)
+                    && path
+                        .edges
+                        .iter_unordered(partials)
+                        .all(|edge| graph.node_for_id(edge.source_node_id).is_some())
+            })
+            .collect::<Vec<_>>();
+        let pruned = self.iter_partial_paths().count() - live_paths.len();
+        if pruned == 0 {
+            return 0;
+        }
+
+        let mut database = Database::new();
+        for handle in live_paths {
+            let path = self.partial_paths.get(handle).clone();
+            database.add_partial_path(graph, partials, path);
+        }
+        database.local_nodes = std::mem::take(&mut self.local_nodes);
+        *self = database;
+        pruned
+    }
+
     pub fn ensure_both_directions(&mut self, partials: &mut PartialPaths) {
         for path in self.partial_paths.iter_handles() {
             self.partial_paths
@@ -653,14 +789,14 @@ impl ToAppendable<Handle<PartialPath>, PartialPath> for Database {
 pub struct DatabaseCandidates<'a> {
     graph: &'a StackGraph,
     partials: &'a mut PartialPaths,
-    database: &'a mut Database,
+    database: &'a Database,
 }
 
 impl<'a> DatabaseCandidates<'a> {
     pub fn new(
         graph: &'a StackGraph,
         partials: &'a mut PartialPaths,
-        database: &'a mut Database,
+        database: &'a Database,
     ) -> Self {
         Self {
             graph,
@@ -757,6 +893,42 @@ impl SymbolStackKey {
         result
     }
 
+    /// Finds the symbol stack key for a partial symbol stack, without adding any new keys to the
+    /// database.  Returns the key for the longest prefix of the stack that the database knows
+    /// about, and whether that prefix is the entire stack.
+    fn find_partial_symbol_stack(
+        partials: &mut PartialPaths,
+        db: &Database,
+        mut stack: PartialSymbolStack,
+    ) -> (SymbolStackKey, bool) {
+        let mut result = SymbolStackKey::empty();
+        while let Some(symbol) = stack.pop_front(partials) {
+            let cache_key = SymbolStackCacheKey {
+                head: symbol.symbol,
+                tail: result.back_handle(),
+            };
+            match db.symbol_stack_key_cache.get(&cache_key) {
+                Some(handle) => result.symbols = List::from_handle(*handle),
+                None => return (result, false),
+            }
+        }
+        (result, true)
+    }
+
+    /// Finds the symbol stack key for a sequence of symbols, without adding any new keys to the
+    /// database.  Returns `None` if the database does not know about the sequence.
+    fn find_symbols(db: &Database, symbols: &[Handle<Symbol>]) -> Option<SymbolStackKey> {
+        let mut result = SymbolStackKey::empty();
+        for symbol in symbols {
+            let cache_key = SymbolStackCacheKey {
+                head: *symbol,
+                tail: result.back_handle(),
+            };
+            result.symbols = List::from_handle(*db.symbol_stack_key_cache.get(&cache_key)?);
+        }
+        Some(result)
+    }
+
     /// Returns a handle to the back of the symbol stack key.
     fn back_handle(self) -> SymbolStackKeyHandle {
         // Because the symbols are stored in reverse order, the handle to the "front" of the list
@@ -841,7 +1013,13 @@ pub struct ForwardPartialPathStitcher<H> {
     similar_path_detector: Option<SimilarPathDetector<PartialPath>>,
     check_only_join_nodes: bool,
     max_work_per_phase: usize,
+    max_path_length: Option<usize>,
+    max_symbol_stack_depth: Option<usize>,
     initial_paths: usize,
+    created_paths: usize,
+    extended_paths: usize,
+    pruned_paths: usize,
+    max_queue_length: usize,
     stats: Option<Stats>,
     #[cfg(feature = "copious-debugging")]
     phase_number: usize,
@@ -881,7 +1059,15 @@ impl<H> ForwardPartialPathStitcher<H> {
             check_only_join_nodes: false,
             // By default, there's no artificial bound on the amount of work done per phase
             max_work_per_phase: usize::MAX,
+            // By default, there's no bound on the length of paths
+            max_path_length: None,
+            // By default, there's no bound on the depth of symbol stacks
+            max_symbol_stack_depth: None,
             initial_paths,
+            created_paths: initial_paths,
+            extended_paths: 0,
+            pruned_paths: 0,
+            max_queue_length: initial_paths,
             stats: None,
             #[cfg(feature = "copious-debugging")]
             phase_number: 1,
@@ -919,6 +1105,22 @@ impl<H> ForwardPartialPathStitcher<H> {
         self.max_work_per_phase = max_work_per_phase;
     }
 
+    /// Sets the maximum number of edges in the partial paths that are created during stitching.
+    /// Extensions that would result in longer partial paths are dropped, and counted as
+    /// [pruned][Self::pruned_paths], instead of being extended further.  If you don't call this
+    /// method, partial paths can be arbitrarily long.
+    pub fn set_max_path_length(&mut self, max_path_length: Option<usize>) {
+        self.max_path_length = max_path_length;
+    }
+
+    /// Sets the maximum number of symbols in the symbol stack pre- and postconditions of the
+    /// partial paths that are created during stitching.  Extensions that would result in deeper
+    /// symbol stacks are dropped, and counted as [pruned][Self::pruned_paths], instead of being
+    /// extended further.  If you don't call this method, symbol stacks can be arbitrarily deep.
+    pub fn set_max_symbol_stack_depth(&mut self, max_symbol_stack_depth: Option<usize>) {
+        self.max_symbol_stack_depth = max_symbol_stack_depth;
+    }
+
     /// Sets whether to collect statistics during stitching.
     pub fn set_collect_stats(&mut self, collect_stats: bool) {
         if !collect_stats {
@@ -939,7 +1141,18 @@ impl<H> ForwardPartialPathStitcher<H> {
         {
             stats.similar_paths_stats = similar_path_detector.stats();
         }
-        self.stats.unwrap_or_default()
+        Stats {
+            pruned_paths: self.pruned_paths,
+            progress: StitcherStats {
+                queue_length: self.queue.len() + self.next_iteration.0.len(),
+                created_paths: self.created_paths,
+                extended_paths: self.extended_paths,
+                pruned_paths: self.pruned_paths,
+                max_queue_length: self.max_queue_length,
+                ..StitcherStats::default()
+            },
+            ..self.stats.unwrap_or_default()
+        }
     }
 }
 
@@ -1037,6 +1250,22 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
                     continue;
                 }
             }
+            if let Some(max_path_length) = self.max_path_length {
+                if new_partial_path.edges.len() > max_path_length {
+                    copious_debugging!("        is pruned: too long");
+                    self.pruned_paths += 1;
+                    continue;
+                }
+            }
+            if let Some(max_symbol_stack_depth) = self.max_symbol_stack_depth {
+                if new_partial_path.symbol_stack_precondition.len() > max_symbol_stack_depth
+                    || new_partial_path.symbol_stack_postcondition.len() > max_symbol_stack_depth
+                {
+                    copious_debugging!("        is pruned: symbol stack too deep");
+                    self.pruned_paths += 1;
+                    continue;
+                }
+            }
             new_cycle_detector.append(&mut self.appended_paths, candidate.clone());
             copious_debugging!("        is {}", new_partial_path.display(graph, partials));
             self.extensions.push((new_partial_path, new_cycle_detector));
@@ -1086,6 +1315,7 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
             self.next_iteration.0.push(new_partial_path);
             self.next_iteration.1.push(new_cycle_detector);
             self.next_iteration.2.push(new_has_split);
+            self.created_paths += 1;
         }
 
         if let Some(stats) = &mut self.stats {
@@ -1112,6 +1342,60 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         self.queue.is_empty() && self.next_iteration.0.is_empty()
     }
 
+    /// Returns the number of partial paths that are waiting to be extended.
+    pub fn queue_length(&self) -> usize {
+        self.queue.len() + self.next_iteration.0.len()
+    }
+
+    /// Returns an error if more partial paths are waiting to be extended than the given maximum.
+    /// The counters in `progress` that the stitcher tracks itself are filled in.
+    fn check_queue_size(
+        &self,
+        max_queue_size: Option<usize>,
+        progress: StitcherStats,
+    ) -> Result<(), ResourceExhausted> {
+        match max_queue_size {
+            Some(max_queue_size) if self.queue_length() > max_queue_size => {
+                Err(ResourceExhausted {
+                    max_queue_size,
+                    progress: StitcherStats {
+                        queue_length: self.queue_length(),
+                        max_queue_length: self.max_queue_length(),
+                        created_paths: self.created_paths(),
+                        extended_paths: self.extended_paths(),
+                        pruned_paths: self.pruned_paths(),
+                        ..progress
+                    },
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the total number of partial paths that have been created so far, including the
+    /// initial paths.
+    pub fn created_paths(&self) -> usize {
+        self.created_paths
+    }
+
+    /// Returns the total number of partial paths that have been extended so far.
+    pub fn extended_paths(&self) -> usize {
+        self.extended_paths
+    }
+
+    /// Returns the maximum number of partial paths that were waiting to be extended at the end of
+    /// any phase so far.
+    pub fn max_queue_length(&self) -> usize {
+        self.max_queue_length
+    }
+
+    /// Returns the total number of extensions that have been dropped so far, because they exceeded
+    /// the [maximum path length][Self::set_max_path_length] or the [maximum symbol stack
+    /// depth][Self::set_max_symbol_stack_depth].
+    pub fn pruned_paths(&self) -> usize {
+        self.pruned_paths
+    }
+
     /// Runs the next phase of the algorithm.  We will have built up a set of incomplete partial
     /// paths during the _previous_ phase.  Before calling this function, you must ensure that `db`
     /// contains all of the possible appendables that we might want to extend any of those
@@ -1156,6 +1440,7 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
                 );
                 continue;
             }
+            self.extended_paths += 1;
             work_performed += self.extend(candidates, &partial_path, cycle_detector, has_split);
             if work_performed >= self.max_work_per_phase {
                 break;
@@ -1164,6 +1449,7 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
         if let Some(stats) = &mut self.stats {
             stats.processed_paths_per_phase.record(work_performed);
         }
+        self.max_queue_length = self.max_queue_length.max(self.queue_length());
 
         #[cfg(feature = "copious-debugging")]
         {
@@ -1179,6 +1465,11 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
     }
 }
 
+/// The maximum amount of work that the stitching loops perform between two checks of the
+/// cancellation flag.  Bounding the work per phase makes sure that a single phase that explodes
+/// on a pathological graph cannot prevent a timely cancellation.
+const MAX_WORK_BETWEEN_CANCELLATION_CHECKS: usize = 1024;
+
 impl ForwardPartialPathStitcher<Edge> {
     /// Finds a minimal set of partial paths in a file, calling the `visit` closure for each one.
     ///
@@ -1195,6 +1486,12 @@ impl ForwardPartialPathStitcher<Edge> {
     /// Caveat: Edges between nodes of different files are not used. Hence the returned set of partial
     /// paths will not cover paths going through those edges.
     ///
+    /// The `cancellation_flag` is checked regularly during stitching.  If it signals cancellation, a
+    /// [`CancellationError`][] is returned.  If the configuration sets a
+    /// [maximum queue size][StitcherConfig::with_max_queue_size] and the queue grows beyond it, a
+    /// [`ResourceExhausted`][] error is returned.  The partial paths arena is left in a consistent
+    /// state and can be reused afterwards.
+    ///
     /// [`process_next_phase`]: #method.process_next_phase
     pub fn find_minimal_partial_path_set_in_file<F>(
         graph: &StackGraph,
@@ -1203,7 +1500,7 @@ impl ForwardPartialPathStitcher<Edge> {
         config: StitcherConfig,
         cancellation_flag: &dyn CancellationFlag,
         mut visit: F,
-    ) -> Result<Stats, CancellationError>
+    ) -> Result<Stats, StitchingError<CancellationError>>
     where
         F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
     {
@@ -1222,10 +1519,13 @@ impl ForwardPartialPathStitcher<Edge> {
             ForwardPartialPathStitcher::from_partial_paths(graph, partials, initial_paths);
         config.apply(&mut stitcher);
         stitcher.set_check_only_join_nodes(true);
+        stitcher.set_max_work_per_phase(MAX_WORK_BETWEEN_CANCELLATION_CHECKS);
 
         let mut accepted_path_length = FrequencyDistribution::default();
         while !stitcher.is_complete() {
-            cancellation_flag.check("finding complete partial paths")?;
+            cancellation_flag
+                .check("finding complete partial paths")
+                .map_err(StitchingError::Candidates)?;
             stitcher.process_next_phase(
                 &mut GraphEdgeCandidates::new(graph, partials, Some(file)),
                 |g, _ps, p| !as_complete_as_necessary(g, p),
@@ -1236,6 +1536,7 @@ impl ForwardPartialPathStitcher<Edge> {
                     visit(graph, partials, path);
                 }
             }
+            stitcher.check_queue_size(config.max_queue_size, StitcherStats::default())?;
         }
 
         Ok(Stats {
@@ -1256,21 +1557,150 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
     /// lazily loading only the necessary pieces, then you should code up your own loop that calls
     /// [`process_next_phase`][] manually.
     ///
+    /// The `cancellation_flag` is checked regularly during stitching.  If it signals cancellation, an
+    /// error is returned.  The partial paths arena and database are left in a consistent state and
+    /// can be reused afterwards.  Likewise, if the configuration sets a
+    /// [maximum queue size][StitcherConfig::with_max_queue_size] and the queue grows beyond it, a
+    /// [`ResourceExhausted`][] error is returned.  In that case, all complete paths that were found
+    /// before, including the ones that are held back to be
+    /// [sorted][StitcherConfig::with_sort_by_cost] or
+    /// [filtered][StitcherConfig::with_shortest_paths_only], are passed to `visit` before the error
+    /// is returned.
+    ///
     /// [`process_next_phase`]: #method.process_next_phase
     pub fn find_all_complete_partial_paths<I, F, A, Db, C, Err>(
+        candidates: &mut C,
+        starting_nodes: I,
+        config: StitcherConfig,
+        cancellation_flag: &dyn CancellationFlag,
+        visit: F,
+    ) -> Result<Stats, StitchingError<Err>>
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
+        F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        Err: std::convert::From<CancellationError>,
+    {
+        Self::find_all_complete_partial_paths_with_observer(
+            candidates,
+            starting_nodes,
+            config,
+            cancellation_flag,
+            visit,
+            |_| {},
+        )
+    }
+
+    /// Finds all complete partial paths that are reachable from a set of starting nodes, like
+    /// [`find_all_complete_partial_paths`][], and calls the `observe` closure after each phase of
+    /// the algorithm with the progress made so far.  The progress is passed by value, and does
+    /// not borrow any of the stitcher's arenas, so the observer is free to update external state,
+    /// such as a progress bar.
+    ///
+    /// [`find_all_complete_partial_paths`]: #method.find_all_complete_partial_paths
+    pub fn find_all_complete_partial_paths_with_observer<I, F, O, A, Db, C, Err>(
         candidates: &mut C,
         starting_nodes: I,
         config: StitcherConfig,
         cancellation_flag: &dyn CancellationFlag,
         mut visit: F,
-    ) -> Result<Stats, Err>
+        mut observe: O,
+    ) -> Result<Stats, StitchingError<Err>>
     where
         I: IntoIterator<Item = Handle<Node>>,
         A: Appendable,
         Db: ToAppendable<H, A>,
         C: ForwardCandidates<H, A, Db, Err>,
         F: FnMut(&StackGraph, &mut PartialPaths, &PartialPath),
+        O: FnMut(&StitcherStats),
         Err: std::convert::From<CancellationError>,
+    {
+        let mut stitcher = Self::for_complete_partial_paths(candidates, starting_nodes, config);
+        let mut accepted_path_length = FrequencyDistribution::default();
+        let mut progress = StitcherStats::default();
+        let mut shortest_paths = ShortestPaths::default();
+        let mut deferred_paths = Vec::new();
+        let mut reported_endpoints = HashSet::new();
+        let mut exhausted = None;
+        while !stitcher.is_complete() {
+            cancellation_flag
+                .check("finding complete partial paths")
+                .map_err(|err| StitchingError::Candidates(err.into()))?;
+            for path in stitcher.previous_phase_partial_paths() {
+                candidates
+                    .load_forward_candidates(path, cancellation_flag)
+                    .map_err(StitchingError::Candidates)?;
+            }
+            stitcher.process_next_phase(candidates, |_, _, _| true);
+            let (graph, partials, _) = candidates.get_graph_partials_and_db();
+            for path in stitcher.previous_phase_partial_paths() {
+                if path.is_complete(graph) {
+                    progress.completed_paths += 1;
+                    if config.shortest_paths_only {
+                        shortest_paths.add(path);
+                    } else if config.sort_by_cost {
+                        deferred_paths.push(path.clone());
+                    } else if !config.deduplicate_complete_paths
+                        || reported_endpoints.insert((path.start_node, path.end_node))
+                    {
+                        accepted_path_length.record(path.edges.len());
+                        visit(graph, partials, path);
+                    }
+                }
+            }
+            progress.phases += 1;
+            progress.queue_length = stitcher.queue_length();
+            progress.created_paths = stitcher.created_paths();
+            progress.extended_paths = stitcher.extended_paths();
+            progress.pruned_paths = stitcher.pruned_paths();
+            progress.max_queue_length = stitcher.max_queue_length();
+            observe(&progress);
+            if let Err(err) = stitcher.check_queue_size(config.max_queue_size, progress) {
+                exhausted = Some(err);
+                break;
+            }
+        }
+
+        let (graph, partials, _) = candidates.get_graph_partials_and_db();
+        deferred_paths.extend(shortest_paths.into_paths());
+        if config.sort_by_cost {
+            // The sort is stable, so paths with the same cost stay in the order they were found.
+            deferred_paths.sort_by_cached_key(|path| path.cost(graph, partials));
+        }
+        for path in deferred_paths {
+            if config.deduplicate_complete_paths
+                && !reported_endpoints.insert((path.start_node, path.end_node))
+            {
+                continue;
+            }
+            accepted_path_length.record(path.edges.len());
+            visit(graph, partials, &path);
+        }
+        if let Some(err) = exhausted {
+            return Err(err.into());
+        }
+
+        Ok(Stats {
+            accepted_path_length,
+            progress,
+            ..stitcher.into_stats()
+        })
+    }
+
+    /// Creates a stitcher that finds the complete partial paths that start at the reference nodes
+    /// among `starting_nodes`.
+    fn for_complete_partial_paths<I, A, Db, C, Err>(
+        candidates: &mut C,
+        starting_nodes: I,
+        config: StitcherConfig,
+    ) -> Self
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+        A: Appendable,
+        Db: ToAppendable<H, A>,
+        C: ForwardCandidates<H, A, Db, Err>,
     {
         let (graph, partials, _) = candidates.get_graph_partials_and_db();
         let initial_paths = starting_nodes
@@ -1286,27 +1716,162 @@ impl<H: Clone> ForwardPartialPathStitcher<H> {
             ForwardPartialPathStitcher::from_partial_paths(graph, partials, initial_paths);
         config.apply(&mut stitcher);
         stitcher.set_check_only_join_nodes(true);
+        stitcher.set_max_work_per_phase(MAX_WORK_BETWEEN_CANCELLATION_CHECKS);
+        stitcher
+    }
+}
 
-        let mut accepted_path_length = FrequencyDistribution::default();
-        while !stitcher.is_complete() {
-            cancellation_flag.check("finding complete partial paths")?;
-            for path in stitcher.previous_phase_partial_paths() {
-                candidates.load_forward_candidates(path, cancellation_flag)?;
+/// Finds complete partial paths lazily.  This does the same work as
+/// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][], but only processes as many
+/// phases of the algorithm as are needed to produce the next path, so you can stop early, for
+/// instance via [`take`][Iterator::take], without exploring the whole search space.
+///
+/// Paths are produced in the order of the phases in which they are found, so paths that needed
+/// fewer stitching steps come first.  Paths found in the same phase are not produced in any
+/// particular order.
+///
+/// The iterator mutably borrows the candidates for as long as it exists.  The paths it produces
+/// refer to the candidates' [`PartialPaths`][] arena, which you can access via
+/// [`candidates`][Self::candidates] between calls to `next`.
+///
+/// If the `cancellation_flag` signals cancellation, the queue grows beyond the configured
+/// [maximum size][StitcherConfig::with_max_queue_size], or loading candidates fails, the iterator
+/// ends, and the error is available via [`error`][Self::error].  The complete paths that were found
+/// in the phase that exceeded the maximum queue size are still produced.
+pub struct PartialPathIterator<'a, H, A, Db, C, Err> {
+    candidates: &'a mut C,
+    stitcher: ForwardPartialPathStitcher<H>,
+    cancellation_flag: &'a dyn CancellationFlag,
+    max_queue_size: Option<usize>,
+    complete_paths: VecDeque<PartialPath>,
+    error: Option<StitchingError<Err>>,
+    _phantom: std::marker::PhantomData<(A, Db)>,
+}
+
+impl<'a, H, A, Db, C, Err> PartialPathIterator<'a, H, A, Db, C, Err>
+where
+    H: Clone,
+    A: Appendable,
+    Db: ToAppendable<H, A>,
+    C: ForwardCandidates<H, A, Db, Err>,
+    Err: std::convert::From<CancellationError>,
+{
+    /// Creates an iterator over all complete partial paths that are reachable from a set of
+    /// starting nodes.
+    pub fn new<I>(
+        candidates: &'a mut C,
+        starting_nodes: I,
+        config: StitcherConfig,
+        cancellation_flag: &'a dyn CancellationFlag,
+    ) -> Self
+    where
+        I: IntoIterator<Item = Handle<Node>>,
+    {
+        let stitcher = ForwardPartialPathStitcher::for_complete_partial_paths(
+            candidates,
+            starting_nodes,
+            config,
+        );
+        Self {
+            candidates,
+            stitcher,
+            cancellation_flag,
+            max_queue_size: config.max_queue_size,
+            complete_paths: VecDeque::new(),
+            error: None,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Returns the candidates that this iterator stitches together.
+    pub fn candidates(&mut self) -> &mut C {
+        self.candidates
+    }
+
+    /// Returns the error that ended this iterator, if any.
+    pub fn error(&self) -> Option<&StitchingError<Err>> {
+        self.error.as_ref()
+    }
+
+    fn process_next_phase(&mut self) -> Result<(), StitchingError<Err>> {
+        self.cancellation_flag
+            .check("finding complete partial paths")
+            .map_err(|err| StitchingError::Candidates(err.into()))?;
+        for path in self.stitcher.previous_phase_partial_paths() {
+            self.candidates
+                .load_forward_candidates(path, self.cancellation_flag)
+                .map_err(StitchingError::Candidates)?;
+        }
+        self.stitcher
+            .process_next_phase(self.candidates, |_, _, _| true);
+        let (graph, _, _) = self.candidates.get_graph_partials_and_db();
+        self.complete_paths.extend(
+            self.stitcher
+                .previous_phase_partial_paths()
+                .filter(|path| path.is_complete(graph))
+                .cloned(),
+        );
+        self.stitcher
+            .check_queue_size(self.max_queue_size, StitcherStats::default())?;
+        Ok(())
+    }
+}
+
+impl<H, A, Db, C, Err> Iterator for PartialPathIterator<'_, H, A, Db, C, Err>
+where
+    H: Clone,
+    A: Appendable,
+    Db: ToAppendable<H, A>,
+    C: ForwardCandidates<H, A, Db, Err>,
+    Err: std::convert::From<CancellationError>,
+{
+    type Item = PartialPath;
+
+    fn next(&mut self) -> Option<PartialPath> {
+        loop {
+            if let Some(path) = self.complete_paths.pop_front() {
+                return Some(path);
             }
-            stitcher.process_next_phase(candidates, |_, _, _| true);
-            let (graph, partials, _) = candidates.get_graph_partials_and_db();
-            for path in stitcher.previous_phase_partial_paths() {
-                if path.is_complete(graph) {
-                    accepted_path_length.record(path.edges.len());
-                    visit(graph, partials, path);
-                }
+            if self.error.is_some() || self.stitcher.is_complete() {
+                return None;
+            }
+            if let Err(err) = self.process_next_phase() {
+                self.error = Some(err);
             }
         }
+    }
+}
 
-        Ok(Stats {
-            accepted_path_length,
-            ..stitcher.into_stats()
-        })
+/// The progress made by a running stitcher.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StitcherStats {
+    /// The number of phases that have been processed.
+    pub phases: usize,
+    /// The number of partial paths that are waiting to be extended.
+    pub queue_length: usize,
+    /// The maximum number of partial paths that were waiting to be extended at the end of any
+    /// phase.
+    pub max_queue_length: usize,
+    /// The total number of partial paths that have been created, including the initial paths.
+    pub created_paths: usize,
+    /// The total number of partial paths that have been extended.
+    pub extended_paths: usize,
+    /// The total number of complete paths that have been found.
+    pub completed_paths: usize,
+    /// The total number of extensions that were dropped because they exceeded the maximum path
+    /// length or symbol stack depth.
+    pub pruned_paths: usize,
+}
+
+impl std::ops::AddAssign<&Self> for StitcherStats {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.phases += rhs.phases;
+        self.queue_length += rhs.queue_length;
+        self.max_queue_length = self.max_queue_length.max(rhs.max_queue_length);
+        self.created_paths += rhs.created_paths;
+        self.extended_paths += rhs.extended_paths;
+        self.completed_paths += rhs.completed_paths;
+        self.pruned_paths += rhs.pruned_paths;
     }
 }
 
@@ -1336,6 +1901,16 @@ pub struct Stats {
     pub node_visits: FrequencyDistribution<crate::graph::NodeID>,
     /// The distribution of the number of similar paths between node pairs.
     pub similar_paths_stats: SimilarPathStats,
+    /// The number of extensions that were dropped because they exceeded the maximum path length
+    /// or symbol stack depth.  Unlike the other statistics, this is always collected.
+    pub pruned_paths: usize,
+    /// The number of partial paths that were created, extended, and completed, and the maximum
+    /// queue length.  These counters do not depend on timing, which makes them useful to check
+    /// that stitching stays within a budget, for instance in regression tests.  Like
+    /// [`pruned_paths`][Self::pruned_paths], they are always collected.  The phases and completed
+    /// paths are only counted when finding complete paths, using
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][].
+    pub progress: StitcherStats,
 }
 
 impl std::ops::AddAssign<Self> for Stats {
@@ -1352,6 +1927,8 @@ impl std::ops::AddAssign<Self> for Stats {
         self.root_visits += rhs.root_visits;
         self.node_visits += rhs.node_visits;
         self.similar_paths_stats += rhs.similar_paths_stats;
+        self.pruned_paths += rhs.pruned_paths;
+        self.progress += &rhs.progress;
     }
 }
 
@@ -1368,6 +1945,43 @@ impl std::ops::AddAssign<&Self> for Stats {
         self.root_visits += rhs.root_visits;
         self.node_visits += &rhs.node_visits;
         self.similar_paths_stats += &rhs.similar_paths_stats;
+        self.pruned_paths += rhs.pruned_paths;
+        self.progress += &rhs.progress;
+    }
+}
+
+/// Keeps the shortest complete paths found for each pair of start and end nodes, in the order in
+/// which the pairs were first found.
+#[derive(Default)]
+struct ShortestPaths {
+    indices: HashMap<(Handle<Node>, Handle<Node>), usize>,
+    paths: Vec<Vec<PartialPath>>,
+}
+
+impl ShortestPaths {
+    fn add(&mut self, path: &PartialPath) {
+        let paths = &mut self.paths;
+        let index = *self
+            .indices
+            .entry((path.start_node, path.end_node))
+            .or_insert_with(|| {
+                paths.push(Vec::new());
+                paths.len() - 1
+            });
+        let shortest = &mut self.paths[index];
+        match shortest
+            .first()
+            .map(|p| p.edges.len().cmp(&path.edges.len()))
+        {
+            Some(Ordering::Less) => return,
+            Some(Ordering::Greater) => shortest.clear(),
+            _ => {}
+        }
+        shortest.push(path.clone());
+    }
+
+    fn into_paths(self) -> impl Iterator<Item = PartialPath> {
+        self.paths.into_iter().flatten()
     }
 }
 
@@ -1378,6 +1992,18 @@ pub struct StitcherConfig {
     detect_similar_paths: bool,
     /// Collect statistics about path stitching.
     collect_stats: bool,
+    /// The maximum number of edges in a partial path, if any.
+    max_path_length: Option<usize>,
+    /// The maximum number of symbols in the symbol stacks of a partial path, if any.
+    max_symbol_stack_depth: Option<usize>,
+    /// Only report the shortest complete paths between each start and end node.
+    shortest_paths_only: bool,
+    /// The maximum number of partial paths that may wait to be extended, if any.
+    max_queue_size: Option<usize>,
+    /// Report complete paths ordered by their total edge cost.
+    sort_by_cost: bool,
+    /// Only report one complete path between each start and end node.
+    deduplicate_complete_paths: bool,
 }
 
 impl StitcherConfig {
@@ -1398,12 +2024,106 @@ impl StitcherConfig {
         self.collect_stats = collect_stats;
         self
     }
+
+    pub fn max_path_length(&self) -> Option<usize> {
+        self.max_path_length
+    }
+
+    /// Sets the maximum number of edges in the partial paths that are created during stitching.
+    /// Longer partial paths are dropped instead of failing the whole run, and the number of
+    /// dropped paths is reported in [`Stats::pruned_paths`][].
+    pub fn with_max_path_length(mut self, max_path_length: Option<usize>) -> Self {
+        self.max_path_length = max_path_length;
+        self
+    }
+
+    pub fn max_symbol_stack_depth(&self) -> Option<usize> {
+        self.max_symbol_stack_depth
+    }
+
+    /// Sets the maximum number of symbols in the symbol stack pre- and postconditions of the
+    /// partial paths that are created during stitching.  This is a safety valve for graphs that
+    /// push symbols without ever popping them, for instance because of a bug in the rules that
+    /// create them, on which stitching would otherwise build ever deeper symbol stacks.  Deeper
+    /// partial paths are dropped instead of failing the whole run, and the number of dropped paths
+    /// is reported in [`Stats::pruned_paths`][], together with paths that were too long.
+    ///
+    /// There is no limit by default.  A limit should be comfortably larger than the longest
+    /// qualified names in the language, such as 64 symbols, because a legitimate resolution whose
+    /// symbol stack gets deeper than the limit is dropped as well.
+    pub fn with_max_symbol_stack_depth(mut self, max_symbol_stack_depth: Option<usize>) -> Self {
+        self.max_symbol_stack_depth = max_symbol_stack_depth;
+        self
+    }
+
+    pub fn shortest_paths_only(&self) -> bool {
+        self.shortest_paths_only
+    }
+
+    /// Only report the shortest complete paths between each pair of start and end nodes, where
+    /// the length of a path is its number of edges.  If several paths share the minimal length,
+    /// all of them are reported.  Because no path is known to be the shortest until the search
+    /// is done, [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][] reports the
+    /// paths after the last phase instead of as they are found.  [`PartialPathIterator`][]
+    /// ignores this setting.
+    pub fn with_shortest_paths_only(mut self, shortest_paths_only: bool) -> Self {
+        self.shortest_paths_only = shortest_paths_only;
+        self
+    }
+
+    pub fn max_queue_size(&self) -> Option<usize> {
+        self.max_queue_size
+    }
+
+    /// Sets the maximum number of partial paths that may wait to be extended during stitching.
+    /// The queue is checked after each phase, and if it holds more partial paths, stitching stops
+    /// with a [`ResourceExhausted`][] error, which bounds memory use on graphs where the search
+    /// space explodes.  Paths that were found before are not lost: they are all reported before the
+    /// error is returned, including the ones that are held back until the end of stitching.
+    pub fn with_max_queue_size(mut self, max_queue_size: Option<usize>) -> Self {
+        self.max_queue_size = max_queue_size;
+        self
+    }
+
+    pub fn sort_by_cost(&self) -> bool {
+        self.sort_by_cost
+    }
+
+    /// Report complete paths in ascending order of their [total edge cost][PartialPath::cost].
+    /// Paths with the same cost are reported in the order in which they are found, so if no edge
+    /// has a cost, the order is the same as without this setting.  Like
+    /// [`with_shortest_paths_only`][Self::with_shortest_paths_only], this makes
+    /// [`ForwardPartialPathStitcher::find_all_complete_partial_paths`][] report the paths after
+    /// the last phase, and [`PartialPathIterator`][] ignores it.
+    pub fn with_sort_by_cost(mut self, sort_by_cost: bool) -> Self {
+        self.sort_by_cost = sort_by_cost;
+        self
+    }
+
+    pub fn deduplicate_complete_paths(&self) -> bool {
+        self.deduplicate_complete_paths
+    }
+
+    /// Only report one complete path between each pair of start and end nodes, for instance to
+    /// avoid duplicate go-to-definition results when a reference reaches the same definition via
+    /// different scopes.  The representative is the first path that would have been reported
+    /// without this setting, which, when combined with [only the shortest
+    /// paths][Self::with_shortest_paths_only] or [sorting by cost][Self::with_sort_by_cost], is
+    /// a shortest or cheapest one.  The other paths are dropped entirely, so their edges and their
+    /// scope stacks, which can record different scope bindings, are not preserved on the
+    /// representative.  [`PartialPathIterator`][] ignores this setting.
+    pub fn with_deduplicate_complete_paths(mut self, deduplicate_complete_paths: bool) -> Self {
+        self.deduplicate_complete_paths = deduplicate_complete_paths;
+        self
+    }
 }
 
 impl StitcherConfig {
     fn apply<H>(&self, stitcher: &mut ForwardPartialPathStitcher<H>) {
         stitcher.set_similar_path_detection(self.detect_similar_paths);
         stitcher.set_collect_stats(self.collect_stats);
+        stitcher.set_max_path_length(self.max_path_length);
+        stitcher.set_max_symbol_stack_depth(self.max_symbol_stack_depth);
     }
 }
 
@@ -1412,6 +2132,202 @@ impl Default for StitcherConfig {
         Self {
             detect_similar_paths: true,
             collect_stats: false,
+            max_path_length: None,
+            max_symbol_stack_depth: None,
+            shortest_paths_only: false,
+            max_queue_size: None,
+            sort_by_cost: false,
+            deduplicate_complete_paths: false,
+        }
+    }
+}
+
+/// An error that is returned when stitching stops because more partial paths were waiting to be
+/// extended than allowed by [`StitcherConfig::with_max_queue_size`][].
+#[derive(Clone, Copy, Debug, Error, Eq, PartialEq)]
+#[error(
+    "{} queued partial paths exceed the maximum of {max_queue_size}",
+    .progress.queue_length
+)]
+pub struct ResourceExhausted {
+    /// The maximum queue size that was exceeded.
+    pub max_queue_size: usize,
+    /// The progress made before stitching stopped, including the queue length that exceeded the
+    /// maximum.
+    pub progress: StitcherStats,
+}
+
+/// An error that stops stitching before all complete paths have been found.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum StitchingError<Err> {
+    /// Stitching was cancelled, or the candidates failed to load.
+    #[error("{0}")]
+    Candidates(Err),
+    /// More partial paths were waiting to be extended than allowed.
+    #[error(transparent)]
+    ResourceExhausted(#[from] ResourceExhausted),
+}
+
+impl<Err> StitchingError<Err> {
+    /// Returns the error of the candidates, for stitching without a
+    /// [maximum queue size][StitcherConfig::with_max_queue_size], which cannot exhaust resources.
+    pub(crate) fn into_candidates_error(self) -> Err {
+        match self {
+            Self::Candidates(err) => err,
+            Self::ResourceExhausted(_) => unreachable!("no maximum queue size is configured"),
+        }
+    }
+}
+
+//-------------------------------------------------------------------------------------------------
+// Definitions
+
+/// An error that can occur when looking up the definitions of a reference, or the references of
+/// a definition.
+#[derive(Clone, Debug, Error, Eq, PartialEq)]
+pub enum DefinitionsError {
+    #[error("node {0:?} is not a reference")]
+    NotAReference(Handle<Node>),
+    #[error("node {0:?} is not a definition")]
+    NotADefinition(Handle<Node>),
+    #[error(transparent)]
+    Cancelled(#[from] CancellationError),
+}
+
+impl StackGraph {
+    /// Returns all definitions that a reference resolves to, by stitching together the partial
+    /// paths in the database.  The database must already contain all partial paths that might be
+    /// needed, for instance by adding the minimal partial path set of each file.  The returned
+    /// definitions are deduplicated and sorted by handle.  Returns an error if `reference` is not
+    /// a reference node.
+    pub fn definitions_for_reference(
+        &self,
+        reference: Handle<Node>,
+        partials: &mut PartialPaths,
+        db: &Database,
+    ) -> Result<Vec<Handle<Node>>, DefinitionsError> {
+        self.definitions_for_reference_with_cancellation(reference, partials, db, &NoCancellation)
+    }
+
+    /// Like [`definitions_for_reference`][Self::definitions_for_reference], but checks the
+    /// cancellation flag while stitching, and returns [`DefinitionsError::Cancelled`][] if it
+    /// fires.
+    pub fn definitions_for_reference_with_cancellation(
+        &self,
+        reference: Handle<Node>,
+        partials: &mut PartialPaths,
+        db: &Database,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<Handle<Node>>, DefinitionsError> {
+        if !self[reference].is_reference() {
+            return Err(DefinitionsError::NotAReference(reference));
+        }
+        let mut definitions = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut DatabaseCandidates::new(self, partials, db),
+            std::iter::once(reference),
+            StitcherConfig::default(),
+            cancellation_flag,
+            |graph, _, path| {
+                if graph[path.end_node].is_definition() {
+                    definitions.push(path.end_node);
+                }
+            },
+        )
+        .map_err(StitchingError::into_candidates_error)?;
+        definitions.sort();
+        definitions.dedup();
+        Ok(definitions)
+    }
+
+    /// Returns all references that resolve to a definition, by stitching together the partial
+    /// paths in the database.  The database must already contain all partial paths that might be
+    /// needed, as for [`definitions_for_reference`][Self::definitions_for_reference].  The
+    /// returned references are deduplicated and sorted by handle.  Returns an error if
+    /// `definition` is not a definition node.
+    ///
+    /// Stitching can only proceed forward from references, so this resolves _every_ reference in
+    /// the graph and keeps the ones that end up at `definition`.  That is as expensive as looking
+    /// up the definitions of all references, so callers that need the references of many
+    /// definitions should do that once and invert the result instead.
+    pub fn references_for_definition(
+        &self,
+        definition: Handle<Node>,
+        partials: &mut PartialPaths,
+        db: &Database,
+    ) -> Result<Vec<Handle<Node>>, DefinitionsError> {
+        self.references_for_definition_with_cancellation(definition, partials, db, &NoCancellation)
+    }
+
+    /// Like [`references_for_definition`][Self::references_for_definition], but checks the
+    /// cancellation flag while stitching, and returns [`DefinitionsError::Cancelled`][] if it
+    /// fires.
+    pub fn references_for_definition_with_cancellation(
+        &self,
+        definition: Handle<Node>,
+        partials: &mut PartialPaths,
+        db: &Database,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<Vec<Handle<Node>>, DefinitionsError> {
+        if !self[definition].is_definition() {
+            return Err(DefinitionsError::NotADefinition(definition));
+        }
+        let mut references = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut DatabaseCandidates::new(self, partials, db),
+            self.iter_references(),
+            StitcherConfig::default(),
+            cancellation_flag,
+            |_, _, path| {
+                if path.end_node == definition {
+                    references.push(path.start_node);
+                }
+            },
+        )
+        .map_err(StitchingError::into_candidates_error)?;
+        references.sort();
+        references.dedup();
+        Ok(references)
+    }
+
+    /// Returns whether there is a complete path from `from` to `to`, by stitching together the
+    /// partial paths in the database, as for
+    /// [`definitions_for_reference`][Self::definitions_for_reference].  Paths are found lazily,
+    /// so this stops as soon as the first such path is found.  Since only complete paths are
+    /// considered, this is always `false` unless `from` is a reference and `to` a definition.
+    pub fn is_reachable(
+        &self,
+        from: Handle<Node>,
+        to: Handle<Node>,
+        partials: &mut PartialPaths,
+        db: &Database,
+    ) -> bool {
+        self.is_reachable_with_cancellation(from, to, partials, db, &NoCancellation)
+            .expect("should never be cancelled")
+    }
+
+    /// Like [`is_reachable`][Self::is_reachable], but checks the cancellation flag while
+    /// stitching.  Returns an error if the flag fires before a path from `from` to `to` is found.
+    pub fn is_reachable_with_cancellation(
+        &self,
+        from: Handle<Node>,
+        to: Handle<Node>,
+        partials: &mut PartialPaths,
+        db: &Database,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<bool, CancellationError> {
+        let mut candidates = DatabaseCandidates::new(self, partials, db);
+        let mut paths: PartialPathIterator<_, _, _, _, CancellationError> =
+            PartialPathIterator::new(
+                &mut candidates,
+                std::iter::once(from),
+                StitcherConfig::default(),
+                cancellation_flag,
+            );
+        let found = paths.any(|path| path.end_node == to);
+        match paths.error() {
+            Some(error) => Err(error.clone().into_candidates_error()),
+            None => Ok(found),
         }
     }
 }
@@ -31,10 +31,12 @@ use crate::serde;
 use crate::serde::FileFilter;
 use crate::stitching::Database;
 use crate::stitching::ForwardCandidates;
+use crate::stitching::ResourceExhausted;
+use crate::stitching::StitchingError;
 use crate::CancellationError;
 use crate::CancellationFlag;
 
-const VERSION: usize = 6;
+const VERSION: usize = 9;
 
 const SCHEMA: &str = r#"
         CREATE TABLE metadata (
@@ -90,6 +92,17 @@ pub enum StorageError {
     SerializeFail(#[from] EncodeError),
     #[error(transparent)]
     DeserializeFail(#[from] DecodeError),
+    #[error(transparent)]
+    ResourceExhausted(#[from] ResourceExhausted),
+}
+
+impl From<StitchingError<StorageError>> for StorageError {
+    fn from(err: StitchingError<StorageError>) -> Self {
+        match err {
+            StitchingError::Candidates(err) => err,
+            StitchingError::ResourceExhausted(err) => err.into(),
+        }
+    }
 }
 
 pub type Result<T> = std::result::Result<T, StorageError>;
@@ -0,0 +1,101 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+//! Helpers for writing resolution tests against small, hand-built stack graphs.
+//!
+//! This module is only available when the `testing` feature is enabled.  It provides shortcuts
+//! for the most common fixture shape — definitions and references that are connected directly to
+//! the root node — and an assertion that checks which definitions a reference resolves to:
+//!
+//! ```
+//! # use stack_graphs::graph::StackGraph;
+//! let mut graph = StackGraph::new();
+//! let a = graph.get_or_create_file("a.py");
+//! let b = graph.get_or_create_file("b.py");
+//! let definition = graph.add_definition(a, "x");
+//! let reference = graph.add_reference(b, "x");
+//! graph.assert_resolves(reference, &[definition]);
+//! ```
+
+use itertools::Itertools;
+
+use crate::arena::Handle;
+use crate::graph::File;
+use crate::graph::Node;
+use crate::graph::StackGraph;
+use crate::partial::PartialPaths;
+use crate::stitching::ForwardPartialPathStitcher;
+use crate::stitching::GraphEdgeCandidates;
+use crate::stitching::StitcherConfig;
+use crate::NoCancellation;
+
+impl StackGraph {
+    /// Adds a definition of `symbol` to `file`, reachable from the root node.
+    ///
+    /// The new node is a _pop symbol_ definition node with a fresh local ID, with an edge from
+    /// the root node to it.
+    pub fn add_definition(&mut self, file: Handle<File>, symbol: &str) -> Handle<Node> {
+        let id = self.new_node_id(file);
+        let symbol = self.add_symbol(symbol);
+        let node = self
+            .add_pop_symbol_node(id, symbol, true)
+            .expect("fresh node ID should not be in use");
+        self.add_edge(StackGraph::root_node(), node, 0);
+        node
+    }
+
+    /// Adds a reference to `symbol` in `file`, which is resolved via the root node.
+    ///
+    /// The new node is a _push symbol_ reference node with a fresh local ID, with an edge from
+    /// it to the root node.
+    pub fn add_reference(&mut self, file: Handle<File>, symbol: &str) -> Handle<Node> {
+        let id = self.new_node_id(file);
+        let symbol = self.add_symbol(symbol);
+        let node = self
+            .add_push_symbol_node(id, symbol, true)
+            .expect("fresh node ID should not be in use");
+        self.add_edge(node, StackGraph::root_node(), 0);
+        node
+    }
+
+    /// Asserts that `reference` resolves to exactly the definitions in `expected`, in any order.
+    ///
+    /// Resolution uses forward partial path stitching over the edges of this graph, with the
+    /// default [`StitcherConfig`][].  Panics with the displayed nodes if the actual definitions
+    /// differ from the expected ones.
+    pub fn assert_resolves(&self, reference: Handle<Node>, expected: &[Handle<Node>]) {
+        let mut partials = PartialPaths::new();
+        let mut actual = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(self, &mut partials, None),
+            std::iter::once(reference),
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, _, path| {
+                if graph[path.end_node].is_definition() {
+                    actual.push(path.end_node);
+                }
+            },
+        )
+        .expect("should never be cancelled");
+        let actual = actual.into_iter().sorted().dedup().collect::<Vec<_>>();
+        let expected = expected
+            .iter()
+            .copied()
+            .sorted()
+            .dedup()
+            .collect::<Vec<_>>();
+        if actual != expected {
+            panic!(
+                "{} resolves to [{}], expected [{}]",
+                self[reference].display(self),
+                actual.iter().map(|n| self[*n].display(self)).join(", "),
+                expected.iter().map(|n| self[*n].display(self)).join(", "),
+            );
+        }
+    }
+}
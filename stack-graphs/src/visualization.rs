@@ -14,6 +14,8 @@ use crate::graph::StackGraph;
 use crate::partial::PartialPath;
 use crate::partial::PartialPaths;
 use crate::serde::Filter;
+use crate::serde::NodeID;
+use crate::serde::SourceInfo;
 use crate::stitching::Database;
 
 static CSS: &'static str = include_str!("visualization/visualization.css");
@@ -34,10 +36,30 @@ impl StackGraph {
         partials: &mut PartialPaths,
         db: &mut Database,
         filter: &dyn Filter,
+    ) -> Result<String, Error> {
+        self.to_html_string_with_highlights(title, partials, db, filter, &[])
+    }
+
+    /// Renders the same self-contained HTML visualization as
+    /// [`to_html_string`][Self::to_html_string], with the given nodes highlighted.  This can be
+    /// used to mark the nodes of a particular resolution path.
+    pub fn to_html_string_with_highlights(
+        &self,
+        title: &str,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+        filter: &dyn Filter,
+        highlighted_nodes: &[Handle<Node>],
     ) -> Result<String, Error> {
         let filter = VisualizationFilter(filter);
         let graph = serde_json::to_string(&self.to_serializable_filter(&filter))?;
         let paths = serde_json::to_string(&db.to_serializable_filter(self, partials, &filter))?;
+        let highlighted = serde_json::to_string(
+            &highlighted_nodes
+                .iter()
+                .map(|node| NodeID::from_node(self, *node))
+                .collect::<Vec<_>>(),
+        )?;
         let html = format!(
             r#"
 <!DOCTYPE html>
@@ -71,6 +93,7 @@ impl StackGraph {
 <script type="text/javascript">
   let graph = {graph};
   let paths = {paths};
+  let highlighted = {highlighted};
 </script>
 
 <style>
@@ -89,7 +112,7 @@ impl StackGraph {
   </div>
   <script type="text/javascript">
     const container = d3.select("\#container");
-    new StackGraph(container, graph, paths, {{ version: "{PKG} {VERSION}" }});
+    new StackGraph(container, graph, paths, {{ version: "{PKG} {VERSION}", highlighted }});
   </script>
 </body>
 
@@ -98,6 +121,133 @@ impl StackGraph {
         );
         Ok(html)
     }
+
+    /// Writes this stack graph in the [Cytoscape.js][] JSON format, as an `elements` object with
+    /// a list of `nodes` and a list of `edges`.  The data of each node contains its ID, kind,
+    /// symbol, and span, and the data of each edge contains its source and target node IDs and
+    /// its precedence.  Node IDs are formatted as `file:local_id`, or just `local_id` for the
+    /// singleton nodes.  Nodes without a symbol or span omit those fields.
+    ///
+    /// [Cytoscape.js]: https://js.cytoscape.org/#notation/elements-json
+    pub fn to_cytoscape_json<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        let graph = self.to_serializable();
+        let nodes = graph
+            .nodes
+            .data
+            .iter()
+            .map(|node| CytoscapeElement {
+                data: CytoscapeNode::from(node),
+            })
+            .collect();
+        let edges = graph
+            .edges
+            .data
+            .iter()
+            .map(|edge| CytoscapeElement {
+                data: CytoscapeEdge {
+                    id: format!("{}->{}", edge.source, edge.sink),
+                    source: edge.source.to_string(),
+                    target: edge.sink.to_string(),
+                    precedence: edge.precedence,
+                },
+            })
+            .collect();
+        serde_json::to_writer(
+            writer,
+            &CytoscapeDocument {
+                elements: CytoscapeElements { nodes, edges },
+            },
+        )
+    }
+}
+
+//-----------------------------------------------------------------------------
+// Cytoscape.js
+
+#[derive(serde::Serialize)]
+struct CytoscapeDocument {
+    elements: CytoscapeElements,
+}
+
+#[derive(serde::Serialize)]
+struct CytoscapeElements {
+    nodes: Vec<CytoscapeElement<CytoscapeNode>>,
+    edges: Vec<CytoscapeElement<CytoscapeEdge>>,
+}
+
+#[derive(serde::Serialize)]
+struct CytoscapeElement<T> {
+    data: T,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(serde::Serialize)]
+struct CytoscapeNode {
+    id: String,
+    kind: &'static str,
+    symbol: Option<String>,
+    span: Option<lsp_positions::Span>,
+}
+
+impl From<&crate::serde::Node> for CytoscapeNode {
+    fn from(node: &crate::serde::Node) -> Self {
+        use crate::serde::Node::*;
+        let (kind, id, symbol, source_info) = match node {
+            DropScopes {
+                id, source_info, ..
+            } => ("drop_scopes", id, None, source_info),
+            JumpToScope {
+                id, source_info, ..
+            } => ("jump_to_scope", id, None, source_info),
+            PopScopedSymbol {
+                id,
+                symbol,
+                source_info,
+                ..
+            } => ("pop_scoped_symbol", id, Some(symbol), source_info),
+            PopSymbol {
+                id,
+                symbol,
+                source_info,
+                ..
+            } => ("pop_symbol", id, Some(symbol), source_info),
+            PushScopedSymbol {
+                id,
+                symbol,
+                source_info,
+                ..
+            } => ("push_scoped_symbol", id, Some(symbol), source_info),
+            PushSymbol {
+                id,
+                symbol,
+                source_info,
+                ..
+            } => ("push_symbol", id, Some(symbol), source_info),
+            Root {
+                id, source_info, ..
+            } => ("root", id, None, source_info),
+            Scope {
+                id, source_info, ..
+            } => ("scope", id, None, source_info),
+        };
+        CytoscapeNode {
+            id: id.to_string(),
+            kind,
+            symbol: symbol.cloned(),
+            span: source_info
+                .as_ref()
+                .map(|SourceInfo { span, .. }| span.clone())
+                .filter(|span| *span != lsp_positions::Span::default()),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct CytoscapeEdge {
+    id: String,
+    source: String,
+    target: String,
+    precedence: i32,
 }
 
 struct VisualizationFilter<'a>(&'a dyn Filter);
@@ -24,6 +24,7 @@ class StackGraph {
 
         this.graph = graph;
         this.paths = paths;
+        this.highlighted = new Set((metadata.highlighted || []).map((id) => this.node_id_to_str(id)));
         this.cleanup_data();
         this.compute_data();
 
@@ -313,6 +314,9 @@ class StackGraph {
     render_node(node, g) {
         g.attr('id', this.node_to_id_str(node));
         g.attr('class', `node ${node.type} ${this.node_to_file_class(node)}`);
+        if (this.highlighted.has(this.node_to_id_str(node))) {
+            g.classed('highlighted', true);
+        }
 
         switch (node.type) {
             case "drop_scopes":
@@ -5,14 +5,23 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::BTreeMap;
+use std::hash::Hash;
+
 use stack_graphs::arena::Arena;
 use stack_graphs::arena::Deque;
 use stack_graphs::arena::DequeArena;
+use stack_graphs::arena::Handle;
 use stack_graphs::arena::List;
 use stack_graphs::arena::ListArena;
 use stack_graphs::arena::ReversibleList;
 use stack_graphs::arena::ReversibleListArena;
 use stack_graphs::arena::SupplementalArena;
+use stack_graphs::graph::File;
+use stack_graphs::graph::InternedString;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::StackGraph;
+use stack_graphs::graph::Symbol;
 
 #[test]
 fn can_allocate_in_arena() {
@@ -28,6 +37,38 @@ fn can_allocate_in_arena() {
     assert_ne!(arena.get(hello2), arena.get(there));
 }
 
+#[test]
+fn handles_are_ordered_by_insertion() {
+    fn assert_key<K: Copy + Eq + Ord + Hash>() {}
+    assert_key::<Handle<File>>();
+    assert_key::<Handle<InternedString>>();
+    assert_key::<Handle<Node>>();
+    assert_key::<Handle<Symbol>>();
+
+    let mut arena = Arena::new();
+    let handles = ["c", "a", "b"]
+        .iter()
+        .map(|s| arena.add(s.to_string()))
+        .collect::<Vec<_>>();
+    assert!(handles.windows(2).all(|w| w[0] < w[1]));
+    assert!(handles.windows(2).all(|w| w[0].as_u32() < w[1].as_u32()));
+    let by_handle = handles
+        .iter()
+        .map(|h| (*h, arena.get(*h).clone()))
+        .collect::<BTreeMap<_, _>>();
+    assert_eq!(
+        by_handle.values().map(String::as_str).collect::<Vec<_>>(),
+        vec!["c", "a", "b"]
+    );
+
+    let mut graph = StackGraph::new();
+    let symbols = ["c", "a", "b"]
+        .iter()
+        .map(|s| graph.add_symbol(s))
+        .collect::<Vec<_>>();
+    assert!(symbols.windows(2).all(|w| w[0] < w[1]));
+}
+
 #[test]
 fn can_allocate_in_supplemental_arena() {
     let mut arena = Arena::<u32>::new();
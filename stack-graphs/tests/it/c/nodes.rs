@@ -8,6 +8,7 @@
 use controlled_option::ControlledOption;
 use libc::c_char;
 use stack_graphs::arena::Handle;
+use stack_graphs::c::sg_debug_entries;
 use stack_graphs::c::sg_file_handle;
 use stack_graphs::c::sg_node;
 use stack_graphs::c::sg_node_handle;
@@ -19,12 +20,14 @@ use stack_graphs::c::sg_source_info;
 use stack_graphs::c::sg_span;
 use stack_graphs::c::sg_stack_graph;
 use stack_graphs::c::sg_stack_graph_add_files;
+use stack_graphs::c::sg_stack_graph_add_node_debug_info;
 use stack_graphs::c::sg_stack_graph_add_source_infos;
 use stack_graphs::c::sg_stack_graph_add_strings;
 use stack_graphs::c::sg_stack_graph_add_symbols;
 use stack_graphs::c::sg_stack_graph_free;
 use stack_graphs::c::sg_stack_graph_get_or_create_nodes;
 use stack_graphs::c::sg_stack_graph_new;
+use stack_graphs::c::sg_stack_graph_node_debug_info;
 use stack_graphs::c::sg_stack_graph_nodes;
 use stack_graphs::c::sg_stack_graph_source_infos;
 use stack_graphs::c::sg_string_handle;
@@ -702,3 +705,44 @@ fn can_create_source_info() {
 
     sg_stack_graph_free(graph);
 }
+
+//-------------------------------------------------------------------------------------------------
+// Debug info
+
+fn get_debug_info(
+    graph: *const sg_stack_graph,
+    node: sg_node_handle,
+) -> Vec<(sg_string_handle, sg_string_handle)> {
+    let sg_debug_entries { entries, count } = sg_stack_graph_node_debug_info(graph, node);
+    if count == 0 {
+        return Vec::new();
+    }
+    let entries = unsafe { std::slice::from_raw_parts(entries, count) };
+    entries
+        .iter()
+        .map(|entry| (entry.key, entry.value))
+        .collect()
+}
+
+#[test]
+fn can_create_debug_info() {
+    let graph = sg_stack_graph_new();
+    let file = add_file(graph, "test.py");
+    let nodes = [exported_scope(file, 0), exported_scope(file, 1)];
+    let mut handles: [sg_node_handle; 2] = [0; 2];
+    sg_stack_graph_get_or_create_nodes(graph, nodes.len(), nodes.as_ptr(), handles.as_mut_ptr());
+
+    let key = add_string(graph, "key");
+    let value = add_string(graph, "value");
+    let other_value = add_string(graph, "other value");
+    sg_stack_graph_add_node_debug_info(graph, handles[1], key, value);
+    sg_stack_graph_add_node_debug_info(graph, handles[1], key, other_value);
+
+    assert_eq!(get_debug_info(graph, handles[0]), vec![]);
+    assert_eq!(
+        get_debug_info(graph, handles[1]),
+        vec![(key, value), (key, other_value)]
+    );
+
+    sg_stack_graph_free(graph);
+}
@@ -184,12 +184,14 @@ fn finding_simple_identity_cycle_is_detected() {
                 .is_cyclic(&graph, &mut partials, db, &mut edges)
                 .unwrap()
                 .is_empty());
+            assert!(cd.report(db, &edges).is_empty());
         }
         cd.append(&mut edges, edge(foo_def, r, 0));
         assert_eq!(
             enum_set![Cyclicity::StrengthensPostcondition],
             cd.is_cyclic(&graph, &mut partials, db, &mut edges).unwrap()
         );
+        assert_eq!(vec![r, foo_ref, s, foo_def, r], cd.report(db, &edges));
     }
 
     // test termination of path finding
@@ -5,10 +5,23 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::num::NonZeroU32;
 
 use maplit::hashset;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::ConcurrentGraphBuilder;
+use stack_graphs::graph::Degree;
+use stack_graphs::graph::EdgeError;
+use stack_graphs::graph::FileError;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::NodeError;
+use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::graph::Symbol;
+use stack_graphs::graph::SymbolInterner;
+use stack_graphs::graph::ValidationError;
 
 use crate::test_graphs;
 use crate::test_graphs::CreateStackGraph;
@@ -39,6 +52,122 @@ fn can_create_symbols() {
     assert_ne!(empty1, a1);
 }
 
+#[test]
+fn can_create_symbols_from_bytes() {
+    let mut graph = StackGraph::new();
+    let a1 = graph.add_symbol("a");
+    let a2 = graph.add_symbol_bytes(b"a");
+    assert_eq!(a1, a2);
+    assert_eq!(graph.symbol_bytes(a1), b"a");
+
+    let invalid1 = graph.add_symbol_bytes(b"b\xffc");
+    let invalid2 = graph.add_symbol_bytes(b"b\xffc");
+    assert_eq!(invalid1, invalid2);
+    assert_eq!(graph.symbol_bytes(invalid1), b"b\xffc");
+    assert_eq!(&graph[invalid1], "b\u{FFFD}c");
+    assert_eq!(invalid1.display(&graph).to_string(), "b\u{FFFD}c");
+    // The lossy representation is not the same symbol as the raw bytes.
+    let lossy = graph.add_symbol("b\u{FFFD}c");
+    assert_ne!(lossy, invalid1);
+}
+
+#[test]
+fn can_count_symbol_usages() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let y = graph.symbol("y");
+    let z = graph.symbol("z");
+    graph.symbol("unused");
+    graph.definition(file, 1, z);
+    graph.definition(file, 2, x);
+    graph.reference(file, 3, x);
+    graph.push_symbol(file, 4, x);
+    graph.pop_scoped_symbol(file, 5, y);
+    graph.reference(file, 6, z);
+    graph.internal_scope(file, 7);
+
+    assert_eq!(graph.symbol_usage_counts(), vec![(x, 3), (z, 2), (y, 1)]);
+}
+
+#[derive(Default)]
+struct VecSymbolInterner {
+    symbols: Vec<String>,
+    handles: HashMap<String, Handle<Symbol>>,
+}
+
+impl SymbolInterner for VecSymbolInterner {
+    fn intern(&mut self, symbol: &str) -> Handle<Symbol> {
+        if let Some(handle) = self.handles.get(symbol) {
+            return *handle;
+        }
+        self.symbols.push(symbol.to_string());
+        let handle = Handle::new(NonZeroU32::new(self.symbols.len() as u32).unwrap());
+        self.handles.insert(symbol.to_string(), handle);
+        handle
+    }
+
+    fn resolve(&self, handle: Handle<Symbol>) -> &str {
+        &self.symbols[handle.as_usize() - 1]
+    }
+
+    fn lookup(&self, symbol: &str) -> Option<Handle<Symbol>> {
+        self.handles.get(symbol).copied()
+    }
+
+    fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    fn clear(&mut self) {
+        self.symbols.clear();
+        self.handles.clear();
+    }
+}
+
+#[test]
+fn can_use_custom_symbol_interner() {
+    let mut graph = StackGraph::with_symbol_interner(VecSymbolInterner::default());
+    let a1 = graph.add_symbol("a");
+    let b = graph.add_symbol("b");
+    let a2 = graph.add_symbol("a");
+    assert_eq!(a1, a2);
+    assert_ne!(a1, b);
+    assert_eq!(&graph[a1], "a");
+    assert_eq!(&graph[b], "b");
+    assert_eq!(graph.get_symbol("b"), Some(b));
+    assert_eq!(graph.get_symbol("c"), None);
+    assert_eq!(graph.iter_symbols().collect::<Vec<_>>(), vec![a1, b]);
+
+    let file = graph.file("test.py");
+    let reference = graph.reference(file, 1, a1);
+    assert_eq!(graph[reference].symbol(), Some(a1));
+    assert!(graph.validate().is_ok());
+}
+
+#[test]
+fn can_normalize_symbols() {
+    let mut graph = StackGraph::with_symbol_normalizer(|s| s.to_lowercase());
+    let foo1 = graph.add_symbol("Foo");
+    let foo2 = graph.add_symbol("foo");
+    let bar = graph.add_symbol("bar");
+    assert_eq!(foo1, foo2);
+    assert_ne!(foo1, bar);
+    assert_eq!(&graph[foo2], "Foo");
+    assert_eq!(graph.get_symbol("FOO"), Some(foo1));
+    assert_eq!(graph.get_symbol("BAR"), Some(bar));
+    assert_eq!(graph.get_symbol("baz"), None);
+    assert_eq!(graph.iter_symbols().count(), 2);
+
+    let file = graph.file("test.sql");
+    let definition = graph.definition(file, 1, foo1);
+    let reference = graph.reference(file, 2, foo2);
+    assert_eq!(graph[definition].symbol(), graph[reference].symbol());
+
+    let mut graph = StackGraph::new();
+    assert_ne!(graph.add_symbol("Foo"), graph.add_symbol("foo"));
+}
+
 #[test]
 fn can_iterate_symbols() {
     let mut graph = StackGraph::new();
@@ -54,6 +183,27 @@ fn can_iterate_symbols() {
     assert_eq!(symbols, hashset! {"a", "b", "c"});
 }
 
+#[test]
+fn symbols_and_strings_are_iterated_in_insertion_order() {
+    let mut graph = StackGraph::new();
+    assert_eq!(graph.symbols_len(), 0);
+    for symbol in &["c", "a", "b", "a"] {
+        graph.add_symbol(symbol);
+        graph.add_string(symbol);
+    }
+    assert_eq!(graph.symbols_len(), 3);
+    let symbols = graph
+        .iter_symbols()
+        .map(|symbol| &graph[symbol])
+        .collect::<Vec<_>>();
+    assert_eq!(symbols, vec!["c", "a", "b"]);
+    let strings = graph
+        .iter_strings()
+        .map(|string| &graph[string])
+        .collect::<Vec<_>>();
+    assert_eq!(strings, vec!["c", "a", "b"]);
+}
+
 #[test]
 fn can_display_symbols() {
     let mut graph = StackGraph::new();
@@ -94,6 +244,20 @@ fn can_create_strings() {
     assert_ne!(empty1, a1);
 }
 
+#[test]
+fn can_look_up_existing_symbols_and_strings() {
+    let mut graph = StackGraph::new();
+    let a = graph.add_symbol("a");
+    let b = graph.add_string("b");
+    assert_eq!(graph.get_symbol("a"), Some(a));
+    assert_eq!(graph.get_symbol("b"), None);
+    assert_eq!(graph.get_string("b"), Some(b));
+    assert_eq!(graph.get_string("a"), None);
+    // looking up missing symbols and strings does not add them
+    assert_eq!(graph.iter_symbols().count(), 1);
+    assert_eq!(graph.iter_strings().count(), 1);
+}
+
 #[test]
 fn can_iterate_strings() {
     let mut graph = StackGraph::new();
@@ -159,6 +323,251 @@ fn can_add_and_remove_edges() {
     );
 }
 
+#[test]
+fn cannot_add_duplicate_edges() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let h1 = graph.internal_scope(file, 0);
+    let h2 = graph.internal_scope(file, 1);
+    assert!(graph.add_edge_checked(h1, h2, 0));
+    assert!(!graph.add_edge_checked(h1, h2, 0));
+    assert!(!graph.add_edge_checked(h1, h2, 1));
+    assert!(graph.add_edge_checked(h2, h1, 0));
+    assert_eq!(graph.edge_count(), 2);
+    assert_eq!(
+        graph
+            .outgoing_edges(h1)
+            .map(|edge| (edge.sink, edge.precedence))
+            .collect::<Vec<_>>(),
+        vec![(h2, 0)]
+    );
+    assert_eq!(graph.incoming_edge_degree(h2), Degree::One);
+}
+
+#[test]
+fn can_change_whether_scopes_are_exported() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let scope = graph.internal_scope(file, 0);
+    let sym = graph.symbol("a");
+    let def = graph.definition(file, 1, sym);
+    assert!(!graph[scope].is_exported_scope());
+    assert_eq!(graph.set_exported_scope(scope, true), Ok(()));
+    assert!(graph[scope].is_exported_scope());
+    assert_eq!(graph.set_exported_scope(scope, false), Ok(()));
+    assert!(!graph[scope].is_exported_scope());
+    assert_eq!(
+        graph.set_exported_scope(def, true),
+        Err(NodeError::NotAScope(def))
+    );
+    assert!(!graph[def].is_exported_scope());
+}
+
+#[test]
+fn can_report_memory_usage() {
+    let mut graph = StackGraph::new();
+    let empty = graph.memory_usage();
+    assert_eq!(
+        empty.total(),
+        empty.nodes
+            + empty.edges
+            + empty.symbols
+            + empty.strings
+            + empty.source_info
+            + empty.debug_info
+    );
+
+    let file = graph.get_or_create_file("test.py");
+    let sym = graph.symbol("a");
+    let mut last = graph.root_node();
+    for i in 0..100 {
+        let node = graph.definition(file, i + 1, sym);
+        graph.edge(last, node);
+        graph.source_info_mut(node).syntax_type = graph.add_string("function").into();
+        let key = graph.add_string("key");
+        let value = graph.add_string("value");
+        graph.node_debug_info_mut(node).add(key, value);
+        last = node;
+    }
+
+    let usage = graph.memory_usage();
+    assert!(usage.nodes > empty.nodes);
+    assert!(usage.edges > empty.edges);
+    assert!(usage.symbols > 0);
+    assert!(usage.strings > 0);
+    assert!(usage.source_info > empty.source_info);
+    assert!(usage.debug_info > empty.debug_info);
+    assert!(usage.total() > empty.total());
+}
+
+#[cfg(feature = "digests")]
+#[test]
+fn can_compute_file_digests() {
+    use stack_graphs::graph::File;
+
+    // Builds the same chain `reference -> scope -> scope -> root` and a root-reachable definition,
+    // assigning local IDs in the given order.
+    fn build(graph: &mut StackGraph, name: &str, ids: [u32; 4], reversed: bool) -> Handle<File> {
+        let file = graph.file(name);
+        let root = StackGraph::root_node();
+        let sym = graph.symbol("a");
+        let def = graph.definition(file, ids[0], sym);
+        let s1 = graph.internal_scope(file, ids[1]);
+        let s2 = graph.internal_scope(file, ids[2]);
+        let r = graph.reference(file, ids[3], sym);
+        graph.edge(root, def);
+        graph.edge(r, s1);
+        if reversed {
+            graph.edge(s2, s1);
+        } else {
+            graph.edge(s1, s2);
+        }
+        graph.edge(s2, root);
+        file
+    }
+
+    let mut graph = StackGraph::new();
+    let f1 = build(&mut graph, "f1.py", [0, 1, 2, 3], false);
+    let f2 = build(&mut graph, "f2.py", [7, 3, 5, 1], false);
+    let f3 = build(&mut graph, "f3.py", [0, 1, 2, 3], true);
+    assert_eq!(graph.file_digest(f1), graph.file_digest(f2));
+    assert_ne!(graph.file_digest(f1), graph.file_digest(f3));
+
+    let mut other = StackGraph::new();
+    let g1 = build(&mut other, "g1.py", [3, 2, 1, 0], false);
+    assert_eq!(graph.file_digest(f1), other.file_digest(g1));
+    let scope = other.internal_scope(g1, 4);
+    let internal = other.file_digest(g1);
+    assert_ne!(graph.file_digest(f1), internal);
+    other.set_exported_scope(scope, true).unwrap();
+    assert_ne!(internal, other.file_digest(g1));
+}
+
+#[test]
+fn can_add_batches_of_edges() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let h1 = graph.internal_scope(file, 0);
+    let h2 = graph.internal_scope(file, 1);
+    let h3 = graph.internal_scope(file, 2);
+    let h4 = graph.internal_scope(file, 3);
+    graph.add_edge(h1, h2, 0);
+    let result = graph.add_edges(vec![
+        (h2, h1, 0),
+        (h1, h3, 0),
+        (h1, h4, 0),
+        // If you try to overwrite an edge, the original edge takes precedence.
+        (h1, h3, 1),
+        (h1, h2, 1),
+    ]);
+    assert_eq!(result, Ok(()));
+    assert_eq!(
+        graph
+            .outgoing_edges(h1)
+            .map(|edge| (edge.sink, edge.precedence))
+            .collect::<HashSet<_>>(),
+        hashset! { (h2, 0), (h3, 0), (h4, 0) }
+    );
+    assert_eq!(graph.outgoing_edges(h2).count(), 1);
+    assert_eq!(graph.edge_count(), 4);
+
+    let missing = Handle::new(NonZeroU32::new(1000).unwrap());
+    let result = graph.add_edges(vec![(h3, h4, 0), (h3, missing, 0), (missing, h4, 0)]);
+    assert_eq!(
+        result,
+        Err(EdgeError::InvalidNode {
+            index: 1,
+            source: h3,
+            sink: missing
+        })
+    );
+    assert_eq!(graph.outgoing_edges(h3).count(), 0);
+    assert_eq!(graph.edge_count(), 4);
+}
+
+#[test]
+fn can_iterate_all_edges() {
+    let mut graph = StackGraph::new();
+    let file = graph.get_or_create_file("test.py");
+    let h1 = graph.internal_scope(file, 0);
+    let h2 = graph.internal_scope(file, 1);
+    let h3 = graph.internal_scope(file, 2);
+    graph.add_edge(h2, h1, 0);
+    graph.add_edge(h1, h3, 2);
+    graph.add_edge(h1, h2, 1);
+    graph.add_edge(StackGraph::root_node(), h1, 0);
+    assert_eq!(
+        graph
+            .all_edges()
+            .map(|edge| (edge.source, edge.sink, edge.precedence))
+            .collect::<Vec<_>>(),
+        vec![
+            (StackGraph::root_node(), h1, 0),
+            (h1, h2, 1),
+            (h1, h3, 2),
+            (h2, h1, 0),
+        ]
+    );
+    assert_eq!(graph.all_edges().count(), graph.edge_count());
+}
+
+#[test]
+fn can_clear_graph() {
+    fn build(graph: &mut StackGraph) {
+        let file = graph.file("test.py");
+        let x = graph.symbol("x");
+        let definition = graph.definition(file, 1, x);
+        let reference = graph.reference(file, 2, x);
+        let scope = graph.internal_scope(file, 3);
+        graph.edge(reference, scope);
+        graph.edge(scope, definition);
+        graph.edge(StackGraph::root_node(), scope);
+        let key = graph.add_string("key");
+        let value = graph.add_string("value");
+        graph.node_debug_info_mut(definition).add(key, value);
+        let syntax_type = graph.add_string("identifier");
+        graph.source_info_mut(reference).syntax_type = syntax_type.into();
+    }
+    fn describe(graph: &StackGraph) -> Vec<String> {
+        graph
+            .iter_nodes()
+            .map(|node| graph[node].display(graph).to_string())
+            .chain(graph.iter_nodes().flat_map(|node| {
+                graph
+                    .outgoing_edges(node)
+                    .map(move |edge| format!("{:?}", edge))
+            }))
+            .collect()
+    }
+
+    let mut expected = StackGraph::new();
+    build(&mut expected);
+
+    let mut graph = StackGraph::new();
+    build(&mut graph);
+    let other = graph.file("other.py");
+    for local_id in 0..100 {
+        graph.internal_scope(other, local_id);
+    }
+    graph.symbol("y");
+    let capacity = graph.node_capacity();
+
+    graph.clear();
+    assert_eq!(graph.node_count(), 2);
+    assert_eq!(graph.edge_count(), 0);
+    assert_eq!(graph.file_count(), 0);
+    assert_eq!(graph.iter_symbols().count(), 0);
+    assert_eq!(graph.get_file("test.py"), None);
+    assert_eq!(graph.get_symbol("x"), None);
+    assert!(graph.node_capacity() >= capacity);
+
+    build(&mut graph);
+    assert_eq!(describe(&graph), describe(&expected));
+    assert_eq!(graph.iter_symbols().count(), 1);
+    assert!(graph.validate().is_ok());
+    assert_eq!(graph.node_capacity(), capacity);
+}
+
 #[test]
 fn singleton_nodes_have_correct_ids() {
     let graph = StackGraph::new();
@@ -196,3 +605,873 @@ fn can_add_graph_to_empty_graph() {
         );
     }
 }
+
+#[test]
+fn can_map_nodes_when_adding_graph() {
+    let mut other = StackGraph::new();
+    let root = other.root_node();
+    let file1 = other.file("test1.py");
+    let file2 = other.file("test2.py");
+    let sym = other.symbol("x");
+    let def = other.definition(file1, 1, sym);
+    let r#ref = other.reference(file2, 1, sym);
+    other.edge(r#ref, root);
+    other.edge(root, def);
+    other.edge(r#ref, def);
+
+    let mut graph = StackGraph::new();
+    let existing_file = graph.file("existing.py");
+    let existing_sym = graph.symbol("x");
+    graph.definition(existing_file, 1, existing_sym);
+
+    let nodes = graph.add_from_graph(&other).expect("Adding graph failed");
+    assert_eq!(nodes[&root], StackGraph::root_node());
+    assert_eq!(nodes[&other.jump_to_node()], StackGraph::jump_to_node());
+    assert_eq!(graph[nodes[&def]].symbol(), Some(existing_sym));
+    assert_eq!(graph[nodes[&r#ref]].symbol(), Some(existing_sym));
+    assert_eq!(
+        graph
+            .outgoing_edges(nodes[&r#ref])
+            .map(|edge| edge.sink)
+            .collect::<HashSet<_>>(),
+        hashset! { StackGraph::root_node(), nodes[&def] }
+    );
+    assert!(graph
+        .outgoing_edges(StackGraph::root_node())
+        .any(|edge| edge.sink == nodes[&def]));
+}
+
+#[test]
+fn can_keep_edge_costs_when_adding_graph() {
+    let mut other = StackGraph::new();
+    let root = other.root_node();
+    let file = other.file("test.py");
+    let sym = other.symbol("x");
+    let def = other.definition(file, 1, sym);
+    let r#ref = other.reference(file, 2, sym);
+    other.edge(r#ref, def);
+    other.edge(root, def);
+    assert!(other.set_edge_cost(r#ref, def, 7));
+
+    let mut graph = StackGraph::new();
+    let nodes = graph.add_from_graph(&other).expect("Adding graph failed");
+    let costs = |node: Handle<Node>| {
+        graph
+            .outgoing_edges(nodes[&node])
+            .map(|edge| edge.cost)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(costs(r#ref), vec![7]);
+    assert_eq!(costs(root), vec![0]);
+}
+
+#[test]
+fn cannot_add_graph_with_duplicate_file() {
+    let mut graph = test_graphs::simple::new();
+    let other = test_graphs::simple::new();
+    assert!(graph.add_from_graph(&other).is_err());
+}
+
+#[test]
+fn can_merge_graphs_built_concurrently() {
+    let builder = std::sync::Arc::new(ConcurrentGraphBuilder::new());
+    let workers = (0..4)
+        .map(|i| {
+            let builder = builder.clone();
+            std::thread::spawn(move || {
+                let mut graph = StackGraph::new();
+                let file = graph.file(&format!("file{}.py", i));
+                let root = graph.root_node();
+                let symbol = graph.symbol("shared");
+                let def = graph.definition(file, 0, symbol);
+                let r#ref = graph.reference(file, 1, symbol);
+                graph.edge(root, def);
+                graph.edge(r#ref, root);
+                builder.add(&graph).expect("Adding graph failed");
+            })
+        })
+        .collect::<Vec<_>>();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let builder = std::sync::Arc::try_unwrap(builder).ok().unwrap();
+    let graph = builder.into_graph();
+    assert_eq!(graph.iter_files().count(), 4);
+    assert_eq!(graph.node_count(), 2 + 4 * 2);
+    assert_eq!(graph.edge_count(), 4 * 2);
+    assert_eq!(
+        graph
+            .iter_symbols()
+            .filter(|symbol| &graph[*symbol] == "shared")
+            .count(),
+        1
+    );
+
+    let builder = ConcurrentGraphBuilder::default();
+    builder.add(&graph).expect("Adding graph failed");
+    assert!(builder.add(&graph).is_err());
+}
+
+#[test]
+fn can_display_full_graph() {
+    fn build(reversed: bool) -> StackGraph {
+        let mut graph = StackGraph::new();
+        let mut files = vec!["b.py", "a.py"];
+        if reversed {
+            files.reverse();
+        }
+        for name in files {
+            let file = graph.file(name);
+            let root = graph.root_node();
+            let x = graph.symbol("x");
+            let scope = graph.exported_scope(file, 0);
+            let def = graph.definition(file, 2, x);
+            let r#ref = graph.reference(file, 1, x);
+            graph.source_info_mut(def).span = span((1, 4), (1, 5));
+            graph.edge(scope, def);
+            graph.edge(r#ref, scope);
+            graph.edge(root, scope);
+            graph.add_edge(r#ref, root, 1);
+        }
+        graph
+    }
+
+    let expected = r#"singletons
+  [root]
+    -> [a.py(0) exported scope] (0)
+    -> [b.py(0) exported scope] (0)
+  [jump to scope]
+file a.py
+  [a.py(0) exported scope]
+    -> [a.py(2) definition x] (0)
+  [a.py(1) reference x]
+    -> [root] (1)
+    -> [a.py(0) exported scope] (0)
+  [a.py(2) definition x] 1:4-1:5
+file b.py
+  [b.py(0) exported scope]
+    -> [b.py(2) definition x] (0)
+  [b.py(1) reference x]
+    -> [root] (1)
+    -> [b.py(0) exported scope] (0)
+  [b.py(2) definition x] 1:4-1:5
+"#;
+    assert_eq!(build(false).display_full().to_string(), expected);
+    assert_eq!(build(true).display_full().to_string(), expected);
+}
+
+#[test]
+fn can_count_graph_elements() {
+    let mut graph = StackGraph::new();
+    assert_eq!(graph.file_count(), 0);
+    assert_eq!(graph.node_count(), 2);
+    assert_eq!(graph.edge_count(), 0);
+
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let sym = graph.symbol("x");
+    let def = graph.definition(file, 1, sym);
+    let r#ref = graph.reference(file, 2, sym);
+    graph.edge(r#ref, root);
+    graph.edge(root, def);
+    // adding an existing edge does not change the count
+    graph.edge(root, def);
+    assert_eq!(graph.file_count(), 1);
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 2);
+
+    let other = test_graphs::simple::new();
+    assert_eq!(other.file_count(), other.iter_files().count());
+    assert_eq!(other.node_count(), other.iter_nodes().count());
+    assert_eq!(
+        other.edge_count(),
+        other
+            .iter_nodes()
+            .map(|n| other.outgoing_edges(n).count())
+            .sum::<usize>()
+    );
+}
+
+#[test]
+fn can_classify_nodes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let definition = graph.definition(file, 1, x);
+    let reference = graph.reference(file, 2, x);
+    let pop = graph.pop_symbol(file, 3, x);
+    let push = graph.push_symbol(file, 4, x);
+    let exported = graph.exported_scope(file, 5);
+    let pop_scoped = graph.pop_scoped_symbol(file, 6, x);
+    let push_scoped = graph.push_scoped_symbol(file, 7, x, file, 5);
+    let root = graph.root_node();
+
+    let classify = |node: Handle<Node>| {
+        let node = &graph[node];
+        (
+            node.is_definition(),
+            node.is_reference(),
+            node.is_scoped(),
+            node.has_symbol(),
+        )
+    };
+    assert_eq!(classify(definition), (true, false, false, true));
+    assert_eq!(classify(reference), (false, true, false, true));
+    assert_eq!(classify(pop), (false, false, false, true));
+    assert_eq!(classify(push), (false, false, false, true));
+    assert_eq!(classify(exported), (false, false, false, false));
+    assert_eq!(classify(pop_scoped), (false, false, true, true));
+    assert_eq!(classify(push_scoped), (false, false, true, true));
+    assert_eq!(classify(root), (false, false, false, false));
+}
+
+fn span(start: (usize, usize), end: (usize, usize)) -> lsp_positions::Span {
+    let position = |(line, column)| lsp_positions::Position {
+        line,
+        column: lsp_positions::Offset {
+            utf8_offset: column,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    lsp_positions::Span {
+        start: position(start),
+        end: position(end),
+    }
+}
+
+#[test]
+fn can_find_nodes_at_position() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let function = graph.internal_scope(file, 1);
+    let statement = graph.internal_scope(file, 2);
+    let reference = graph.reference(file, 3, x);
+    let other = graph.definition(file, 4, x);
+    graph.internal_scope(file, 5);
+    graph.source_info_mut(function).span = span((0, 0), (3, 0));
+    graph.source_info_mut(statement).span = span((1, 4), (1, 12));
+    graph.source_info_mut(reference).span = span((1, 8), (1, 9));
+    graph.source_info_mut(other).span = span((2, 4), (2, 5));
+
+    assert_eq!(
+        graph.nodes_at_position(file, 1, 8),
+        vec![reference, statement, function]
+    );
+    assert_eq!(
+        graph.nodes_at_position(file, 1, 9),
+        vec![statement, function]
+    );
+    assert_eq!(graph.nodes_at_position(file, 2, 0), vec![function]);
+    assert_eq!(graph.nodes_at_position(file, 3, 0), vec![]);
+}
+
+#[test]
+fn can_compute_node_byte_ranges() {
+    let source = "def f():\n    return x\n";
+    let mut calculator = lsp_positions::SpanCalculator::new(source);
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let function = graph.internal_scope(file, 1);
+    let reference = graph.reference(file, 2, x);
+    let unknown = graph.internal_scope(file, 3);
+    let partial = graph.internal_scope(file, 4);
+    graph.source_info_mut(function).span = lsp_positions::Span {
+        start: calculator.for_line_and_column(0, 0, 0),
+        end: calculator.for_line_and_column(1, 9, 12),
+    };
+    graph.source_info_mut(reference).span = lsp_positions::Span {
+        start: calculator.for_line_and_column(1, 9, 11),
+        end: calculator.for_line_and_column(1, 9, 12),
+    };
+    // spans without the byte offsets of their lines can only be resolved on the first line
+    graph.source_info_mut(partial).span = span((1, 11), (1, 12));
+
+    assert_eq!(graph.node_byte_range(function), Some(0..21));
+    assert_eq!(graph.node_byte_range(reference), Some(20..21));
+    assert_eq!(&source[graph.node_byte_range(reference).unwrap()], "x");
+    assert_eq!(graph.node_byte_range(unknown), None);
+    assert_eq!(graph.node_byte_range(partial), None);
+}
+
+#[test]
+fn can_find_enclosing_scopes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let module = graph.internal_scope(file, 1);
+    let function = graph.internal_scope(file, 2);
+    let other = graph.internal_scope(file, 3);
+    let definition = graph.definition(file, 4, x);
+    let reference = graph.reference(file, 5, x);
+    let shared = graph.reference(file, 6, x);
+    let orphan = graph.reference(file, 7, x);
+    graph.edge(module, function);
+    graph.edge(function, definition);
+    graph.edge(definition, reference);
+    graph.edge(function, shared);
+    graph.edge(other, shared);
+    graph.edge(function, module);
+
+    assert_eq!(graph.enclosing_scopes(definition), vec![function]);
+    assert_eq!(graph.enclosing_scope(reference), Some(function));
+    assert_eq!(graph.enclosing_scopes(shared), vec![function, other]);
+    assert_eq!(graph.enclosing_scope(shared), Some(function));
+    assert_eq!(graph.enclosing_scope(function), Some(module));
+    assert_eq!(graph.enclosing_scopes(other), vec![]);
+    assert_eq!(graph.enclosing_scope(orphan), None);
+}
+
+#[test]
+fn can_find_file_boundary_edges() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let other_file = graph.file("other.py");
+    let x = graph.symbol("x");
+    let root = graph.root_node();
+    let jump_to = graph.jump_to_node();
+    let definition = graph.definition(file, 1, x);
+    let scope = graph.internal_scope(file, 2);
+    let reference = graph.reference(file, 3, x);
+    let other = graph.internal_scope(other_file, 1);
+    graph.edge(root, definition);
+    graph.edge(scope, definition);
+    graph.edge(reference, scope);
+    graph.edge(scope, jump_to);
+    graph.edge(other, scope);
+    graph.edge(root, other);
+
+    let edges = graph
+        .file_boundary_edges(file)
+        .into_iter()
+        .map(|edge| (edge.source, edge.sink))
+        .collect::<HashSet<_>>();
+    assert_eq!(
+        edges,
+        hashset! {(root, definition), (scope, jump_to), (other, scope)}
+    );
+    let edges = graph
+        .file_boundary_edges(other_file)
+        .into_iter()
+        .map(|edge| (edge.source, edge.sink))
+        .collect::<HashSet<_>>();
+    assert_eq!(edges, hashset! {(other, scope), (root, other)});
+}
+
+#[test]
+fn can_set_file_language() {
+    let mut graph = StackGraph::new();
+    let python = graph.add_file("test.py").unwrap();
+    let other = graph.add_file("other.py").unwrap();
+    assert_eq!(graph.file_language(python), None);
+    graph.set_file_language(python, "python");
+    assert_eq!(graph.file_language(python), Some("python"));
+    assert_eq!(graph.file_language(other), None);
+
+    let mut copy = StackGraph::new();
+    copy.add_from_graph(&graph).unwrap();
+    let copied = copy.get_file("test.py").unwrap();
+    assert_eq!(copy.file_language(copied), Some("python"));
+
+    graph.clear();
+    let python = graph.add_file("test.py").unwrap();
+    assert_eq!(graph.file_language(python), None);
+}
+
+#[test]
+fn can_find_unreferenced_nodes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let root = graph.root_node();
+    let definition = graph.definition(file, 1, x);
+    let reference = graph.reference(file, 2, x);
+    let orphan = graph.internal_scope(file, 3);
+    let scope = graph.internal_scope(file, 4);
+    let other_orphan = graph.pop_symbol(file, 5, x);
+    graph.edge(root, definition);
+    graph.edge(reference, scope);
+    assert_eq!(graph.unreferenced_nodes(), vec![orphan, other_orphan]);
+}
+
+#[test]
+fn can_rename_files() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("old.py");
+    let other = graph.file("other.py");
+    let x = graph.symbol("x");
+    let node = graph.definition(file, 1, x);
+
+    assert_eq!(graph.rename_file(file, "new.py"), Ok(()));
+    assert_eq!(graph[file].name(), "new.py");
+    assert_eq!(graph.get_file("new.py"), Some(file));
+    assert_eq!(graph.get_file("old.py"), None);
+    assert_eq!(graph.node_for_id(NodeID::new_in_file(file, 1)), Some(node));
+
+    assert_eq!(graph.rename_file(file, "new.py"), Ok(()));
+    assert_eq!(
+        graph.rename_file(file, "other.py"),
+        Err(FileError::FileAlreadyExists(other))
+    );
+    assert_eq!(graph[file].name(), "new.py");
+
+    // the old name can be reused
+    let reused = graph.file("old.py");
+    assert_ne!(reused, file);
+}
+
+#[cfg(feature = "digests")]
+#[test]
+fn can_create_deterministic_node_ids() {
+    let build = |reversed: bool| {
+        let mut graph = StackGraph::with_deterministic_ids();
+        let file = graph.file("test.py");
+        let mut nodes = vec![
+            ("pop_symbol", Some("x"), span((0, 4), (0, 5))),
+            ("push_symbol", Some("x"), span((1, 0), (1, 1))),
+            ("scope", None, span((0, 0), (1, 1))),
+            // same content as the first node
+            ("pop_symbol", Some("x"), span((0, 4), (0, 5))),
+        ];
+        if reversed {
+            nodes[..3].reverse();
+        }
+        nodes
+            .into_iter()
+            .map(|(kind, symbol, span)| {
+                let id = graph.new_node_id_for_content(file, kind, symbol, &span);
+                graph.add_scope_node(id, false).unwrap();
+                (kind, symbol, span.start.line, id.local_id())
+            })
+            .collect::<HashSet<_>>()
+    };
+    let ids = build(false);
+    assert_eq!(ids.len(), 4);
+    assert_eq!(ids, build(true));
+    // the IDs are stable across library and compiler versions
+    assert!(ids.contains(&("scope", None, 0, 2073758951)));
+    assert!(ids.contains(&("pop_symbol", Some("x"), 0, 1115110142)));
+    assert!(ids.contains(&("pop_symbol", Some("x"), 0, 1115110143)));
+
+    let mut graph = StackGraph::with_deterministic_ids();
+    let file = graph.file("test.py");
+    let id = graph.new_node_id_for_content(file, "scope", None, &span((0, 0), (1, 1)));
+    let node = graph.add_scope_node(id, false).unwrap();
+    assert_eq!(graph.node_for_id(id), Some(node));
+    assert_eq!(graph.nodes_for_file(file).collect::<Vec<_>>(), vec![node]);
+    let next = graph.new_node_id(file);
+    assert_ne!(next, id);
+    let next_node = graph.add_scope_node(next, false).unwrap();
+    assert_eq!(graph.nodes_for_file(file).count(), 2);
+    graph.remove_file(file);
+    assert_eq!(graph.node_for_id(id), None);
+    assert_eq!(graph.node_for_id(next), None);
+    assert_ne!(node, next_node);
+
+    // graphs without deterministic IDs allocate them sequentially
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let id = graph.new_node_id_for_content(file, "scope", None, &span((0, 0), (1, 1)));
+    assert_eq!(id, graph.new_node_id(file));
+}
+
+#[test]
+fn can_list_sparse_nodes_in_order() {
+    // nodes are returned in order of their local IDs, even if some of them are stored sparsely
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    for local_id in &[3000, 1000, 2000, 2900, 3200] {
+        graph
+            .add_scope_node(NodeID::new_in_file(file, *local_id), false)
+            .unwrap();
+    }
+    let local_ids = graph
+        .nodes_for_file(file)
+        .map(|node| graph[node].id().local_id())
+        .collect::<Vec<_>>();
+    assert_eq!(local_ids, vec![1000, 2000, 2900, 3000, 3200]);
+}
+
+#[test]
+fn can_deduplicate_symbol_nodes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let other_file = graph.file("other.py");
+    let x = graph.symbol("x");
+    let y = graph.symbol("y");
+
+    let push_x = graph.get_or_create_push_symbol_node(file, x, true);
+    assert_eq!(push_x, graph.get_or_create_push_symbol_node(file, x, true));
+    assert_ne!(push_x, graph.get_or_create_push_symbol_node(file, x, false));
+    assert_ne!(push_x, graph.get_or_create_push_symbol_node(file, y, true));
+    assert_ne!(
+        push_x,
+        graph.get_or_create_push_symbol_node(other_file, x, true)
+    );
+
+    let pop_x = graph.get_or_create_pop_symbol_node(file, x, true);
+    assert_ne!(push_x, pop_x);
+    assert_eq!(pop_x, graph.get_or_create_pop_symbol_node(file, x, true));
+    assert!(graph[pop_x].is_definition());
+
+    // nodes that are explicitly added are not reused
+    let explicit = graph.reference(file, 100, x);
+    assert_ne!(
+        explicit,
+        graph.get_or_create_push_symbol_node(file, x, true)
+    );
+    assert_eq!(graph.nodes_for_file(file).count(), 5);
+}
+
+#[test]
+fn can_remove_file() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file1 = graph.file("test1.py");
+    let file2 = graph.file("test2.py");
+    let sym = graph.symbol("x");
+    let def = graph.definition(file1, 1, sym);
+    let scope = graph.internal_scope(file1, 2);
+    let r#ref = graph.reference(file2, 1, sym);
+    let other_scope = graph.internal_scope(file2, 2);
+    graph.edge(root, def);
+    graph.edge(scope, def);
+    graph.edge(r#ref, scope);
+    graph.edge(r#ref, root);
+    graph.edge(other_scope, root);
+    let key = graph.add_string("key");
+    let value = graph.add_string("value");
+    graph.node_debug_info_mut(def).add(key, value);
+    graph.edge_debug_info_mut(root, def).add(key, value);
+
+    graph.remove_file(file1);
+
+    assert_eq!(graph.nodes_for_file(file1).count(), 0);
+    assert_eq!(graph.get_file("test1.py"), Some(file1));
+    assert!(graph.node_for_id(graph[def].id()).is_none());
+    assert_eq!(
+        graph.iter_nodes().collect::<HashSet<_>>(),
+        hashset! { root, graph.jump_to_node(), r#ref, other_scope }
+    );
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 2);
+    assert_eq!(graph.outgoing_edges(root).count(), 0);
+    assert_eq!(
+        graph
+            .outgoing_edges(r#ref)
+            .map(|edge| edge.sink)
+            .collect::<HashSet<_>>(),
+        hashset! { root }
+    );
+    assert_eq!(graph.incoming_edge_degree(root), Degree::Multiple);
+    assert_eq!(graph.incoming_edge_degree(def), Degree::Zero);
+    assert!(graph.edge_debug_info(root, def).is_none());
+
+    // the file can be populated again
+    let new_def = graph.definition(file1, 1, sym);
+    graph.edge(root, new_def);
+    assert_ne!(new_def, def);
+    assert_eq!(graph.nodes_for_file(file1).count(), 1);
+    assert_eq!(graph.edge_count(), 3);
+}
+
+#[test]
+fn can_add_nodes_while_iterating_nodes() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let removed = graph.file("removed.py");
+    graph.internal_scope(file, 1);
+    graph.internal_scope(removed, 1);
+
+    let mut local_id = 100;
+    for node in graph.iter_nodes() {
+        if graph[node].file() == Some(file) {
+            graph.internal_scope(file, local_id);
+            local_id += 1;
+        }
+    }
+    assert_eq!(graph.nodes_for_file(file).count(), 2);
+
+    // nodes can also be added after a file has been removed
+    graph.remove_file(removed);
+    for node in graph.iter_nodes() {
+        if graph[node].file() == Some(file) {
+            graph.internal_scope(file, local_id);
+            local_id += 1;
+        }
+    }
+    assert_eq!(graph.nodes_for_file(file).count(), 4);
+    assert_eq!(graph.iter_nodes().count(), 2 + 4);
+}
+
+#[test]
+fn can_compact_graph() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let jump_to = graph.jump_to_node();
+    let file1 = graph.file("test1.py");
+    let file2 = graph.file("test2.py");
+    let sym = graph.symbol("x");
+    let def = graph.definition(file1, 1, sym);
+    let r#ref = graph.reference(file2, 1, sym);
+    let scope = graph.internal_scope(file2, 2);
+    graph.edge(root, def);
+    graph.edge(r#ref, scope);
+    graph.edge(scope, root);
+    let key = graph.add_string("key");
+    let value = graph.add_string("value");
+    graph.node_debug_info_mut(scope).add(key, value);
+    graph.edge_debug_info_mut(r#ref, scope).add(key, value);
+
+    graph.remove_file(file1);
+    let mapping = graph.compact();
+
+    assert_eq!(mapping.len(), 4);
+    assert_eq!(mapping[&root], root);
+    assert_eq!(mapping[&jump_to], jump_to);
+    assert!(!mapping.contains_key(&def));
+    let new_ref = mapping[&r#ref];
+    let new_scope = mapping[&scope];
+    assert_eq!(
+        graph.iter_nodes().collect::<Vec<_>>(),
+        vec![root, jump_to, new_ref, new_scope]
+    );
+    assert_eq!(graph.node_count(), 4);
+    assert_eq!(graph.edge_count(), 2);
+    assert_eq!(graph[new_ref].id(), NodeID::new_in_file(file2, 1));
+    assert_eq!(
+        graph.node_for_id(NodeID::new_in_file(file2, 2)),
+        Some(new_scope)
+    );
+    assert_eq!(
+        graph
+            .outgoing_edges(new_ref)
+            .map(|edge| edge.sink)
+            .collect::<Vec<_>>(),
+        vec![new_scope]
+    );
+    assert_eq!(graph.incoming_edge_degree(new_scope), Degree::One);
+    assert_eq!(graph.incoming_edge_degree(root), Degree::One);
+    assert!(graph.node_debug_info(new_scope).is_some());
+    assert!(graph.edge_debug_info(new_ref, new_scope).is_some());
+
+    // new nodes are allocated after the remaining ones
+    let new_def = graph.definition(file1, 1, sym);
+    assert_eq!(new_def.as_u32(), 5);
+}
+
+#[test]
+fn can_diff_graphs() {
+    let old: StackGraph = test_graphs::simple::new();
+    let mut new: StackGraph = test_graphs::simple::new();
+    assert!(old.diff(&new).is_empty());
+
+    let file = new.get_file("test.py").unwrap();
+    let root = new.root_node();
+    let sym = new.symbol("y");
+    let def = new.definition(file, 100, sym);
+    new.edge(root, def);
+    // source info is not part of the structure
+    new.source_info_mut(def).syntax_type = new.add_string("def").into();
+
+    let diff = old.diff(&new);
+    assert_eq!(diff.added_nodes.len(), 1);
+    assert_eq!(diff.added_nodes[0].id().local_id, 100);
+    assert!(diff.removed_nodes.is_empty());
+    assert_eq!(diff.added_edges.len(), 1);
+    assert_eq!(diff.added_edges[0].sink.local_id, 100);
+    assert!(diff.removed_edges.is_empty());
+
+    let reverse = new.diff(&old);
+    assert_eq!(reverse.removed_nodes, diff.added_nodes);
+    assert_eq!(reverse.removed_edges, diff.added_edges);
+    assert!(reverse.added_nodes.is_empty());
+    assert!(reverse.added_edges.is_empty());
+}
+
+#[test]
+fn can_iterate_outgoing_edges_by_precedence() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let source = graph.internal_scope(file, 0);
+    let sinks = (1..=4)
+        .map(|i| graph.internal_scope(file, i))
+        .collect::<Vec<_>>();
+    graph.add_edge(source, sinks[3], 0);
+    graph.add_edge(source, sinks[0], 1);
+    graph.add_edge(source, sinks[2], 2);
+    graph.add_edge(source, sinks[1], 0);
+
+    let edges = graph
+        .outgoing_edges_by_precedence(source)
+        .map(|edge| (edge.sink, edge.precedence))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        edges,
+        vec![(sinks[2], 2), (sinks[0], 1), (sinks[1], 0), (sinks[3], 0)]
+    );
+    assert_eq!(graph.outgoing_edges_by_precedence(sinks[0]).count(), 0);
+}
+
+#[test]
+fn can_iterate_nodes_by_kind() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let sym = graph.symbol("x");
+    let def = graph.definition(file, 1, sym);
+    let r#ref = graph.reference(file, 2, sym);
+    let internal = graph.internal_scope(file, 3);
+    let exported = graph.exported_scope(file, 4);
+    let push = graph.push_symbol(file, 5, sym);
+
+    assert_eq!(graph.iter_definitions().collect::<Vec<_>>(), vec![def]);
+    assert_eq!(graph.iter_references().collect::<Vec<_>>(), vec![r#ref]);
+    assert_eq!(
+        graph.iter_scopes().collect::<Vec<_>>(),
+        vec![internal, exported]
+    );
+    assert_eq!(
+        graph
+            .iter_nodes_matching(|node| node.symbol() == Some(sym))
+            .collect::<Vec<_>>(),
+        vec![def, r#ref, push]
+    );
+}
+
+#[test]
+fn can_add_strings_and_debug_info_in_batches() {
+    let mut graph = StackGraph::new();
+    let existing = graph.add_string("b");
+    let strings = graph.add_strings(["a", "b", "c", "a"]);
+    assert_eq!(strings.len(), 4);
+    assert_eq!(strings[1], existing);
+    assert_eq!(strings[0], strings[3]);
+    assert_eq!(
+        strings.iter().map(|s| &graph[*s]).collect::<Vec<_>>(),
+        vec!["a", "b", "c", "a"]
+    );
+
+    let file = graph.file("test.py");
+    let node = graph.internal_scope(file, 0);
+    let info = graph.node_debug_info_mut(node);
+    info.add(strings[0], strings[1]);
+    info.add_many([(strings[1], strings[2]), (strings[2], strings[0])]);
+    assert_eq!(
+        graph
+            .node_debug_info(node)
+            .unwrap()
+            .iter()
+            .map(|entry| (&graph[entry.key], &graph[entry.value]))
+            .collect::<Vec<_>>(),
+        vec![("a", "b"), ("b", "c"), ("c", "a")]
+    );
+}
+
+#[test]
+fn can_validate_graph() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    assert_eq!(graph.validate(), Ok(()));
+
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let other_file = graph.file("other.py");
+    let sym = graph.symbol("x");
+    let internal = graph.internal_scope(file, 1);
+    let scope_id = graph[internal].id();
+    let push = graph
+        .add_push_scoped_symbol_node(NodeID::new_in_file(file, 2), sym, scope_id, true)
+        .unwrap();
+    let mut other_graph = StackGraph::new();
+    other_graph.add_symbol("a");
+    let unknown_symbol = other_graph.add_symbol("b");
+    let pop = graph
+        .add_pop_symbol_node(NodeID::new_in_file(file, 3), unknown_symbol, true)
+        .unwrap();
+    let removed = graph.internal_scope(other_file, 1);
+    graph.remove_file(other_file);
+    graph.edge(root, removed);
+
+    assert_eq!(
+        graph.validate(),
+        Err(vec![
+            ValidationError::DanglingEdge {
+                source: root,
+                sink: removed,
+            },
+            ValidationError::InvalidAttachedScope {
+                node: push,
+                scope: scope_id,
+            },
+            ValidationError::UnknownSymbol {
+                node: pop,
+                symbol: unknown_symbol,
+            },
+        ])
+    );
+}
+
+#[test]
+fn can_set_edge_precedence() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let source = graph.internal_scope(file, 0);
+    let sink = graph.internal_scope(file, 1);
+    let other = graph.internal_scope(file, 2);
+    graph.add_edge(source, sink, 0);
+
+    assert!(graph.set_edge_precedence(source, sink, 5));
+    assert_eq!(
+        graph
+            .outgoing_edges(source)
+            .map(|edge| edge.precedence)
+            .collect::<Vec<_>>(),
+        vec![5]
+    );
+    assert!(!graph.set_edge_precedence(source, other, 5));
+    assert!(!graph.set_edge_precedence(sink, source, 5));
+    assert_eq!(graph.edge_count(), 1);
+}
+
+#[test]
+fn can_write_dot() {
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    let file = graph.file("test.py");
+    let other_file = graph.file("other.py");
+    let sym = graph.symbol("\"quoted\"");
+    let def = graph.definition(file, 1, sym);
+    let r#ref = graph.reference(other_file, 1, sym);
+    graph.edge(root, def);
+    graph.add_edge(r#ref, root, 1);
+
+    let mut dot = Vec::new();
+    graph
+        .to_dot(Some(file), &mut dot)
+        .expect("Cannot write DOT");
+    let expected = r#"digraph stack_graph {
+  node [shape=box];
+  n3 [label="[test.py(1) definition \"quoted\"]"];
+  n1 [label="[root]", style=dashed];
+  n1 -> n3 [label="0"];
+}
+"#;
+    assert_eq!(expected, String::from_utf8(dot).unwrap());
+
+    let mut dot = Vec::new();
+    graph.to_dot(None, &mut dot).expect("Cannot write DOT");
+    let expected = r#"digraph stack_graph {
+  node [shape=box];
+  n1 [label="[root]"];
+  n2 [label="[jump to scope]"];
+  n3 [label="[test.py(1) definition \"quoted\"]"];
+  n4 [label="[other.py(1) reference \"quoted\"]"];
+  n1 -> n3 [label="0"];
+  n4 -> n1 [label="1"];
+}
+"#;
+    assert_eq!(expected, String::from_utf8(dot).unwrap());
+}
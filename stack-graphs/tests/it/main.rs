@@ -28,4 +28,8 @@ mod stats;
 mod stitching;
 #[cfg(feature = "storage")]
 mod storage;
+#[cfg(feature = "testing")]
+mod testing;
 mod util;
+#[cfg(feature = "visualization")]
+mod visualization;
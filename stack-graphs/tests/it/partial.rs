@@ -9,6 +9,7 @@ use stack_graphs::arena::Handle;
 use stack_graphs::graph::Node;
 use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::ConcatenationError;
 use stack_graphs::partial::PartialPath;
 use stack_graphs::partial::PartialPathEdgeList;
 use stack_graphs::partial::PartialPaths;
@@ -19,7 +20,12 @@ use stack_graphs::partial::ScopeStackVariable;
 use stack_graphs::partial::SymbolStackVariable;
 use stack_graphs::paths::PathResolutionError;
 use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::NoCancellation;
 
+use crate::test_graphs;
 use crate::util::*;
 
 #[test]
@@ -964,3 +970,243 @@ fn can_resolve_to_node() -> Result<(), PathResolutionError> {
 
     Ok(())
 }
+
+#[test]
+fn can_display_partial_path_in_detail() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").expect("");
+    let mut partials = PartialPaths::new();
+
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", true);
+    let bar_def = create_pop_symbol_node(&mut graph, file, "bar", true);
+    let path = create_partial_path_and_edges(
+        &mut graph,
+        &mut partials,
+        &[foo_ref, StackGraph::root_node()],
+    )
+    .expect("");
+    let expected = "[test(0) reference foo] -> [root]
+  symbol stack precondition:  <%1>
+  symbol stack postcondition: <foo,%1>
+  scope stack precondition:   ($1)
+  scope stack postcondition:  ($1)
+  edges:
+    [test(0) reference foo] -> [root]";
+    assert_eq!(
+        expected,
+        path.display_detailed(&graph, &mut partials).to_string()
+    );
+
+    let path = PartialPath::from_node(&graph, &mut partials, bar_def);
+    let expected = "[test(1) definition bar] -> [test(1) definition bar]
+  symbol stack precondition:  <bar,%1>
+  symbol stack postcondition: <%1>
+  scope stack precondition:   ($1)
+  scope stack postcondition:  ($1)
+  edges: none";
+    assert_eq!(
+        expected,
+        path.display_detailed(&graph, &mut partials).to_string()
+    );
+}
+
+#[test]
+fn can_check_whether_partial_paths_can_be_concatenated() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let s = create_scope_node(&mut graph, file, false);
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", true);
+    let foo_def = create_pop_symbol_node(&mut graph, file, "foo", true);
+    let bar_def = create_pop_symbol_node(&mut graph, file, "bar", true);
+
+    let mut partials = PartialPaths::new();
+    let to_scope = create_partial_path_and_edges(&mut graph, &mut partials, &[foo_ref, s]).unwrap();
+    let mut to_foo =
+        create_partial_path_and_edges(&mut graph, &mut partials, &[s, foo_def]).unwrap();
+    to_foo.ensure_no_overlapping_variables(&mut partials, &to_scope);
+    let mut to_bar =
+        create_partial_path_and_edges(&mut graph, &mut partials, &[s, bar_def]).unwrap();
+    to_bar.ensure_no_overlapping_variables(&mut partials, &to_scope);
+
+    assert!(to_scope
+        .can_concatenate(&to_foo, &graph, &mut partials)
+        .is_ok());
+    assert!(matches!(
+        to_scope.can_concatenate(&to_bar, &graph, &mut partials),
+        Err(ConcatenationError::IncompatibleSymbolStacks(_))
+    ));
+    assert!(matches!(
+        to_foo.can_concatenate(&to_scope, &graph, &mut partials),
+        Err(ConcatenationError::IncorrectJoinNode {
+            lhs_end_node,
+            rhs_start_node,
+        }) if lhs_end_node == foo_def && rhs_start_node == foo_ref
+    ));
+
+    let mut path = to_scope.clone();
+    path.concatenate(&graph, &mut partials, &to_foo).unwrap();
+}
+
+#[test]
+fn can_concatenate_partial_paths_with_fuel() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let s = create_scope_node(&mut graph, file, false);
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", true);
+    let foo_def = create_pop_symbol_node(&mut graph, file, "foo", true);
+
+    let mut partials = PartialPaths::new();
+    let to_scope = create_partial_path_and_edges(&mut graph, &mut partials, &[foo_ref, s]).unwrap();
+    let mut to_foo =
+        create_partial_path_and_edges(&mut graph, &mut partials, &[s, foo_def]).unwrap();
+    to_foo.ensure_no_overlapping_variables(&mut partials, &to_scope);
+
+    let mut expected = to_scope.clone();
+    expected
+        .concatenate(&graph, &mut partials, &to_foo)
+        .unwrap();
+
+    // find out how much fuel the concatenation needs
+    let mut path = to_scope.clone();
+    let mut fuel = u64::MAX;
+    path.concatenate_with_fuel(&graph, &mut partials, &to_foo, &mut fuel)
+        .unwrap();
+    assert!(path.equals(&mut partials, &expected));
+    let needed = u64::MAX - fuel;
+    assert!(needed > 0);
+
+    let mut path = to_scope.clone();
+    let mut fuel = needed;
+    path.concatenate_with_fuel(&graph, &mut partials, &to_foo, &mut fuel)
+        .unwrap();
+    assert!(path.equals(&mut partials, &expected));
+    assert_eq!(fuel, 0);
+
+    for limit in 0..needed {
+        let mut path = to_scope.clone();
+        let mut fuel = limit;
+        assert!(matches!(
+            path.concatenate_with_fuel(&graph, &mut partials, &to_foo, &mut fuel),
+            Err(PathResolutionError::OutOfFuel)
+        ));
+        assert!(path.equals(&mut partials, &to_scope));
+    }
+}
+
+#[test]
+fn can_simplify_partial_paths() {
+    let mut graph = StackGraph::new();
+    let file = graph.add_file("test").unwrap();
+    let s0 = create_scope_node(&mut graph, file, false);
+    let s1 = create_scope_node(&mut graph, file, false);
+    let foo_ref = create_push_symbol_node(&mut graph, file, "foo", true);
+    let foo_def = create_pop_symbol_node(&mut graph, file, "foo", true);
+    let bar_push = create_push_symbol_node(&mut graph, file, "bar", false);
+    let bar_pop = create_pop_symbol_node(&mut graph, file, "bar", false);
+    let baz_push = create_push_symbol_node(&mut graph, file, "baz", false);
+    let baz_pop = create_pop_symbol_node(&mut graph, file, "baz", false);
+
+    fn check(graph: &mut StackGraph, nodes: NicePartialPath, expected_edges: usize) {
+        let mut partials = PartialPaths::new();
+        let path = create_partial_path_and_edges(graph, &mut partials, nodes).unwrap();
+        let mut simplified = path.clone();
+        simplified.simplify(graph, &mut partials);
+        assert_eq!(simplified.edges.len(), expected_edges);
+        let expected = path.display(graph, &mut partials).to_string();
+        let actual = simplified.display(graph, &mut partials).to_string();
+        assert_eq!(expected, actual);
+    }
+
+    check(
+        &mut graph,
+        &[
+            foo_ref, s0, baz_push, bar_push, bar_pop, baz_pop, s1, foo_def,
+        ],
+        3,
+    );
+    check(&mut graph, &[s0, bar_push, bar_pop, s1], 1);
+    // a pop followed by a push changes the precondition, so both are kept
+    check(&mut graph, &[s0, bar_pop, bar_push, s1], 3);
+    // the start and end nodes are kept
+    check(&mut graph, &[bar_push, bar_pop, s1], 2);
+    check(&mut graph, &[s0, bar_push, bar_pop], 2);
+}
+
+#[test]
+fn simplified_partial_paths_resolve_the_same() {
+    fn resolve(graph: &StackGraph, simplify: bool) -> Vec<String> {
+        let mut partials = PartialPaths::new();
+        let mut db = Database::new();
+        for file in graph.iter_files() {
+            ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+                graph,
+                &mut partials,
+                file,
+                StitcherConfig::default(),
+                &NoCancellation,
+                |graph, partials, path| {
+                    let mut path = path.clone();
+                    if simplify {
+                        path.simplify(graph, partials);
+                    }
+                    db.add_partial_path(graph, partials, path);
+                },
+            )
+            .unwrap();
+        }
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut DatabaseCandidates::new(graph, &mut partials, &mut db),
+            graph.iter_references(),
+            StitcherConfig::default(),
+            &NoCancellation,
+            |_, _, path| paths.push(path.clone()),
+        )
+        .unwrap();
+        let mut results = Vec::new();
+        for path in &paths {
+            let shadowed = paths.iter().any(|other| other.shadows(&mut partials, path));
+            results.push(format!(
+                "{}{}",
+                path.display(graph, &mut partials),
+                if shadowed { " (shadowed)" } else { "" }
+            ));
+        }
+        results.sort();
+        results
+    }
+
+    // A graph where a push/pop pair sits in front of edges with different precedences, so
+    // simplifying the pair away would lose the shadowing between the two definitions.
+    fn with_precedence() -> StackGraph {
+        let mut graph = StackGraph::new();
+        let file = graph.add_file("test").unwrap();
+        let x_ref = create_push_symbol_node(&mut graph, file, "x", true);
+        let y_push = create_push_symbol_node(&mut graph, file, "y", false);
+        let y_pop = create_pop_symbol_node(&mut graph, file, "y", false);
+        let s1 = create_scope_node(&mut graph, file, false);
+        let s2 = create_scope_node(&mut graph, file, false);
+        let x_def1 = create_pop_symbol_node(&mut graph, file, "x", true);
+        let x_def2 = create_pop_symbol_node(&mut graph, file, "x", true);
+        graph.add_edge(x_ref, y_push, 0);
+        graph.add_edge(y_push, y_pop, 0);
+        graph.add_edge(y_pop, s1, 1);
+        graph.add_edge(y_pop, s2, 0);
+        graph.add_edge(s1, x_def1, 0);
+        graph.add_edge(s2, x_def2, 0);
+        graph
+    }
+
+    for graph in &[
+        with_precedence(),
+        test_graphs::class_field_through_function_parameter::new(),
+        test_graphs::cyclic_imports_python::new(),
+        test_graphs::cyclic_imports_rust::new(),
+        test_graphs::sequenced_import_star::new(),
+        test_graphs::simple::new(),
+    ] {
+        let expected = resolve(graph, false);
+        assert!(!expected.is_empty());
+        assert_eq!(expected, resolve(graph, true));
+    }
+}
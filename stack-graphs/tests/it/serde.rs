@@ -5,6 +5,8 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::time::Duration;
+
 use assert_json_diff::assert_json_eq;
 use serde_json;
 use serde_json::json;
@@ -14,6 +16,7 @@ use stack_graphs::partial::PartialPaths;
 use stack_graphs::serde;
 use stack_graphs::stitching::{Database, ForwardPartialPathStitcher, StitcherConfig};
 use stack_graphs::NoCancellation;
+use stack_graphs::TimeoutCancellation;
 
 use crate::test_graphs;
 
@@ -29,6 +32,7 @@ fn serde_json_stack_graph() {
                     local_id: 1,
                     file: None,
                 },
+                singleton: true,
                 source_info: Some(serde::SourceInfo {
                     span: lsp_positions::Span {
                         start: lsp_positions::Position {
@@ -52,6 +56,7 @@ fn serde_json_stack_graph() {
                             trimmed_line: 0..0,
                         },
                     },
+                    definiens_span: None,
                     syntax_type: None,
                 }),
                 debug_info: Some(serde::DebugInfo { data: vec![] }),
@@ -68,9 +73,12 @@ fn serde_json_stack_graph() {
                     local_id: 0,
                 },
                 precedence: 0,
+                cost: 0,
                 debug_info: Some(serde::DebugInfo { data: vec![] }),
             }],
         },
+        file_debug_info: vec![],
+        file_languages: vec![],
     };
 
     // formatted using: json_pp -json_opt utf8,canonical,pretty,indent_length=4
@@ -457,6 +465,7 @@ fn can_serialize_graph() {
                     "id" : {
                         "local_id" : 1
                     },
+                    "singleton" : true,
                     "source_info" : {
                         "span" : {
                             "end" : {
@@ -500,6 +509,7 @@ fn can_serialize_graph() {
                     "id" : {
                         "local_id" : 2
                     },
+                    "singleton" : true,
                     "source_info" : {
                         "span" : {
                             "end" : {
@@ -1276,3 +1286,748 @@ fn can_serialize_partial_paths() {
     );
     assert_json_eq!(expected, actual);
 }
+
+#[test]
+fn can_read_large_graph_from_reader() {
+    use crate::test_graphs::CreateStackGraph;
+
+    let mut graph = StackGraph::new();
+    let root = graph.root_node();
+    for f in 0..100 {
+        let file = graph.file(&format!("file{}.py", f));
+        let scope = graph.exported_scope(file, 0);
+        graph.edge(root, scope);
+        for i in 1..100 {
+            let symbol = graph.symbol(&format!("sym{}", i % 17));
+            let def = graph.definition(file, 2 * i, symbol);
+            let r#ref = graph.reference(file, 2 * i + 1, symbol);
+            graph.edge(scope, def);
+            graph.edge(r#ref, scope);
+            let key = graph.add_string("name");
+            let value = graph.add_string(&format!("x{}", i));
+            graph.node_debug_info_mut(def).add(key, value);
+        }
+    }
+    let expected = graph.to_serializable();
+    let json = serde_json::to_vec(&expected).expect("Cannot serialize graph");
+
+    let read = StackGraph::read_from_reader(json.as_slice()).expect("Cannot read graph");
+    assert_eq!(expected, read.to_serializable());
+
+    let mut loaded = StackGraph::new();
+    serde_json::from_slice::<serde::StackGraph>(&json)
+        .expect("Cannot deserialize graph")
+        .load_into(&mut loaded)
+        .expect("Cannot load graph");
+    assert_eq!(loaded.to_serializable(), read.to_serializable());
+}
+
+#[test]
+fn can_read_graph_with_reordered_fields_from_reader() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let expected = graph.to_serializable();
+    // serializing through a value sorts the keys, so edges and nodes come before files
+    let value = serde_json::to_value(&expected).expect("Cannot serialize graph");
+    let json = serde_json::to_vec(&value).expect("Cannot serialize graph");
+    let read = StackGraph::read_from_reader(json.as_slice()).expect("Cannot read graph");
+    assert_eq!(expected, read.to_serializable());
+}
+
+#[test]
+fn can_extend_graph_from_json() {
+    use crate::test_graphs::CreateStackGraph;
+
+    let to_json = |graph: &StackGraph| serde_json::to_vec(&graph.to_serializable()).unwrap();
+    let file_nodes = |graph: &StackGraph, name: &str| {
+        let file = graph.get_file(name).unwrap();
+        graph
+            .nodes_for_file(file)
+            .map(|n| graph[n].display(graph).to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let other = test_graphs::class_field_through_function_parameter::new();
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let test_py_nodes = file_nodes(&graph, "test.py");
+    graph
+        .extend_from_json(to_json(&other).as_slice())
+        .expect("Cannot extend graph");
+    assert_eq!(graph.iter_files().count(), 1 + other.iter_files().count());
+    assert_eq!(file_nodes(&graph, "test.py"), test_py_nodes);
+    for file in other.iter_files() {
+        let name = other[file].name();
+        assert_eq!(file_nodes(&graph, name), file_nodes(&other, name));
+    }
+    assert_eq!(
+        graph.edge_count(),
+        test_graphs::simple::new().edge_count() + other.edge_count()
+    );
+
+    // files that are already present are replaced
+    let mut replacement = StackGraph::new();
+    let file = replacement.file("test.py");
+    let root = replacement.root_node();
+    let scope = replacement.exported_scope(file, 0);
+    replacement.edge(root, scope);
+    let edge_count = graph.edge_count();
+    graph
+        .extend_from_json(to_json(&replacement).as_slice())
+        .expect("Cannot extend graph");
+    assert_eq!(graph.iter_files().count(), 1 + other.iter_files().count());
+    assert_eq!(
+        file_nodes(&graph, "test.py"),
+        file_nodes(&replacement, "test.py")
+    );
+    assert_eq!(
+        graph.edge_count(),
+        edge_count - test_graphs::simple::new().edge_count() + 1
+    );
+}
+
+#[test]
+fn cannot_read_graph_with_missing_fields_from_reader() {
+    let json = br#"{ "files": [], "nodes": [] }"#;
+    assert!(StackGraph::read_from_reader(&json[..]).is_err());
+}
+
+#[test]
+fn cannot_load_graph_with_invalid_singleton_nodes() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let value = serde_json::to_value(graph.to_serializable()).expect("Cannot serialize graph");
+    let nodes = value["nodes"].as_array().unwrap();
+    let root = nodes.iter().find(|n| n["type"] == "root").unwrap();
+    assert_eq!(root["singleton"], true);
+
+    let with_nodes = |nodes: Vec<serde_json::Value>| {
+        let mut value = value.clone();
+        value["nodes"] = serde_json::Value::Array(nodes);
+        value
+    };
+    let load = |value: &serde_json::Value| {
+        let mut loaded = StackGraph::new();
+        serde_json::from_value::<serde::StackGraph>(value.clone())
+            .expect("Cannot deserialize graph")
+            .load_into(&mut loaded)
+    };
+
+    let without_jump_to = with_nodes(
+        nodes
+            .iter()
+            .filter(|n| n["type"] != "jump_to_scope")
+            .cloned()
+            .collect(),
+    );
+    assert_eq!(
+        load(&without_jump_to),
+        Err(serde::Error::InvalidSingletonCount("jump-to", 0))
+    );
+    assert!(StackGraph::read_from_reader(without_jump_to.to_string().as_bytes()).is_err());
+
+    let mut duplicate_root = nodes.clone();
+    duplicate_root.push(root.clone());
+    let duplicate_root = with_nodes(duplicate_root);
+    assert_eq!(
+        load(&duplicate_root),
+        Err(serde::Error::InvalidSingletonCount("root", 2))
+    );
+    assert!(StackGraph::read_from_reader(duplicate_root.to_string().as_bytes()).is_err());
+
+    let mut misplaced_root = nodes.clone();
+    for node in misplaced_root.iter_mut().filter(|n| n["type"] == "root") {
+        node["id"]["local_id"] = 7.into();
+    }
+    let misplaced_root = with_nodes(misplaced_root);
+    assert!(matches!(
+        load(&misplaced_root),
+        Err(serde::Error::InvalidSingletonID("root", _))
+    ));
+    assert!(StackGraph::read_from_reader(misplaced_root.to_string().as_bytes()).is_err());
+}
+
+#[test]
+fn loaded_graph_iterates_nodes_in_serialized_order() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let json = serde_json::to_string(&graph.to_serializable()).expect("Cannot serialize graph");
+
+    let node_ids = |graph: &StackGraph| {
+        graph
+            .iter_nodes()
+            .map(|n| serde::NodeID::from_node(graph, n))
+            .collect::<Vec<_>>()
+    };
+    let expected = node_ids(&graph);
+    for _ in 0..2 {
+        let mut loaded = StackGraph::new();
+        serde_json::from_str::<serde::StackGraph>(&json)
+            .expect("Cannot deserialize graph")
+            .load_into(&mut loaded)
+            .expect("Cannot load graph");
+        assert_eq!(expected, node_ids(&loaded));
+        let handles = loaded.iter_nodes().collect::<Vec<_>>();
+        assert!(handles.windows(2).all(|w| w[0] < w[1]));
+    }
+}
+
+#[test]
+fn can_load_partial_paths_from_json() {
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("Expect path finding to work");
+    }
+    let expected = db.to_serializable(&graph, &mut partials);
+    let json = serde_json::to_vec(&expected).expect("Cannot serialize paths");
+
+    let mut loaded = Database::new();
+    loaded
+        .load_from_json(json.as_slice(), &mut graph, &mut partials)
+        .expect("Cannot load paths");
+    assert_eq!(expected, loaded.to_serializable(&graph, &mut partials));
+}
+
+#[test]
+fn can_write_all_partial_paths_as_json() {
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("Expect path finding to work");
+    }
+    let expected = db.to_serializable(&graph, &mut partials);
+
+    let mut json = Vec::new();
+    partials
+        .write_all_json(&graph, &mut json)
+        .expect("Cannot write paths");
+    let actual: serde_json::Value = serde_json::from_slice(&json).expect("Cannot parse paths");
+    let paths = actual.as_array().expect("Expected an array of paths");
+    assert!(!paths.is_empty());
+    assert!(paths.iter().all(|path| path.get("start_node").is_some()
+        && path.get("end_node").is_some()
+        && path.get("symbol_stack_precondition").is_some()
+        && path.get("scope_stack_postcondition").is_some()));
+
+    let mut loaded = Database::new();
+    loaded
+        .load_from_json(json.as_slice(), &mut graph, &mut partials)
+        .expect("Cannot load paths");
+    assert_eq!(expected, loaded.to_serializable(&graph, &mut partials));
+}
+
+#[test]
+fn can_cancel_writing_all_partial_paths_as_json() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let cancelled = TimeoutCancellation::new(Duration::ZERO).with_granularity(1);
+    let mut json = Vec::new();
+    let result = partials.write_all_json_with_cancellation(&graph, &mut json, &cancelled);
+    assert!(matches!(result, Err(serde::WriteError::Cancelled(_))));
+    assert!(json.is_empty());
+}
+
+#[test]
+fn cannot_load_partial_paths_with_unknown_symbols_from_json() {
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let path = |symbol: &str| {
+        json!({
+            "start_node": { "local_id": 1 },
+            "end_node": { "local_id": 1 },
+            "symbol_stack_precondition": { "symbols": [ { "symbol": symbol } ] },
+            "symbol_stack_postcondition": { "symbols": [] },
+            "scope_stack_precondition": { "scopes": [] },
+            "scope_stack_postcondition": { "scopes": [] },
+            "edges": [],
+        })
+    };
+    let json = serde_json::to_vec(&json!([path("x"), path("unknown")])).unwrap();
+
+    let mut db = Database::new();
+    let result = db.load_from_json(json.as_slice(), &mut graph, &mut partials);
+    assert!(matches!(
+        result,
+        Err(serde::DatabaseLoadError::InvalidPath {
+            index: 1,
+            error: serde::Error::SymbolNotFound(ref symbol),
+        }) if symbol == "unknown"
+    ));
+    assert_eq!(0, db.iter_partial_paths().count());
+    assert!(graph.get_symbol("unknown").is_none());
+}
+
+#[test]
+fn can_serialize_edge_costs() {
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let edge = graph.all_edges().next().expect("Missing edge");
+    assert!(graph.set_edge_cost(edge.source, edge.sink, 7));
+
+    let serialized = graph.to_serializable();
+    let json = serde_json::to_value(&serialized).unwrap();
+    let costs = json["edges"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|edge| edge.get("cost"))
+        .collect::<Vec<_>>();
+    assert_eq!(costs, vec![&json!(7)]);
+
+    let mut read = StackGraph::new();
+    serde_json::from_value::<serde::StackGraph>(json)
+        .unwrap()
+        .load_into(&mut read)
+        .expect("Cannot load graph");
+    assert_eq!(serialized, read.to_serializable());
+    assert_eq!(read.all_edges().map(|edge| edge.cost).sum::<u32>(), 7);
+}
+
+#[test]
+fn can_round_trip_graph_with_partial_paths() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("Expect path finding to work");
+    }
+    let mut json = Vec::new();
+    graph
+        .write_with_partials(&mut partials, &db, &mut json)
+        .expect("Cannot write graph with partial paths");
+
+    let (loaded_graph, mut loaded_partials, loaded_db) =
+        StackGraph::read_with_partials(json.as_slice()).expect("Cannot read graph");
+    assert_eq!(graph.to_serializable(), loaded_graph.to_serializable());
+    assert_eq!(
+        db.to_serializable(&graph, &mut partials),
+        loaded_db.to_serializable(&loaded_graph, &mut loaded_partials)
+    );
+}
+
+#[test]
+fn cannot_read_graph_with_partial_paths_to_missing_nodes() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut json = serde_json::to_value(&serde::StackGraphWithPartials {
+        graph: graph.to_serializable(),
+        database: Database::new().to_serializable(&graph, &mut PartialPaths::new()),
+    })
+    .unwrap();
+    json["database"] = json!([{
+        "start_node": { "file": "test.py", "local_id": 999 },
+        "end_node": { "local_id": 1 },
+        "symbol_stack_precondition": { "symbols": [] },
+        "symbol_stack_postcondition": { "symbols": [] },
+        "scope_stack_precondition": { "scopes": [] },
+        "scope_stack_postcondition": { "scopes": [] },
+        "edges": [],
+    }]);
+    let json = serde_json::to_vec(&json).unwrap();
+
+    let result = StackGraph::read_with_partials(json.as_slice());
+    assert!(matches!(
+        result,
+        Err(serde::DatabaseLoadError::InvalidPath {
+            index: 0,
+            error: serde::Error::NodeNotFound(_),
+        })
+    ));
+}
+
+#[test]
+fn can_read_only_source_info() {
+    let span = |start: usize, end: usize| {
+        let position = |line| lsp_positions::Position {
+            line,
+            ..Default::default()
+        };
+        lsp_positions::Span {
+            start: position(start),
+            end: position(end),
+        }
+    };
+    let mut graph = StackGraph::new();
+    let other = graph.get_or_create_file("other.py");
+    let file = graph.get_or_create_file("test.py");
+    let x = graph.add_symbol("x");
+    let def = graph
+        .add_pop_symbol_node(graph::NodeID::new_in_file(file, 1), x, true)
+        .unwrap();
+    let reference = graph
+        .add_push_symbol_node(graph::NodeID::new_in_file(other, 1), x, true)
+        .unwrap();
+    graph.source_info_mut(def).span = span(1, 2);
+    graph.source_info_mut(def).definiens_span = span(1, 5);
+    graph.source_info_mut(reference).span = span(7, 8);
+    let scope = graph
+        .add_scope_node(graph::NodeID::new_in_file(file, 2), false)
+        .unwrap();
+    let json = serde_json::to_vec(&graph.to_serializable()).expect("Cannot serialize graph");
+
+    let source_info = StackGraph::read_source_info_only(json.as_slice()).expect("Cannot read");
+    let def_info = &source_info[&graph.global_id(def)];
+    assert_eq!(def_info.span, span(1, 2));
+    assert_eq!(def_info.definiens_span, Some(span(1, 5)));
+    let reference_info = &source_info[&graph.global_id(reference)];
+    assert_eq!(reference_info.span, span(7, 8));
+    assert_eq!(reference_info.definiens_span, None);
+    assert!(!source_info.contains_key(&graph.global_id(scope)));
+
+    let loaded = StackGraph::read_from_reader(json.as_slice()).expect("Cannot read graph");
+    let loaded_def = loaded.node_for_global_id(graph.global_id(def)).unwrap();
+    assert_eq!(
+        loaded.source_info(loaded_def).unwrap().definiens_span,
+        span(1, 5)
+    );
+}
+
+#[test]
+fn global_ids_are_stable_across_serialization() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let json = serde_json::to_vec(&graph.to_serializable()).expect("Cannot serialize graph");
+    let loaded = StackGraph::read_from_reader(json.as_slice()).expect("Cannot read graph");
+
+    for node in graph.iter_nodes() {
+        let global_id = graph.global_id(node);
+        assert_eq!(Some(node), graph.node_for_global_id(global_id));
+        let loaded_node = loaded
+            .node_for_global_id(global_id)
+            .expect("Cannot find node in loaded graph");
+        assert_eq!(
+            serde::NodeID::from_node(&graph, node),
+            serde::NodeID::from_node(&loaded, loaded_node)
+        );
+    }
+    assert_eq!(None, graph.node_for_global_id(3));
+    assert_eq!(None, graph.node_for_global_id(u64::MAX));
+}
+
+#[test]
+fn can_write_graph_as_jsonl() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut jsonl = Vec::new();
+    graph.write_jsonl(&mut jsonl).expect("Cannot write graph");
+    let records = String::from_utf8(jsonl)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("Invalid record"))
+        .collect::<Vec<_>>();
+
+    let serialized = graph.to_serializable();
+    assert_eq!(
+        records.len(),
+        serialized.nodes.data.len() + serialized.edges.data.len()
+    );
+    let (nodes, edges) = records.split_at(serialized.nodes.data.len());
+    for (record, (handle, node)) in nodes
+        .iter()
+        .zip(graph.iter_nodes().zip(&serialized.nodes.data))
+    {
+        assert_eq!(record["type"], "node");
+        assert_eq!(record["id"], graph.global_id(handle));
+        assert_eq!(record["node"], serde_json::to_value(node).unwrap());
+    }
+    for (record, edge) in edges.iter().zip(&serialized.edges.data) {
+        assert_eq!(record["type"], "edge");
+        let node_id = |id: &serde_json::Value| {
+            let handle = graph
+                .node_for_global_id(id.as_u64().unwrap())
+                .expect("Unknown node");
+            serde::NodeID::from_node(&graph, handle)
+        };
+        assert_eq!(node_id(&record["source"]), edge.source);
+        assert_eq!(node_id(&record["sink"]), edge.sink);
+        assert_eq!(record["precedence"], edge.precedence);
+    }
+}
+
+#[test]
+fn can_write_subset_of_files() {
+    use crate::test_graphs::CreateStackGraph;
+
+    let mut graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let main_file = graph.get_file("main.py").expect("Missing file");
+    let a_file = graph.get_file("a.py").expect("Missing file");
+    let main_node = graph
+        .nodes_for_file(main_file)
+        .next()
+        .expect("Missing node");
+    let a_node = graph.nodes_for_file(a_file).next().expect("Missing node");
+    graph.edge(main_node, a_node);
+
+    let write_partial = |file| {
+        let mut json = Vec::new();
+        graph
+            .write_partial(&[file], &mut json)
+            .expect("Cannot write graph");
+        serde_json::from_slice::<serde::StackGraph>(&json).expect("Cannot read graph")
+    };
+
+    let main = write_partial(main_file);
+    assert_eq!(main.files.data, vec!["main.py".to_string()]);
+    assert!(main
+        .nodes
+        .data
+        .iter()
+        .all(|node| matches!(node.id().file.as_deref(), Some("main.py") | None)));
+    assert!(main.edges.data.iter().all(|edge| {
+        edge.source.file.as_deref() == Some("main.py")
+            || (edge.source.file.is_none() && edge.sink.file.as_deref() == Some("main.py"))
+    }));
+    assert!(main
+        .edges
+        .data
+        .iter()
+        .any(|edge| edge.sink.file.as_deref() == Some("a.py")));
+
+    // the files can be reloaded independently, as long as the target of the edge that crosses
+    // from main.py into a.py is loaded first
+    let mut reloaded = StackGraph::new();
+    for file in graph.iter_files().filter(|file| *file != main_file) {
+        write_partial(file)
+            .load_into(&mut reloaded)
+            .expect("Cannot load graph");
+    }
+    main.load_into(&mut reloaded).expect("Cannot load graph");
+    assert!(graph.diff(&reloaded).is_empty());
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn can_serialize_file_debug_info() {
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").unwrap();
+    graph.set_file_debug_info(file, "language", "python");
+    graph.set_file_debug_info(file, "source_hash", "0000");
+    graph.set_file_debug_info(file, "source_hash", "abcd");
+    let file_debug_info = |graph: &StackGraph| {
+        let file = graph.get_file("test.py").unwrap();
+        graph
+            .file_debug_info(file)
+            .unwrap()
+            .iter()
+            .map(|entry| (graph[entry.key].to_owned(), graph[entry.value].to_owned()))
+            .collect::<Vec<_>>()
+    };
+    let expected = vec![
+        ("language".to_owned(), "python".to_owned()),
+        ("source_hash".to_owned(), "abcd".to_owned()),
+    ];
+    assert_eq!(file_debug_info(&graph), expected);
+
+    let json = serde_json::to_string(&graph.to_serializable()).unwrap();
+    let mut loaded = StackGraph::new();
+    serde_json::from_str::<serde::StackGraph>(&json)
+        .expect("Cannot deserialize graph")
+        .load_into(&mut loaded)
+        .expect("Cannot load graph");
+    assert_eq!(file_debug_info(&loaded), expected);
+
+    let streamed = StackGraph::read_from_reader(json.as_bytes()).expect("Cannot read graph");
+    assert_eq!(file_debug_info(&streamed), expected);
+
+    // the debug info may appear before the files it refers to
+    let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    let value = value.as_object_mut().unwrap();
+    let reordered = serde_json::json!({
+        "file_debug_info": value.remove("file_debug_info").unwrap(),
+        "files": value.remove("files").unwrap(),
+        "nodes": value.remove("nodes").unwrap(),
+        "edges": value.remove("edges").unwrap(),
+    });
+    let streamed =
+        StackGraph::read_from_reader(reordered.to_string().as_bytes()).expect("Cannot read graph");
+    assert_eq!(file_debug_info(&streamed), expected);
+
+    // graphs without file debug info serialize as before
+    let json = serde_json::to_value(&test_graphs::simple::new().to_serializable()).unwrap();
+    assert!(json.get("file_debug_info").is_none());
+}
+
+#[test]
+fn can_serialize_file_languages() {
+    let mut graph: StackGraph = test_graphs::simple::new();
+    let file = graph.get_file("test.py").unwrap();
+    graph.set_file_language(file, "python");
+
+    let json = serde_json::to_value(&graph.to_serializable()).unwrap();
+    assert_eq!(
+        json["file_languages"],
+        serde_json::json!([{ "file": "test.py", "language": "python" }])
+    );
+
+    let mut loaded = StackGraph::new();
+    serde_json::from_value::<serde::StackGraph>(json.clone())
+        .expect("Cannot deserialize graph")
+        .load_into(&mut loaded)
+        .expect("Cannot load graph");
+    let file = loaded.get_file("test.py").unwrap();
+    assert_eq!(loaded.file_language(file), Some("python"));
+
+    let streamed =
+        StackGraph::read_from_reader(json.to_string().as_bytes()).expect("Cannot read graph");
+    let file = streamed.get_file("test.py").unwrap();
+    assert_eq!(streamed.file_language(file), Some("python"));
+
+    // graphs without file languages serialize as before
+    let json = serde_json::to_value(&test_graphs::simple::new().to_serializable()).unwrap();
+    assert!(json.get("file_languages").is_none());
+}
+
+#[test]
+fn can_round_trip_graph_through_binary() {
+    use crate::test_graphs::CreateStackGraph;
+
+    let mut graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let file = graph.get_file("main.py").expect("Missing file");
+    let node = graph.nodes_for_file(file).next().expect("Missing node");
+    let root = graph.root_node();
+    graph.edge(root, node);
+    let key = graph.add_string("key");
+    let value = graph.add_string("value");
+    graph.node_debug_info_mut(node).add(key, value);
+    graph.edge_debug_info_mut(root, node).add(key, value);
+    graph.source_info_mut(node).syntax_type = graph.add_string("syntax").into();
+
+    let mut binary = Vec::new();
+    graph.write_binary(&mut binary).expect("Cannot write graph");
+    let read = StackGraph::read_binary(binary.as_slice()).expect("Cannot read graph");
+    assert_eq!(graph.to_serializable(), read.to_serializable());
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn can_round_trip_database_through_binary() {
+    use stack_graphs::stitching::DatabaseCandidates;
+
+    fn resolve_all(
+        graph: &StackGraph,
+        partials: &mut PartialPaths,
+        db: &mut Database,
+    ) -> Vec<String> {
+        let mut results = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut DatabaseCandidates::new(graph, partials, db),
+            graph.iter_references(),
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| results.push(path.display(graph, partials).to_string()),
+        )
+        .expect("should never be cancelled");
+        results.sort();
+        results
+    }
+
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("Expect path finding to work");
+    }
+    let mut graph_binary = Vec::new();
+    graph
+        .write_binary(&mut graph_binary)
+        .expect("Cannot write graph");
+    let mut db_binary = Vec::new();
+    db.write_binary(&graph, &mut partials, &mut db_binary)
+        .expect("Cannot write database");
+
+    let mut loaded_graph =
+        StackGraph::read_binary(graph_binary.as_slice()).expect("Cannot read graph");
+    let mut loaded_partials = PartialPaths::new();
+    let mut loaded_db = Database::new();
+    loaded_db
+        .load_from_binary(
+            db_binary.as_slice(),
+            &mut loaded_graph,
+            &mut loaded_partials,
+        )
+        .expect("Cannot read database");
+
+    assert_eq!(
+        db.to_serializable(&graph, &mut partials),
+        loaded_db.to_serializable(&loaded_graph, &mut loaded_partials)
+    );
+    let expected = resolve_all(&graph, &mut partials, &mut db);
+    assert!(!expected.is_empty());
+    assert_eq!(
+        expected,
+        resolve_all(&loaded_graph, &mut loaded_partials, &mut loaded_db)
+    );
+
+    // the version directly follows the four magic bytes
+    db_binary[4] += 1;
+    assert!(matches!(
+        Database::new().load_from_binary(
+            db_binary.as_slice(),
+            &mut loaded_graph,
+            &mut loaded_partials
+        ),
+        Err(serde::BinaryError::UnsupportedVersion(_))
+    ));
+    assert!(matches!(
+        Database::new().load_from_binary(
+            graph_binary.as_slice(),
+            &mut loaded_graph,
+            &mut loaded_partials
+        ),
+        Err(serde::BinaryError::InvalidHeader)
+    ));
+}
+
+#[cfg(feature = "bincode")]
+#[test]
+fn cannot_read_binary_graph_with_unknown_version() {
+    let graph: StackGraph = test_graphs::simple::new();
+    let mut binary = Vec::new();
+    graph.write_binary(&mut binary).expect("Cannot write graph");
+
+    // the version directly follows the four magic bytes
+    binary[4] += 1;
+    assert!(matches!(
+        StackGraph::read_binary(binary.as_slice()),
+        Err(serde::BinaryError::UnsupportedVersion(_))
+    ));
+    assert!(matches!(
+        StackGraph::read_binary(&b"{}"[..]),
+        Err(serde::BinaryError::InvalidHeader)
+    ));
+}
@@ -5,11 +5,38 @@
 // Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
 // ------------------------------------------------------------------------------------------------
 
+use std::cell::Cell;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
 use itertools::Itertools;
+use stack_graphs::arena::Handle;
+use stack_graphs::graph::Degree;
+use stack_graphs::graph::File;
+use stack_graphs::graph::Node;
+use stack_graphs::graph::NodeID;
 use stack_graphs::graph::StackGraph;
+use stack_graphs::partial::PartialPath;
 use stack_graphs::partial::PartialPaths;
 use stack_graphs::stitching::Database;
+use stack_graphs::stitching::DatabaseCandidates;
+use stack_graphs::stitching::DefinitionsError;
+use stack_graphs::stitching::ForwardCandidates;
+use stack_graphs::stitching::ForwardPartialPathStitcher;
+use stack_graphs::stitching::GraphEdgeCandidates;
+use stack_graphs::stitching::PartialPathIterator;
+use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::stitching::StitchingError;
+use stack_graphs::CancellationError;
+use stack_graphs::CancellationFlag;
+use stack_graphs::NoCancellation;
+use stack_graphs::TimeoutCancellation;
 
+use crate::test_graphs;
 use crate::util::create_partial_path_and_edges;
 use crate::util::create_pop_symbol_node;
 use crate::util::create_push_symbol_node;
@@ -102,3 +129,994 @@ fn find_candidates_for_shorter_symbol_stack_without_variable() {
     let results = test_foo_bar_root_candidate_paths(&["foo"], false);
     assert_eq!(0, results);
 }
+
+struct CancelAfterChecks(Cell<usize>);
+
+impl CancellationFlag for CancelAfterChecks {
+    fn check(&self, at: &'static str) -> Result<(), CancellationError> {
+        let remaining = self.0.get();
+        if remaining == 0 {
+            return Err(CancellationError(at));
+        }
+        self.0.set(remaining - 1);
+        Ok(())
+    }
+}
+
+fn find_complete_paths(
+    graph: &StackGraph,
+    partials: &mut PartialPaths,
+    cancellation_flag: &dyn CancellationFlag,
+) -> Result<BTreeSet<String>, StitchingError<CancellationError>> {
+    let mut results = BTreeSet::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference());
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(graph, partials, None),
+        references,
+        StitcherConfig::default(),
+        cancellation_flag,
+        |graph, partials, path| {
+            results.insert(path.display(graph, partials).to_string());
+        },
+    )?;
+    Ok(results)
+}
+
+#[test]
+fn can_cancel_finding_complete_paths() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    let expected = find_complete_paths(&graph, &mut PartialPaths::new(), &NoCancellation)
+        .expect("should never be cancelled");
+
+    let mut partials = PartialPaths::new();
+    let cancellation_flag = CancelAfterChecks(Cell::new(3));
+    assert!(find_complete_paths(&graph, &mut partials, &cancellation_flag).is_err());
+
+    // the partial paths arena can be reused after cancellation
+    let actual = find_complete_paths(&graph, &mut partials, &NoCancellation)
+        .expect("should never be cancelled");
+    assert_eq!(expected, actual);
+}
+
+#[test]
+fn can_cancel_after_timeout() {
+    let graph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+
+    let cancellation_flag = TimeoutCancellation::new(Duration::from_secs(3600));
+    assert!(find_complete_paths(&graph, &mut partials, &cancellation_flag).is_ok());
+
+    let cancellation_flag = TimeoutCancellation::new(Duration::ZERO).with_granularity(1);
+    assert!(find_complete_paths(&graph, &mut partials, &cancellation_flag).is_err());
+
+    // the clock is only read on every n-th check, but an expired deadline is remembered
+    let cancellation_flag =
+        TimeoutCancellation::with_deadline(Instant::now() + Duration::from_millis(10))
+            .with_granularity(100);
+    assert!(cancellation_flag.check("first").is_ok());
+    std::thread::sleep(Duration::from_millis(20));
+    for _ in 1..100 {
+        assert!(cancellation_flag.check("skipped").is_ok());
+    }
+    assert!(cancellation_flag.check("clock").is_err());
+    assert!(cancellation_flag.check("expired").is_err());
+}
+
+#[test]
+fn can_observe_stitching_progress() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|handle| graph[*handle].is_reference());
+    let mut visited = 0;
+    let mut observed = Vec::new();
+    let stats = ForwardPartialPathStitcher::find_all_complete_partial_paths_with_observer(
+        &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+        references,
+        StitcherConfig::default(),
+        &NoCancellation,
+        |_, _, _| visited += 1,
+        |progress| observed.push(*progress),
+    )
+    .expect("should never be cancelled");
+
+    assert!(!observed.is_empty());
+    assert!(observed.windows(2).all(|w| w[0].phases < w[1].phases
+        && w[0].extended_paths <= w[1].extended_paths
+        && w[0].completed_paths <= w[1].completed_paths));
+    let last = observed.last().unwrap();
+    assert_eq!(last.phases, observed.len());
+    assert_eq!(last.queue_length, 0);
+    assert_eq!(last.completed_paths, visited);
+    assert!(last.extended_paths >= visited);
+    assert!(last.created_paths >= last.extended_paths);
+    assert!(last.max_queue_length >= observed.iter().map(|p| p.queue_length).max().unwrap());
+    assert_eq!(&stats.progress, last);
+}
+
+#[test]
+fn stitching_stats_are_reproducible() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut find_stats = || {
+        let references = graph
+            .iter_nodes()
+            .filter(|handle| graph[*handle].is_reference());
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            references,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |_, _, _| {},
+        )
+        .expect("should never be cancelled")
+        .progress
+    };
+    let progress = find_stats();
+    assert!(progress.completed_paths > 0);
+    assert!(progress.max_queue_length > 0);
+    assert_eq!(progress, find_stats());
+}
+
+#[test]
+fn can_iterate_complete_paths_lazily() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut expected = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+        graph.iter_nodes(),
+        StitcherConfig::default(),
+        &NoCancellation,
+        |graph, partials, path| {
+            expected.insert(path.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+
+    let mut candidates = GraphEdgeCandidates::new(&graph, &mut partials, None);
+    let mut iter = PartialPathIterator::new(
+        &mut candidates,
+        graph.iter_nodes(),
+        StitcherConfig::default(),
+        &NoCancellation,
+    );
+    let mut actual = BTreeSet::new();
+    while let Some(path) = iter.next() {
+        let (graph, partials, _) = iter.candidates().get_graph_partials_and_db();
+        actual.insert(path.display(graph, partials).to_string());
+    }
+    assert!(iter.error().is_none());
+    assert_eq!(expected, actual);
+
+    let mut candidates = GraphEdgeCandidates::new(&graph, &mut partials, None);
+    let first = PartialPathIterator::new(
+        &mut candidates,
+        graph.iter_nodes(),
+        StitcherConfig::default(),
+        &NoCancellation,
+    )
+    .take(1)
+    .count();
+    assert_eq!(first, 1);
+
+    let cancellation_flag = TimeoutCancellation::new(Duration::ZERO).with_granularity(1);
+    let mut candidates = GraphEdgeCandidates::new(&graph, &mut partials, None);
+    let mut iter = PartialPathIterator::new(
+        &mut candidates,
+        graph.iter_nodes(),
+        StitcherConfig::default(),
+        &cancellation_flag,
+    );
+    assert!(iter.next().is_none());
+    assert!(iter.error().is_some());
+}
+
+#[test]
+fn can_limit_path_length() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut find_path_lengths = |config: StitcherConfig| {
+        let references = graph
+            .iter_nodes()
+            .filter(|handle| graph[*handle].is_reference());
+        let mut lengths = Vec::new();
+        let stats = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            references,
+            config,
+            &NoCancellation,
+            |_, _, path| lengths.push(path.edges.len()),
+        )
+        .expect("should never be cancelled");
+        (lengths, stats.pruned_paths)
+    };
+
+    let (lengths, pruned_paths) = find_path_lengths(StitcherConfig::default());
+    assert_eq!(pruned_paths, 0);
+    let max_length = *lengths.iter().max().unwrap();
+
+    let config = StitcherConfig::default().with_max_path_length(Some(max_length));
+    let (bounded_lengths, _) = find_path_lengths(config);
+    assert_eq!(bounded_lengths.len(), lengths.len());
+
+    let config = StitcherConfig::default().with_max_path_length(Some(max_length - 1));
+    let (bounded_lengths, pruned_paths) = find_path_lengths(config);
+    assert!(bounded_lengths.len() < lengths.len());
+    assert!(bounded_lengths.iter().all(|length| *length < max_length));
+    assert!(pruned_paths > 0);
+}
+
+#[test]
+fn can_limit_symbol_stack_depth() {
+    use crate::test_graphs::CreateStackGraph;
+
+    // `x` resolves through a chain that pushes `a`, `b`, and `c` on top of it before popping them
+    // again, so the symbol stack is four symbols deep at its deepest
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let mut prev = graph.reference(file, 0, x);
+    let mut local_id = 1;
+    let symbols = ["a", "b", "c"]
+        .iter()
+        .map(|s| graph.symbol(s))
+        .collect::<Vec<_>>();
+    for symbol in &symbols {
+        let node = graph.push_symbol(file, local_id, *symbol);
+        graph.edge(prev, node);
+        prev = node;
+        local_id += 1;
+    }
+    for symbol in symbols.iter().rev() {
+        let node = graph.pop_symbol(file, local_id, *symbol);
+        graph.edge(prev, node);
+        prev = node;
+        local_id += 1;
+    }
+    let definition = graph.definition(file, local_id, x);
+    graph.edge(prev, definition);
+
+    let mut partials = PartialPaths::new();
+    let mut find_paths = |config: StitcherConfig| {
+        let mut count = 0;
+        let stats = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            graph.iter_references(),
+            config,
+            &NoCancellation,
+            |_, _, _| count += 1,
+        )
+        .expect("should never be cancelled");
+        (count, stats.pruned_paths)
+    };
+
+    assert_eq!(find_paths(StitcherConfig::default()), (1, 0));
+    assert_eq!(
+        find_paths(StitcherConfig::default().with_max_symbol_stack_depth(Some(4))),
+        (1, 0)
+    );
+    assert_eq!(
+        find_paths(StitcherConfig::default().with_max_symbol_stack_depth(Some(3))),
+        (0, 1)
+    );
+}
+
+#[test]
+fn can_find_only_shortest_paths() {
+    use crate::test_graphs::CreateStackGraph;
+
+    // `x` resolves to `x1` via a short and a long path, and to `x2` via a long path only
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let r#ref = graph.reference(file, 0, x);
+    let s1 = graph.internal_scope(file, 1);
+    let s2 = graph.internal_scope(file, 2);
+    let s3 = graph.internal_scope(file, 3);
+    let x1 = graph.definition(file, 4, x);
+    let x2 = graph.definition(file, 5, x);
+    graph.edge(r#ref, s1);
+    graph.edge(s1, x1);
+    graph.edge(s1, s2);
+    graph.edge(s2, s3);
+    graph.edge(s3, x1);
+    graph.edge(s3, x2);
+
+    let mut partials = PartialPaths::new();
+    let mut find_paths = |config: StitcherConfig| {
+        let mut paths = Vec::new();
+        let stats = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            vec![r#ref],
+            config.with_detect_similar_paths(false),
+            &NoCancellation,
+            |_, _, path| paths.push((path.end_node, path.edges.len())),
+        )
+        .expect("should never be cancelled");
+        paths.sort();
+        assert_eq!(stats.accepted_path_length.count(), paths.len());
+        paths
+    };
+
+    assert_eq!(
+        find_paths(StitcherConfig::default()),
+        vec![(x1, 2), (x1, 4), (x2, 4)]
+    );
+    assert_eq!(
+        find_paths(StitcherConfig::default().with_shortest_paths_only(true)),
+        vec![(x1, 2), (x2, 4)]
+    );
+}
+
+#[test]
+fn can_deduplicate_complete_paths() {
+    use crate::test_graphs::CreateStackGraph;
+
+    // `x` resolves to `x1` via a short and a long path, and to `x2` via a long path only
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let r#ref = graph.reference(file, 0, x);
+    let s1 = graph.internal_scope(file, 1);
+    let s2 = graph.internal_scope(file, 2);
+    let s3 = graph.internal_scope(file, 3);
+    let x1 = graph.definition(file, 4, x);
+    let x2 = graph.definition(file, 5, x);
+    graph.edge(r#ref, s1);
+    graph.edge(s1, x1);
+    graph.edge(s1, s2);
+    graph.edge(s2, s3);
+    graph.edge(s3, x1);
+    graph.edge(s3, x2);
+    assert!(graph.set_edge_cost(s1, x1, 5));
+
+    let mut partials = PartialPaths::new();
+    let mut find_paths = |config: StitcherConfig| {
+        let mut paths = Vec::new();
+        let stats = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            vec![r#ref],
+            config.with_detect_similar_paths(false),
+            &NoCancellation,
+            |_, _, path| paths.push((path.end_node, path.edges.len())),
+        )
+        .expect("should never be cancelled");
+        assert_eq!(stats.accepted_path_length.count(), paths.len());
+        paths
+    };
+
+    let config = StitcherConfig::default().with_deduplicate_complete_paths(true);
+    let mut paths = find_paths(config);
+    paths.sort();
+    assert_eq!(paths, vec![(x1, 2), (x2, 4)]);
+    assert_eq!(
+        find_paths(config.with_sort_by_cost(true)),
+        vec![(x1, 4), (x2, 4)]
+    );
+}
+
+#[test]
+fn can_sort_complete_paths_by_cost() {
+    use crate::test_graphs::CreateStackGraph;
+
+    // `x` resolves to `x1` via a short but expensive path, and to `x2` via a longer, cheaper one
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let r#ref = graph.reference(file, 0, x);
+    let s1 = graph.internal_scope(file, 1);
+    let s2 = graph.internal_scope(file, 2);
+    let s3 = graph.internal_scope(file, 3);
+    let x1 = graph.definition(file, 4, x);
+    let x2 = graph.definition(file, 5, x);
+    graph.edge(r#ref, s1);
+    graph.edge(s1, x1);
+    graph.edge(r#ref, s2);
+    graph.edge(s2, s3);
+    graph.edge(s3, x2);
+    assert!(graph.set_edge_cost(r#ref, s1, 2));
+    assert!(graph.set_edge_cost(s1, x1, 3));
+    assert!(graph.set_edge_cost(s3, x2, 1));
+    assert!(!graph.set_edge_cost(s1, x2, 1));
+    assert_eq!(
+        graph
+            .outgoing_edges(s1)
+            .map(|edge| (edge.sink, edge.cost))
+            .collect::<Vec<_>>(),
+        vec![(x1, 3)]
+    );
+
+    let mut partials = PartialPaths::new();
+    let mut find_paths = |config: StitcherConfig| {
+        let mut paths = Vec::new();
+        ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            vec![r#ref],
+            config,
+            &NoCancellation,
+            |graph, partials, path| paths.push((path.end_node, path.cost(graph, partials))),
+        )
+        .expect("should never be cancelled");
+        paths
+    };
+
+    let mut unsorted = find_paths(StitcherConfig::default());
+    unsorted.sort();
+    assert_eq!(unsorted, vec![(x1, 5), (x2, 1)]);
+    assert_eq!(
+        find_paths(StitcherConfig::default().with_sort_by_cost(true)),
+        vec![(x2, 1), (x1, 5)]
+    );
+}
+
+#[test]
+fn can_find_definitions_for_reference() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let node = |file: &str, local_id: u32| {
+        let file = graph.get_file(file).expect("missing file");
+        graph
+            .node_for_id(NodeID::new_in_file(file, local_id))
+            .expect("missing node")
+    };
+
+    let definitions = graph
+        .definitions_for_reference(node("main.py", 10), &mut partials, &mut db)
+        .expect("should be a reference");
+    assert_eq!(definitions, vec![node("b.py", 8)]);
+
+    assert_eq!(
+        graph.definitions_for_reference(node("b.py", 8), &mut partials, &mut db),
+        Err(DefinitionsError::NotAReference(node("b.py", 8)))
+    );
+
+    let references = graph
+        .references_for_definition(node("b.py", 8), &mut partials, &mut db)
+        .expect("should be a definition");
+    assert_eq!(references, vec![node("main.py", 10)]);
+
+    assert_eq!(
+        graph.references_for_definition(node("main.py", 10), &mut partials, &mut db),
+        Err(DefinitionsError::NotADefinition(node("main.py", 10)))
+    );
+}
+
+#[test]
+fn can_check_whether_nodes_are_reachable() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let node = |file: &str, local_id: u32| {
+        let file = graph.get_file(file).expect("missing file");
+        graph
+            .node_for_id(NodeID::new_in_file(file, local_id))
+            .expect("missing node")
+    };
+
+    assert!(graph.is_reachable(node("main.py", 10), node("b.py", 8), &mut partials, &db));
+    assert!(graph.is_reachable(node("main.py", 17), node("a.py", 0), &mut partials, &db));
+    assert!(!graph.is_reachable(node("main.py", 17), node("b.py", 0), &mut partials, &db));
+    assert!(!graph.is_reachable(node("b.py", 8), node("main.py", 10), &mut partials, &db));
+}
+
+#[test]
+fn can_cancel_resolution_queries() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+    let main = graph.get_file("main.py").unwrap();
+    let b = graph.get_file("b.py").unwrap();
+    let reference = graph.node_for_id(NodeID::new_in_file(main, 10)).unwrap();
+    let definition = graph.node_for_id(NodeID::new_in_file(b, 8)).unwrap();
+    let cancelled = TimeoutCancellation::new(Duration::ZERO).with_granularity(1);
+
+    assert_eq!(
+        graph.definitions_for_reference_with_cancellation(
+            reference,
+            &mut partials,
+            &db,
+            &NoCancellation
+        ),
+        graph.definitions_for_reference(reference, &mut partials, &db)
+    );
+    assert!(matches!(
+        graph.definitions_for_reference_with_cancellation(
+            reference,
+            &mut partials,
+            &db,
+            &cancelled
+        ),
+        Err(DefinitionsError::Cancelled(_))
+    ));
+    assert!(matches!(
+        graph.references_for_definition_with_cancellation(
+            definition,
+            &mut partials,
+            &db,
+            &cancelled
+        ),
+        Err(DefinitionsError::Cancelled(_))
+    ));
+    assert_eq!(
+        graph.is_reachable_with_cancellation(
+            reference,
+            definition,
+            &mut partials,
+            &db,
+            &NoCancellation
+        ),
+        Ok(true)
+    );
+    assert!(graph
+        .is_reachable_with_cancellation(reference, definition, &mut partials, &db, &cancelled)
+        .is_err());
+    assert_eq!(
+        graph.validate_with_cancellation(&NoCancellation),
+        Ok(Ok(()))
+    );
+    assert!(graph.validate_with_cancellation(&cancelled).is_err());
+}
+
+#[test]
+fn stitching_stops_when_queue_exceeds_maximum_size() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|node| graph[*node].is_reference())
+        .collect::<Vec<_>>();
+    let mut find_paths = |config: StitcherConfig| {
+        let mut paths = 0;
+        let result = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            references.iter().copied(),
+            config,
+            &NoCancellation,
+            |_, _, _| paths += 1,
+        );
+        (result, paths)
+    };
+
+    let (result, all_paths) = find_paths(StitcherConfig::default());
+    let max_queue_length = result.unwrap().progress.max_queue_length;
+    assert!(max_queue_length > 1);
+
+    let (result, paths) =
+        find_paths(StitcherConfig::default().with_max_queue_size(Some(max_queue_length)));
+    assert!(result.is_ok());
+    assert_eq!(paths, all_paths);
+
+    let (result, paths) = find_paths(StitcherConfig::default().with_max_queue_size(Some(1)));
+    match result {
+        Err(StitchingError::ResourceExhausted(error)) => {
+            assert_eq!(error.max_queue_size, 1);
+            assert!(error.progress.queue_length > 1);
+            assert!(error.progress.phases > 0);
+            assert_eq!(error.progress.completed_paths, paths);
+        }
+        result => panic!("expected ResourceExhausted, got {:?}", result),
+    }
+    assert!(paths < all_paths);
+
+    let mut candidates = GraphEdgeCandidates::new(&graph, &mut partials, None);
+    let mut iterator: PartialPathIterator<_, _, _, _, CancellationError> = PartialPathIterator::new(
+        &mut candidates,
+        references.iter().copied(),
+        StitcherConfig::default().with_max_queue_size(Some(1)),
+        &NoCancellation,
+    );
+    assert!(iterator.by_ref().count() < all_paths);
+    assert!(matches!(
+        iterator.error(),
+        Some(StitchingError::ResourceExhausted(_))
+    ));
+}
+
+#[test]
+fn held_back_paths_are_reported_when_queue_exceeds_maximum_size() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let references = graph
+        .iter_nodes()
+        .filter(|node| graph[*node].is_reference())
+        .collect::<Vec<_>>();
+    let mut find_paths = |config: StitcherConfig| {
+        let mut paths = Vec::new();
+        let result = ForwardPartialPathStitcher::find_all_complete_partial_paths(
+            &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+            references.iter().copied(),
+            config,
+            &NoCancellation,
+            |_, _, path| paths.push((path.start_node, path.end_node)),
+        );
+        (result, paths)
+    };
+
+    // find a limit that is exceeded after some complete paths have been found
+    let (max_queue_size, mut immediate) = (1..)
+        .map(|max_queue_size| {
+            let config = StitcherConfig::default().with_max_queue_size(Some(max_queue_size));
+            let (result, paths) = find_paths(config);
+            assert!(
+                result.is_err(),
+                "no limit exceeded after some paths were found"
+            );
+            (max_queue_size, paths)
+        })
+        .find(|(_, paths)| !paths.is_empty())
+        .unwrap();
+
+    let (result, mut sorted) = find_paths(
+        StitcherConfig::default()
+            .with_max_queue_size(Some(max_queue_size))
+            .with_sort_by_cost(true),
+    );
+    let error = match result {
+        Err(StitchingError::ResourceExhausted(error)) => error,
+        result => panic!("expected ResourceExhausted, got {:?}", result),
+    };
+    assert_eq!(error.progress.completed_paths, sorted.len());
+    immediate.sort();
+    sorted.sort();
+    assert_eq!(immediate, sorted);
+}
+
+#[test]
+fn can_share_database_between_threads() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let main_file = graph.get_file("main.py").expect("missing file");
+    let reference = graph
+        .node_for_id(NodeID::new_in_file(main_file, 10))
+        .expect("missing node");
+    let b_file = graph.get_file("b.py").expect("missing file");
+    let definition = graph
+        .node_for_id(NodeID::new_in_file(b_file, 8))
+        .expect("missing node");
+
+    let graph = Arc::new(graph);
+    let db = Arc::new(db);
+    let threads = (0..4)
+        .map(|_| {
+            let graph = graph.clone();
+            let db = db.clone();
+            let mut partials = partials.clone();
+            std::thread::spawn(move || {
+                graph
+                    .definitions_for_reference(reference, &mut partials, &db)
+                    .expect("should be a reference")
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        assert_eq!(thread.join().expect("thread panicked"), vec![definition]);
+    }
+}
+
+#[test]
+fn can_find_candidates_with_prefix() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let precondition_symbols = |partials: &mut PartialPaths, path: &PartialPath| {
+        path.symbol_stack_precondition
+            .iter(partials)
+            .map(|symbol| symbol.symbol)
+            .collect::<Vec<_>>()
+    };
+    let root_paths = db
+        .partial_paths_for_node(StackGraph::root_node())
+        .cloned()
+        .collect::<Vec<_>>();
+    assert!(!root_paths.is_empty());
+    assert_eq!(
+        db.find_candidates_with_prefix(&graph, &mut partials, &[])
+            .len(),
+        root_paths.len()
+    );
+    for path in &root_paths {
+        let symbols = precondition_symbols(&mut partials, path);
+        for len in 1..=symbols.len() {
+            let prefix = &symbols[..len];
+            let found = db.find_candidates_with_prefix(&graph, &mut partials, prefix);
+            assert!(found.iter().any(|p| p.equals(&mut partials, path)));
+            for p in &found {
+                assert!(precondition_symbols(&mut partials, p).starts_with(prefix));
+            }
+        }
+    }
+
+    let symbol = precondition_symbols(&mut partials, &root_paths[0])[0];
+    assert!(db
+        .find_candidates_with_prefix(&graph, &mut partials, &[symbol; 16])
+        .is_empty());
+}
+
+#[test]
+fn can_iterate_partial_paths_for_node() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    for file in graph.iter_files() {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+
+    let mut total = 0;
+    for node in graph.iter_nodes() {
+        let paths = db.partial_paths_for_node(node).collect::<Vec<_>>();
+        assert!(paths.iter().all(|path| path.start_node == node));
+        total += paths.len();
+    }
+    assert_eq!(total, db.iter_partial_paths().count());
+    assert!(db
+        .partial_paths_for_node(StackGraph::root_node())
+        .next()
+        .is_some());
+}
+
+/// A candidate source that keeps the partial paths of each file in a separate shard, standing in
+/// for a remote index, and only loads the shards that stitching actually needs.
+struct ShardedCandidates<'a> {
+    graph: &'a StackGraph,
+    partials: &'a mut PartialPaths,
+    shards: HashMap<Handle<File>, Vec<PartialPath>>,
+    loaded: HashSet<Handle<File>>,
+    db: Database,
+}
+
+impl ShardedCandidates<'_> {
+    fn load_shard(&mut self, file: Handle<File>) {
+        if !self.loaded.insert(file) {
+            return;
+        }
+        for path in self.shards.get(&file).into_iter().flatten() {
+            self.db
+                .add_partial_path(self.graph, self.partials, path.clone());
+        }
+    }
+}
+
+impl ForwardCandidates<Handle<PartialPath>, PartialPath, Database, CancellationError>
+    for ShardedCandidates<'_>
+{
+    fn load_forward_candidates(
+        &mut self,
+        path: &PartialPath,
+        cancellation_flag: &dyn CancellationFlag,
+    ) -> Result<(), CancellationError> {
+        cancellation_flag.check("loading shards")?;
+        match self.graph[path.end_node].file() {
+            Some(file) => self.load_shard(file),
+            // paths from the root node can be in any shard
+            None => {
+                let files = self.shards.keys().copied().collect::<Vec<_>>();
+                for file in files {
+                    self.load_shard(file);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn get_forward_candidates<R>(&mut self, path: &PartialPath, result: &mut R)
+    where
+        R: std::iter::Extend<Handle<PartialPath>>,
+    {
+        self.db
+            .find_candidate_partial_paths(self.graph, self.partials, path, result);
+    }
+
+    fn get_joining_candidate_degree(&self, path: &PartialPath) -> Degree {
+        self.db.get_incoming_path_degree(path.end_node)
+    }
+
+    fn get_graph_partials_and_db(&mut self) -> (&StackGraph, &mut PartialPaths, &Database) {
+        (self.graph, self.partials, &self.db)
+    }
+}
+
+#[test]
+fn can_stitch_with_custom_candidates() {
+    let graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    let mut shards = HashMap::new();
+    for file in graph.iter_files() {
+        let mut shard = Vec::new();
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |graph, partials, path| {
+                db.add_partial_path(graph, partials, path.clone());
+                shard.push(path.clone());
+            },
+        )
+        .expect("should never be cancelled");
+        shards.insert(file, shard);
+    }
+
+    let mut expected = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut DatabaseCandidates::new(&graph, &mut partials, &db),
+        graph.iter_references(),
+        StitcherConfig::default(),
+        &NoCancellation,
+        |graph, partials, path| {
+            expected.insert(path.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+
+    let mut candidates = ShardedCandidates {
+        graph: &graph,
+        partials: &mut partials,
+        shards,
+        loaded: HashSet::new(),
+        db: Database::new(),
+    };
+    let mut actual = BTreeSet::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut candidates,
+        graph.iter_references(),
+        StitcherConfig::default(),
+        &NoCancellation,
+        |graph, partials, path| {
+            actual.insert(path.display(graph, partials).to_string());
+        },
+    )
+    .expect("should never be cancelled");
+    let loaded = candidates.loaded.len();
+    assert!(!expected.is_empty());
+    assert_eq!(expected, actual);
+    assert!(loaded > 0);
+}
+
+#[test]
+fn can_prune_partial_paths_of_removed_files() {
+    let mut graph: StackGraph = test_graphs::class_field_through_function_parameter::new();
+    let mut partials = PartialPaths::new();
+    let mut db = Database::new();
+    let files = graph.iter_files().collect::<Vec<_>>();
+    for file in files {
+        ForwardPartialPathStitcher::find_minimal_partial_path_set_in_file(
+            &graph,
+            &mut partials,
+            file,
+            StitcherConfig::default(),
+            &NoCancellation,
+            |g, ps, p| {
+                db.add_partial_path(g, ps, p.clone());
+            },
+        )
+        .expect("should never be cancelled");
+    }
+    let references = graph
+        .iter_nodes()
+        .filter(|node| graph[*node].is_reference())
+        .collect::<Vec<_>>();
+    let mut complete_paths = Vec::new();
+    ForwardPartialPathStitcher::find_all_complete_partial_paths(
+        &mut GraphEdgeCandidates::new(&graph, &mut partials, None),
+        references,
+        StitcherConfig::default(),
+        &NoCancellation,
+        |_, _, path| complete_paths.push(path.clone()),
+    )
+    .expect("should never be cancelled");
+    for path in complete_paths {
+        db.add_partial_path(&graph, &mut partials, path);
+    }
+
+    // Classify the paths by whether their endpoints or any of their edges are in `a.py`.
+    let a = graph.get_file("a.py").unwrap();
+    let in_a = |graph: &StackGraph, node: Handle<Node>| graph[node].id().file() == Some(a);
+    let mut expected_pruned = 0;
+    let mut passes_through = false;
+    for handle in db.iter_partial_paths().collect::<Vec<_>>() {
+        let path = &db[handle];
+        let endpoints_in_a = in_a(&graph, path.start_node) || in_a(&graph, path.end_node);
+        let edges_in_a = path
+            .edges
+            .iter_unordered(&partials)
+            .any(|edge| edge.source_node_id.file() == Some(a));
+        if endpoints_in_a || edges_in_a {
+            expected_pruned += 1;
+        }
+        passes_through |= !endpoints_in_a && edges_in_a;
+    }
+    assert!(passes_through);
+    let total = db.iter_partial_paths().count();
+
+    graph.remove_file(a);
+    assert_eq!(db.prune(&graph, &mut partials), expected_pruned);
+    assert_eq!(db.iter_partial_paths().count(), total - expected_pruned);
+    assert_eq!(db.prune(&graph, &mut partials), 0);
+}
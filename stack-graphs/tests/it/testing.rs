@@ -0,0 +1,36 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2023, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use stack_graphs::graph::StackGraph;
+
+#[test]
+fn can_resolve_references_to_helper_definitions() {
+    let mut graph = StackGraph::new();
+    let a = graph.get_or_create_file("a.py");
+    let b = graph.get_or_create_file("b.py");
+    let x1 = graph.add_definition(a, "x");
+    let x2 = graph.add_definition(b, "x");
+    let y = graph.add_definition(a, "y");
+    let ref_x = graph.add_reference(b, "x");
+    let ref_y = graph.add_reference(b, "y");
+    let ref_z = graph.add_reference(a, "z");
+    assert!(graph[x1].is_definition());
+    assert!(graph[ref_x].is_reference());
+    graph.assert_resolves(ref_x, &[x2, x1]);
+    graph.assert_resolves(ref_y, &[y]);
+    graph.assert_resolves(ref_z, &[]);
+}
+
+#[test]
+#[should_panic(expected = "expected [[a.py(0) definition x]]")]
+fn assert_resolves_fails_on_wrong_definitions() {
+    let mut graph = StackGraph::new();
+    let a = graph.get_or_create_file("a.py");
+    let x = graph.add_definition(a, "x");
+    let ref_y = graph.add_reference(a, "y");
+    graph.assert_resolves(ref_y, &[x]);
+}
@@ -157,6 +157,7 @@ pub(crate) fn create_partial_path_and_edges(
                 source: *prev,
                 sink: *next,
                 precedence: 0,
+                cost: 0,
             },
         )?;
         prev = next;
@@ -176,5 +177,6 @@ pub(crate) fn edge(source: Handle<Node>, sink: Handle<Node>, precedence: i32) ->
         source,
         sink,
         precedence,
+        cost: 0,
     }
 }
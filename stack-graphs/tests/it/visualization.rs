@@ -0,0 +1,68 @@
+// -*- coding: utf-8 -*-
+// ------------------------------------------------------------------------------------------------
+// Copyright © 2024, stack-graphs authors.
+// Licensed under either of Apache License, Version 2.0, or MIT license, at your option.
+// Please see the LICENSE-APACHE or LICENSE-MIT files in this distribution for license details.
+// ------------------------------------------------------------------------------------------------
+
+use serde_json::json;
+use stack_graphs::graph::StackGraph;
+
+use crate::test_graphs::CreateStackGraph;
+
+#[test]
+fn can_write_cytoscape_json() {
+    let mut graph = StackGraph::new();
+    let file = graph.file("test.py");
+    let x = graph.symbol("x");
+    let root = graph.root_node();
+    let definition = graph.definition(file, 1, x);
+    let scope = graph.internal_scope(file, 2);
+    graph.edge(root, definition);
+    graph.add_edge(scope, definition, 1);
+    graph.source_info_mut(definition).span = lsp_positions::Span {
+        start: lsp_positions::Position {
+            line: 1,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let mut output = Vec::new();
+    graph
+        .to_cytoscape_json(&mut output)
+        .expect("Cannot write graph");
+    let json: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+    let nodes = json["elements"]["nodes"].as_array().unwrap();
+    let data = |id: &str| {
+        nodes
+            .iter()
+            .map(|node| &node["data"])
+            .find(|data| data["id"] == id)
+            .unwrap_or_else(|| panic!("missing node {}", id))
+    };
+    assert_eq!(nodes.len(), 4);
+    assert_eq!(data("1"), &json!({ "id": "1", "kind": "root" }));
+    assert_eq!(data("test.py:1")["kind"], "pop_symbol");
+    assert_eq!(data("test.py:1")["symbol"], "x");
+    assert_eq!(data("test.py:1")["span"]["start"]["line"], 1);
+    assert_eq!(
+        data("test.py:2"),
+        &json!({ "id": "test.py:2", "kind": "scope" })
+    );
+
+    let edges = json["elements"]["edges"].as_array().unwrap();
+    assert_eq!(
+        edges.iter().map(|edge| &edge["data"]).collect::<Vec<_>>(),
+        vec![
+            &json!({ "id": "1->test.py:1", "source": "1", "target": "test.py:1", "precedence": 0 }),
+            &json!({
+                "id": "test.py:2->test.py:1",
+                "source": "test.py:2",
+                "target": "test.py:1",
+                "precedence": 1,
+            }),
+        ]
+    );
+}
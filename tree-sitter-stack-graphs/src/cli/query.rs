@@ -15,8 +15,10 @@ use clap::ValueHint;
 use stack_graphs::stitching::ForwardPartialPathStitcher;
 use stack_graphs::stitching::Stats as StitchingStats;
 use stack_graphs::stitching::StitcherConfig;
+use stack_graphs::stitching::StitchingError;
 use stack_graphs::storage::FileStatus;
 use stack_graphs::storage::SQLiteReader;
+use stack_graphs::storage::StorageError;
 use thiserror::Error;
 use tree_sitter_graph::parse_error::Excerpt;
 
@@ -317,6 +319,12 @@ impl From<crate::CancellationError> for QueryError {
     }
 }
 
+impl From<StitchingError<StorageError>> for QueryError {
+    fn from(value: StitchingError<StorageError>) -> Self {
+        Self::StorageError(value.into())
+    }
+}
+
 pub struct QueryResult {
     pub source: SourceSpan,
     pub targets: Vec<SourceSpan>,
@@ -418,10 +418,13 @@ impl TestArgs {
         cancellation_flag: &dyn CancellationFlag,
         cache: &mut HashMap<Language, stack_graphs::serde::Database>,
     ) -> anyhow::Result<()> {
-        let files = graph
+        graph
             .add_from_graph(&lc.builtins)
             .map_err(|h| anyhow!("Duplicate builtin file {}", &graph[h]))?;
-        let files = files.into_iter().collect::<HashSet<_>>();
+        let files = graph
+            .iter_files()
+            .filter(|file| lc.builtins.get_file(graph[*file].name()).is_some())
+            .collect::<HashSet<_>>();
         match cache.entry(lc.language) {
             Entry::Occupied(o) => {
                 o.get().load_into(graph, partials, db)?;
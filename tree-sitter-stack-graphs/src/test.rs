@@ -492,6 +492,7 @@ pub enum TestFailure {
         unexpected_symbols: Vec<String>,
     },
     Cancelled(stack_graphs::CancellationError),
+    ResourceExhausted(stack_graphs::stitching::ResourceExhausted),
 }
 
 impl std::fmt::Display for TestFailure {
@@ -612,6 +613,7 @@ impl std::fmt::Display for TestFailure {
                 Ok(())
             }
             Self::Cancelled(err) => write!(f, "{}", err),
+            Self::ResourceExhausted(err) => write!(f, "{}", err),
         }
     }
 }
@@ -744,6 +746,7 @@ impl Test {
                 })
             }
             AssertionError::Cancelled(err) => Err(TestFailure::Cancelled(err)),
+            AssertionError::ResourceExhausted(err) => Err(TestFailure::ResourceExhausted(err)),
         }
     }
 